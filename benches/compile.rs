@@ -0,0 +1,124 @@
+/*
+ * Compile generated programs of growing size with the stem-rs binary
+ * and check that the compilation time grows linearly with the number of lines, of items and of operands
+ *
+ * cargo bench --bench compile
+ */
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+const SIZES: [usize; 3] = [50_000, 100_000, 200_000];
+const ITEMS: [usize; 3] = [10_000, 20_000, 40_000];
+const TERMS: [usize; 3] = [50_000, 100_000, 200_000];
+const RUNS: u32 = 5;
+
+/*
+ * a program of `lines` statements mixing arithmetic, variables and structs
+ * every other line declares a variable of its own, and they read the first one and the ones declared halfway up
+ */
+fn generate_program(lines: usize) -> String {
+    let mut program = "struct Point { x: i64, y: i64 }\nlet v0 = 1;\n".to_string();
+    for i in 0..lines {
+        program += &match i % 4 {
+            0 => format!("let v{} = (v0 + {}) * 3 - v{} / 2;\n", i, i % 7, i / 8 * 4),
+            1 => format!("let p{} = Point {{ x: v0, y: v{} }};\n", i, i - 1),
+            2 => format!("p{0}.x = p{0}.y + v{1};\n", i - 1, i / 8 * 4),
+            _ => format!("put v{} + p{}.x;\n", i - 3, i - 2),
+        };
+    }
+    program
+}
+
+/*
+ * a program of `items` structs, globals and functions, each one with its own name,
+ * then a variable and a call for each function
+ */
+fn generate_items(items: usize) -> String {
+    let mut program = "".to_string();
+    for i in 0..items {
+        program += &format!("struct S{i} {{ x: i64, y: i64 }}\n");
+        program += &format!("static g{i}: i64 = {i};\n");
+        program += &format!("fn f{i}(p: *S{i}) -> i64 {{ return p.x + g{i}; }}\n");
+    }
+    for i in 0..items {
+        program += &format!("let s{i} = S{i} {{ x: {i}, y: 0 }};\nput f{i}(&s{i});\n");
+    }
+    program
+}
+
+/*
+ * a program adding `terms` operands in a single expression, as deep as it is long
+ */
+fn generate_deep(terms: usize) -> String {
+    let operands: Vec<String> = (0..terms).map(|i| if i % 2 == 0 { "x".to_string() } else { (i % 10).to_string() }).collect();
+    format!("let x = 1;\nput {};\n", operands.join(" + "))
+}
+
+/*
+ * write a generated program in a directory of its own, name tells the programs apart
+ */
+fn write_program(name: &str, program: String) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("stem-bench-{}-{}", std::process::id(), name));
+    fs::create_dir_all(&dir).expect("Can't create the benchmark directory");
+    fs::write(dir.join("bench.stm"), program).expect("Can't write the benchmark program");
+    dir
+}
+
+/*
+ * time one compilation of the program written in dir
+ */
+fn time_compilation(dir: &Path) -> Duration {
+    let start = Instant::now();
+    let status = Command::new(env!("CARGO_BIN_EXE_stem-rs"))
+        .arg("bench.stm")
+        .current_dir(dir)
+        .stdout(Stdio::null())
+        .status()
+        .expect("Can't run stem-rs");
+    let elapsed = start.elapsed();
+    assert!(status.success(), "stem-rs failed on {}", dir.display());
+    elapsed
+}
+
+/*
+ * time the programs generate makes of each size, each twice the previous one, and check that the time grows linearly
+ * the sizes take turns RUNS times and each one keeps its best time, a busy moment of the machine doesn't fall on a
+ * single size
+ */
+fn bench(unit: &str, sizes: [usize; 3], generate: fn(usize) -> String) {
+    let dirs: Vec<PathBuf> = sizes.iter().map(|size| write_program(&format!("{size}-{unit}"), generate(*size))).collect();
+    let mut times = vec![Duration::MAX; sizes.len()];
+    for _ in 0..RUNS {
+        for (time, dir) in times.iter_mut().zip(&dirs) {
+            *time = (*time).min(time_compilation(dir));
+        }
+    }
+    for (size, (time, dir)) in sizes.iter().zip(times.iter().zip(&dirs)) {
+        fs::remove_dir_all(dir).expect("Can't remove the benchmark directory");
+        println!(
+            "{:>7} {}: {:>8.1} ms ({:.0} ns/{})",
+            size,
+            unit,
+            time.as_secs_f64() * 1e3,
+            time.as_nanos() as f64 / *size as f64,
+            unit.trim_end_matches('s')
+        );
+    }
+
+    // twice as much should take about twice as long, a quadratic compiler would take 4 times longer
+    for i in 1..sizes.len() {
+        let ratio = times[i].as_secs_f64() / times[i - 1].as_secs_f64();
+        println!("{} {unit} / {} {unit}: {:.2}x", sizes[i], sizes[i - 1], ratio);
+        assert!(ratio < 2.5, "compilation time grows faster than linearly with the {} ({:.2}x)", unit, ratio);
+    }
+}
+
+fn main() {
+    bench("lines", SIZES, generate_program);
+    // duplicate names are looked for among all the items
+    bench("items", ITEMS, generate_items);
+    // every pass recurses over the operands
+    bench("terms", TERMS, generate_deep);
+}
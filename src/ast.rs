@@ -0,0 +1,355 @@
+/*
+ * The tree built by the parser: expressions, statements and the declarations of a program
+ * every node carries the span of the source it was parsed from
+ */
+use crate::source::Span;
+
+#[derive(Copy, PartialEq, Clone, Debug)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    And,
+    Or,
+    Xor,
+    Shl,
+    Shr,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl BinaryOp {
+    /*
+     * comparisons give 1 or 0
+     */
+    pub fn is_comparison(&self) -> bool {
+        matches!(self, Self::Eq | Self::Ne | Self::Lt | Self::Le | Self::Gt | Self::Ge)
+    }
+}
+
+impl core::fmt::Display for BinaryOp {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match *self {
+            Self::Add => write!(f, "+"),
+            Self::Sub => write!(f, "-"),
+            Self::Mul => write!(f, "*"),
+            Self::Div => write!(f, "/"),
+            Self::Rem => write!(f, "%"),
+            Self::And => write!(f, "&"),
+            Self::Or => write!(f, "|"),
+            Self::Xor => write!(f, "^"),
+            Self::Shl => write!(f, "<<"),
+            Self::Shr => write!(f, ">>"),
+            Self::Eq => write!(f, "=="),
+            Self::Ne => write!(f, "!="),
+            Self::Lt => write!(f, "<"),
+            Self::Le => write!(f, "<="),
+            Self::Gt => write!(f, ">"),
+            Self::Ge => write!(f, ">="),
+        }
+    }
+}
+
+#[derive(Copy, PartialEq, Clone, Debug)]
+pub enum UnaryOp {
+    Neg,
+    Not,
+}
+
+impl core::fmt::Display for UnaryOp {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match *self {
+            Self::Neg => write!(f, "-"),
+            Self::Not => write!(f, "~"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ExprKind {
+    // `10`, `0xff_ff`, `300u16`, the type comes from the suffix
+    Integer {
+        value: u64,
+        type_: Option<Type>,
+    },
+    // `"text"`, a `*u8` to its bytes followed by a 0, like a C string, the escapes are decoded
+    String(String),
+    Variable(String),
+    StructLiteral {
+        name: String,
+        fields: Vec<(String, Expr)>,
+    },
+    Field {
+        base: Box<Expr>,
+        field: String,
+    },
+    Unary {
+        op: UnaryOp,
+        operand: Box<Expr>,
+    },
+    // op_span is the operator, the panics of the operation point at it
+    Binary {
+        op: BinaryOp,
+        op_span: Span,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+    },
+    Assign {
+        target: Box<Expr>,
+        value: Box<Expr>,
+    },
+    // `a += b`, op is the `+`
+    CompoundAssign {
+        op: BinaryOp,
+        op_span: Span,
+        target: Box<Expr>,
+        value: Box<Expr>,
+    },
+    // `a++` and `a--`, op is Add or Sub, the value is the one before the update
+    Postfix {
+        op: BinaryOp,
+        op_span: Span,
+        target: Box<Expr>,
+    },
+    Call {
+        name: String,
+        args: Vec<Expr>,
+    },
+    // `&place`
+    AddressOf(Box<Expr>),
+    // `*pointer`
+    Deref(Box<Expr>),
+}
+
+#[derive(Debug, Clone)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+impl Expr {
+    pub fn new(kind: ExprKind, span: Span) -> Expr {
+        Expr { kind, span }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum StmtKind {
+    Expr(Expr),
+    Put(Expr),
+    // `let name: type_ = value;`, the type is the one of the value when omitted
+    Let {
+        name: String,
+        type_: Option<Type>,
+        value: Expr,
+    },
+    // `{ ... }`, a scope for the variables declared in it
+    Block(Vec<Stmt>),
+    // then is a Block, otherwise is a Block or an If
+    If {
+        condition: Expr,
+        then: Box<Stmt>,
+        otherwise: Option<Box<Stmt>>,
+    },
+    While {
+        condition: Expr,
+        body: Box<Stmt>,
+    },
+    Return(Option<Expr>),
+    // `assert(condition, "message");`, the message is optional
+    Assert {
+        condition: Expr,
+        message: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub span: Span,
+}
+
+/*
+ * Type of a variable, of a struct field or of an expression
+ */
+#[derive(PartialEq, Clone, Debug)]
+pub enum Type {
+    I8,
+    I16,
+    I32,
+    I64,
+    U8,
+    U16,
+    U32,
+    U64,
+    Struct(String),
+    // `*type`
+    Pointer(Box<Type>),
+}
+
+impl Type {
+    /*
+     * every name that is not a primitive type is a struct name
+     */
+    pub fn from_name(name: &str) -> Type {
+        match name {
+            "i8" => Type::I8,
+            "i16" => Type::I16,
+            "i32" => Type::I32,
+            "i64" => Type::I64,
+            "u8" => Type::U8,
+            "u16" => Type::U16,
+            "u32" => Type::U32,
+            "u64" => Type::U64,
+            _ => Type::Struct(name.to_string()),
+        }
+    }
+
+    pub fn is_struct(&self) -> bool {
+        matches!(self, Type::Struct(_))
+    }
+
+    pub fn is_pointer(&self) -> bool {
+        matches!(self, Type::Pointer(_))
+    }
+
+    /*
+     * the type a pointer points to
+     */
+    pub fn pointee(&self) -> Option<&Type> {
+        match self {
+            Type::Pointer(pointee) => Some(pointee),
+            _ => None,
+        }
+    }
+
+    pub fn is_signed(&self) -> bool {
+        matches!(self, Type::I8 | Type::I16 | Type::I32 | Type::I64)
+    }
+
+    /*
+     * largest integer literal of the type, a negated literal goes one further for signed types (`-128i8`)
+     */
+    pub fn max_literal(&self, negated: bool) -> u64 {
+        let bits = match self {
+            Type::I8 | Type::U8 => 8,
+            Type::I16 | Type::U16 => 16,
+            Type::I32 | Type::U32 => 32,
+            Type::I64 | Type::U64 => 64,
+            Type::Struct(_) | Type::Pointer(_) => unreachable!("only integer types have literals"),
+        };
+        if self.is_signed() {
+            (1u64 << (bits - 1)) - 1 + negated as u64
+        } else {
+            u64::MAX >> (64 - bits)
+        }
+    }
+}
+
+impl core::fmt::Display for Type {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            Self::I8 => write!(f, "i8"),
+            Self::I16 => write!(f, "i16"),
+            Self::I32 => write!(f, "i32"),
+            Self::I64 => write!(f, "i64"),
+            Self::U8 => write!(f, "u8"),
+            Self::U16 => write!(f, "u16"),
+            Self::U32 => write!(f, "u32"),
+            Self::U64 => write!(f, "u64"),
+            Self::Struct(name) => write!(f, "{}", name),
+            Self::Pointer(pointee) => write!(f, "*{}", pointee),
+        }
+    }
+}
+
+/*
+ * `struct Name { field: type, ... }` as written in the source
+ */
+#[derive(Debug, Clone)]
+pub struct StructDecl {
+    pub name: String,
+    pub fields: Vec<(String, Type, Span)>,
+    pub span: Span,
+}
+
+/*
+ * `fn name(param: type, ...) -> returns { ... }`, body is a Block
+ * `pub extern fn` also exports it to C, export is then its name as written, the symbol C calls
+ */
+#[derive(Debug, Clone)]
+pub struct FnDecl {
+    pub name: String,
+    pub params: Vec<(String, Type, Span)>,
+    pub returns: Option<Type>,
+    pub body: Stmt,
+    pub export: Option<String>,
+    pub span: Span,
+}
+
+/*
+ * `extern fn name(param: type, ...) -> returns;`, a function of C called with its own name
+ * variadic when the parameters end with `...`, the arguments after them can have any number type
+ */
+#[derive(Debug, Clone)]
+pub struct ExternDecl {
+    pub name: String,
+    pub params: Vec<(String, Type, Span)>,
+    pub variadic: bool,
+    pub returns: Option<Type>,
+    pub span: Span,
+}
+
+/*
+ * `static name: type = value;` when mutable, `const name: type = value;` otherwise
+ * a static without value is zeroed, a const always has one
+ */
+#[derive(Debug, Clone)]
+pub struct GlobalDecl {
+    pub name: String,
+    pub type_: Option<Type>,
+    pub value: Option<Expr>,
+    pub mutable: bool,
+    pub span: Span,
+}
+
+/*
+ * `test "name" { ... }`, body is a Block, only built by `stem-rs test`
+ */
+#[derive(Debug, Clone)]
+pub struct TestDecl {
+    pub name: String,
+    pub body: Stmt,
+    pub span: Span,
+}
+
+/*
+ * `import "path";` or `mod name;`, which is `import "name.stm";`
+ * the items of the file are then `name::item`, name is the file name without `.stm`
+ */
+#[derive(Debug, Clone)]
+pub struct ImportDecl {
+    pub path: String,
+    pub name: String,
+    pub span: Span,
+}
+
+/*
+ * The items declared in the program and its top-level statements
+ */
+#[derive(Debug, Clone)]
+pub struct Program {
+    pub structs: Vec<StructDecl>,
+    pub functions: Vec<FnDecl>,
+    pub externs: Vec<ExternDecl>,
+    pub globals: Vec<GlobalDecl>,
+    pub tests: Vec<TestDecl>,
+    pub imports: Vec<ImportDecl>,
+    pub statements: Vec<Stmt>,
+}
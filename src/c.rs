@@ -0,0 +1,481 @@
+/*
+ * The C backend of `--emit=c`: the program is lowered from its tree by lower.rs and its operations are written as
+ * a single file of portable C99, with the runtime of src/runtime/c, for the machines without a backend of their own
+ * and to test the backends against each other
+ * every value is an uint64_t, a pointer holds its address and the arithmetic only uses the operations that C defines
+ * for any value: the signed ones go through the helpers of the prelude and the overflows are checked by hand
+ * C leaves the order in which the operands are evaluated to the compiler, the lowering computes every value in a
+ * temporary first, in the order of the source, so the code is a statement per operation
+ * the C functions the program declares with `extern fn` are called with their C types
+ */
+use crate::ast::{BinaryOp, ExternDecl, FnDecl, Program, Type};
+use crate::lower::{self, Backend, Body, Lowering};
+use crate::source::SourceMap;
+use crate::{c_structs, c_type, check_tests, new_context, runtime, Context, Function, Linkage, Options};
+use std::collections::HashSet;
+
+pub const OUTPUT: &str = "c";
+
+/*
+ * the functions of the standard headers the runtime includes (stdio.h, stdlib.h, string.h and inttypes.h) in C99:
+ * a program calls them with the prototype of their header, declaring them again could conflict with it
+ */
+const HEADER_FUNCTIONS: &[&str] = &[
+    "remove", "rename", "tmpfile", "tmpnam", "fclose", "fflush", "fopen", "freopen", "setbuf", "setvbuf", "fprintf", "fscanf", "printf", "scanf",
+    "snprintf", "sprintf", "sscanf", "vfprintf", "vfscanf", "vprintf", "vscanf", "vsnprintf", "vsprintf", "vsscanf", "fgetc", "fgets", "fputc",
+    "fputs", "getc", "getchar", "gets", "putc", "putchar", "puts", "ungetc", "fread", "fwrite", "fgetpos", "fseek", "fsetpos", "ftell", "rewind",
+    "clearerr", "feof", "ferror", "perror", "atof", "atoi", "atol", "atoll", "strtod", "strtof", "strtold", "strtol", "strtoll", "strtoul",
+    "strtoull", "rand", "srand", "calloc", "free", "malloc", "realloc", "abort", "atexit", "exit", "_Exit", "getenv", "system", "bsearch", "qsort",
+    "abs", "labs", "llabs", "div", "ldiv", "lldiv", "mblen", "mbtowc", "wctomb", "mbstowcs", "wcstombs", "memcpy", "memmove", "strcpy", "strncpy",
+    "strcat", "strncat", "memcmp", "strcmp", "strcoll", "strncmp", "strxfrm", "memchr", "strchr", "strcspn", "strpbrk", "strrchr", "strspn",
+    "strstr", "strtok", "memset", "strerror", "strlen", "imaxabs", "imaxdiv", "strtoimax", "strtoumax", "wcstoimax", "wcstoumax",
+];
+
+/*
+ * State of the translation: the globals of the file and the temporaries of the function being written
+ */
+struct C {
+    // the definitions of the globals that aren't folded
+    globals: String,
+    // the number of temporaries of the function, and the ones of each statement being translated, declared at its top
+    temporaries: u32,
+    declared: Vec<Vec<String>>,
+}
+
+/*
+ * the name of the function or the global name in C, each part of the path after its length: `a::bc` is `stem_1a2bc`
+ * so no two paths get the same name, and none gets the name of a helper of the runtime, no digit follows their `stem_`
+ * the test i, named `test.i`, is `test_i`
+ */
+fn c_name(name: &str) -> String {
+    if let Some(i) = name.strip_prefix("test.") {
+        return format!("test_{i}");
+    }
+    let parts: Vec<String> = name.split("::").map(|part| format!("{}{part}", part.len())).collect();
+    return format!("stem_{}", parts.concat());
+}
+
+/*
+ * a string literal of C, the bytes that aren't printable are escaped in octal and `?` too, it could start a trigraph
+ */
+fn c_string(bytes: &[u8]) -> String {
+    let mut text = "\"".to_string();
+    for byte in bytes {
+        match byte {
+            b'"' | b'\\' | b'?' => text += &format!("\\{}", *byte as char),
+            0x20..=0x7e => text.push(*byte as char),
+            _ => text += &format!("\\{byte:03o}"),
+        }
+    }
+    text.push('"');
+    return text;
+}
+
+/*
+ * the names the C output gives to its own functions, variables and macros, a C function of the program can't have one:
+ * the runtime, the functions of the program and the tests, the locals and the temporaries, and the ones of main
+ */
+fn reserved(name: &str) -> bool {
+    let prefixes = ["stem_", "runtime_", "test_", "alloc_", "get_", "ALLOC_", "l_"];
+    if prefixes.iter().any(|prefix| name.starts_with(prefix)) || ["main", "frame", "frame_top", "test", "argc", "argv"].contains(&name) {
+        return true;
+    }
+    // `t3` or `l2_name`
+    let numbered = |rest: &str| rest.split('_').next().is_some_and(|digits| !digits.is_empty() && digits.bytes().all(|byte| byte.is_ascii_digit()));
+    return name.strip_prefix('t').is_some_and(|rest| !rest.is_empty() && rest.bytes().all(|byte| byte.is_ascii_digit()))
+        || name.strip_prefix('l').is_some_and(|rest| rest.contains('_') && numbered(rest));
+}
+
+/*
+ * the prototypes of the C functions the program declares that no header of the runtime declares, after the structs
+ * they point to
+ */
+fn externs_codegen(externs: &[ExternDecl], ctx: &Context) -> String {
+    let mut declared: HashSet<&str> = HashSet::new();
+    let mut decls: Vec<&ExternDecl> = vec![];
+    for decl in externs {
+        if reserved(&decl.name) {
+            eprintln!("ERROR:{}: `{}` is a name of the C output, a C function can't have it with --emit=c", ctx.sources.location(decl.span), decl.name);
+            std::process::exit(1);
+        }
+        if declared.insert(&decl.name) && !HEADER_FUNCTIONS.contains(&decl.name.as_str()) {
+            decls.push(decl);
+        }
+    }
+    let mut code = "".to_string();
+    for c_name in c_structs(decls.iter().flat_map(|decl| decl.params.iter().map(|(_, type_, _)| type_).chain(&decl.returns))) {
+        code += &format!("{c_name};\n");
+    }
+    for decl in decls {
+        let returns = decl.returns.as_ref().map_or("void".to_string(), c_type);
+        let mut params: Vec<String> = decl.params.iter().map(|(_, type_, _)| c_type(type_)).collect();
+        if params.is_empty() {
+            params.push("void".to_string());
+        }
+        if decl.variadic {
+            params.push("...".to_string());
+        }
+        code += &format!("{returns} {}({});\n", decl.name, params.join(", "));
+    }
+    return code;
+}
+
+/*
+ * value as an argument of type type_ of a C function, variadic when it is after its parameters: C promotes the
+ * integers narrower than an int to one
+ */
+fn c_argument(value: &str, type_: &Type, variadic: bool) -> String {
+    let c_type = match type_ {
+        Type::Pointer(_) => return format!("stem_pointer({value})"),
+        Type::I8 | Type::I16 | Type::I32 if variadic => "int".to_string(),
+        Type::U8 | Type::U16 | Type::U32 if variadic => "unsigned".to_string(),
+        type_ => c_type(type_),
+    };
+    if type_.is_signed() {
+        return format!("({c_type})stem_signed({value})");
+    }
+    return format!("({c_type}){value}");
+}
+
+impl Backend for C {
+    fn constant(&self, value: u64) -> String {
+        return format!("UINT64_C({value})");
+    }
+
+    fn string(&mut self, text: &str) -> String {
+        return format!("stem_address({})", c_string(text.as_bytes()));
+    }
+
+    fn frame_address(&self, offset: u32) -> String {
+        return format!("stem_address(frame_top - {offset})");
+    }
+
+    /*
+     * a global is an array of bytes in a union with an uint64_t, for its alignment, the bytes of its value are
+     * little endian and the machine has to be too
+     */
+    fn global(&mut self, name: &str, size: u32, _align: u32, bytes: Option<Vec<u8>>, location: &str) -> String {
+        let name = c_name(name);
+        self.globals += &format!("/* {location} */\nstatic union {{\n    unsigned char bytes[{size}];\n    uint64_t align;\n}} {name}");
+        if let Some(bytes) = bytes {
+            let bytes: Vec<String> = bytes.iter().map(|byte| byte.to_string()).collect();
+            self.globals += &format!(" = {{{{{}}}}}", bytes.join(", "));
+        }
+        self.globals += ";\n";
+        return format!("stem_address({name}.bytes)");
+    }
+
+    fn temporary(&mut self) -> String {
+        let temporary = format!("t{}", self.temporaries);
+        self.temporaries += 1;
+        self.declared.last_mut().expect("a temporary belongs to a statement").push(temporary.clone());
+        return temporary;
+    }
+
+    /*
+     * `l_name`, then `l2_name`, `l3_name`, ...
+     * the prefix keeps them apart from the names of C and of the runtime
+     */
+    fn local(&self, name: &str, i: u32) -> String {
+        if i == 1 {
+            return format!("l_{name}");
+        }
+        return format!("l{i}_{name}");
+    }
+
+    fn set(&mut self, destination: &str, value: &str) -> String {
+        return format!("{destination} = {value};\n");
+    }
+
+    fn operation(&mut self, destination: &str, op: BinaryOp, a: &str, b: &str, signed: bool) -> String {
+        let operator = match op {
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Rem => "%",
+            BinaryOp::And => "&",
+            BinaryOp::Or => "|",
+            BinaryOp::Xor => "^",
+            BinaryOp::Shl => "<<",
+            BinaryOp::Shr => ">>",
+            BinaryOp::Eq => "==",
+            BinaryOp::Ne => "!=",
+            BinaryOp::Lt => "<",
+            BinaryOp::Le => "<=",
+            BinaryOp::Gt => ">",
+            BinaryOp::Ge => ">=",
+        };
+        let value = match op {
+            BinaryOp::Div if signed => format!("stem_div({a}, {b})"),
+            BinaryOp::Rem if signed => format!("stem_rem({a}, {b})"),
+            // like the machine, a shift only uses the low 6 bits of its count
+            BinaryOp::Shr if signed => format!("stem_shr({a}, {b})"),
+            BinaryOp::Shl | BinaryOp::Shr => format!("{a} {operator} ({b} & 63)"),
+            _ if op.is_comparison() && signed && !matches!(op, BinaryOp::Eq | BinaryOp::Ne) => format!("stem_signed({a}) {operator} stem_signed({b})"),
+            _ => format!("{a} {operator} {b}"),
+        };
+        return format!("{destination} = {value};\n");
+    }
+
+    fn extend(&mut self, destination: &str, value: &str, size: u32, signed: bool) -> String {
+        return format!("{destination} = stem_extend({value}, {size}, {});\n", signed as u8);
+    }
+
+    fn load(&mut self, destination: &str, address: &str, size: u32, signed: bool) -> String {
+        return format!("{destination} = stem_load({address}, {size}, {});\n", signed as u8);
+    }
+
+    fn store(&mut self, address: &str, value: &str, size: u32) -> String {
+        return format!("stem_store({address}, {value}, {size});\n");
+    }
+
+    fn copy(&mut self, destination: &str, source: &str, size: u32) -> String {
+        return format!("memmove(stem_pointer({destination}), stem_pointer({source}), {size});\n");
+    }
+
+    /*
+     * a builtin gets location as a string after its arguments, a C function gets them as its C types and its value
+     * comes back as an uint64_t
+     */
+    fn call(&mut self, destination: Option<&str>, name: &str, function: &Function, args: &[(String, Type)], location: &str) -> String {
+        let mut values: Vec<String> = args.iter().map(|(value, _)| value.clone()).collect();
+        let call = match function.linkage {
+            Linkage::Builtin => {
+                values.push(c_string(location.as_bytes()));
+                format!("runtime_{name}({})", values.join(", "))
+            }
+            Linkage::Stem => format!("{}({})", c_name(name), values.join(", ")),
+            Linkage::C { .. } => {
+                let values: Vec<String> = args.iter().enumerate().map(|(i, (value, type_))| match function.params.get(i) {
+                    Some(param) => c_argument(value, param, false),
+                    None => c_argument(value, type_, true),
+                }).collect();
+                let call = format!("{name}({})", values.join(", "));
+                match (&function.returns, destination) {
+                    (Some(Type::Pointer(_)), Some(_)) => format!("stem_address({call})"),
+                    (Some(_), Some(_)) => format!("(uint64_t){call}"),
+                    _ => call,
+                }
+            }
+        };
+        return match destination {
+            Some(destination) => format!("{destination} = {call};\n"),
+            None => format!("{call};\n"),
+        };
+    }
+
+    fn discard(&mut self, value: &str) -> String {
+        return format!("(void){value};\n");
+    }
+
+    fn put(&mut self, value: &str, signed: bool) -> String {
+        return format!("runtime_put({value}, {});\n", signed as u8);
+    }
+
+    fn panic(&mut self, message: &str) -> String {
+        return format!("runtime_panic({});\n", c_string(message.as_bytes()));
+    }
+
+    fn ret(&mut self, value: Option<&str>) -> String {
+        return match value {
+            Some(value) => format!("return {value};\n"),
+            None => "return;\n".to_string(),
+        };
+    }
+
+    fn if_nonzero(&mut self, value: &str) -> String {
+        return format!("if ({value} != 0) {{\n");
+    }
+
+    fn if_zero(&mut self, value: &str) -> String {
+        return format!("if ({value} == 0) {{\n");
+    }
+
+    fn if_else(&mut self) -> String {
+        return "} else {\n".to_string();
+    }
+
+    fn if_end(&mut self) -> String {
+        return "}\n".to_string();
+    }
+
+    fn loop_start(&mut self) -> String {
+        return "for (;;) {\n".to_string();
+    }
+
+    fn loop_exit_if_zero(&mut self, value: &str) -> String {
+        return format!("if ({value} == 0) {{\nbreak;\n}}\n");
+    }
+
+    fn loop_end(&mut self) -> String {
+        return "}\n".to_string();
+    }
+
+    fn statement_start(&mut self) {
+        self.declared.push(vec![]);
+    }
+
+    /*
+     * the code of a statement after a comment with its location, its temporaries are declared in a block around it
+     */
+    fn statement_end(&mut self, location: &str, code: String) -> String {
+        let temporaries = self.declared.pop().expect("the temporaries of the statement");
+        let location = format!("/* {} */\n", location.replace("*/", "* /"));
+        if temporaries.is_empty() {
+            return location + &code;
+        }
+        return format!("{location}{{\nuint64_t {};\n{code}}}\n", temporaries.join(", "));
+    }
+
+    /*
+     * falling off the end of a function returns 0
+     */
+    fn function(&mut self, decl: &FnDecl, location: &str, body: Body) -> String {
+        let epilogue = if decl.returns.is_some() { "return 0;\n" } else { "" };
+        let header = format!("/* {} */\n{}", location.replace("*/", "* /"), prototype(decl, Some(&body.params)));
+        return self.function_codegen(&header, &body, epilogue);
+    }
+}
+
+impl C {
+    /*
+     * the C function of a body: its locals, then its frame if it has one, the body and the epilogue
+     */
+    fn function_codegen(&mut self, header: &str, body: &Body, epilogue: &str) -> String {
+        let mut code = format!("{header} {{\n");
+        if !body.locals.is_empty() {
+            let locals: Vec<String> = body.locals.iter().map(|local| format!("{local} = 0")).collect();
+            code += &format!("uint64_t {};\n", locals.join(", "));
+        }
+        if let Some(frame_size) = body.frame_size {
+            code += &format!("uint64_t frame[{}];\n", frame_size.div_ceil(8));
+            code += "unsigned char *frame_top = (unsigned char *)frame + sizeof frame;\n";
+        }
+        code += &body.code;
+        code += epilogue;
+        code += "}\n";
+        self.temporaries = 0;
+        return indent(&code, 0);
+    }
+}
+
+/*
+ * indent the lines of a body by the braces they are in, starting at depth
+ */
+fn indent(code: &str, depth: usize) -> String {
+    let mut indented = "".to_string();
+    let mut depth = depth;
+    for line in code.lines() {
+        if line.starts_with('}') {
+            depth -= 1;
+        }
+        indented += &format!("{}{line}\n", "    ".repeat(depth));
+        if line.ends_with('{') {
+            depth += 1;
+        }
+    }
+    return indented;
+}
+
+/*
+ * the prototype of a function, the parameters and the value are all uint64_t, the parameters named after params
+ * a function returning a struct gets first the address to store it at
+ */
+fn prototype(decl: &FnDecl, params: Option<&[String]>) -> String {
+    let params: Vec<String> = match params {
+        Some(params) => params.iter().map(|param| format!("uint64_t {param}")).collect(),
+        None => {
+            let hidden = decl.returns.as_ref().is_some_and(Type::is_struct) as usize;
+            vec!["uint64_t".to_string(); decl.params.len() + hidden]
+        }
+    };
+    let params = if params.is_empty() { "void".to_string() } else { params.join(", ") };
+    let returns = if decl.returns.is_some() { "uint64_t" } else { "void" };
+    return format!("{returns} {}({params})", c_name(&decl.name));
+}
+
+/*
+ * main: the top-level statements then the main of the program, or the test given on the command line, then exit
+ */
+fn main_codegen(program: &Program, ctx: &mut Context, lower: &mut Lowering<C>, options: &Options) -> String {
+    lower::function_start(&program.statements, ctx, lower);
+    let mut code = "".to_string();
+    let mut uses_args = options.tests;
+    let status = if options.tests {
+        // the test is a function named after its index
+        code += "uint64_t test = argc > 1 ? strtoull(argv[1], NULL, 10) : 0;\n";
+        for i in 0..program.tests.len() {
+            code += &format!("if (test == {i}) {{\n{}();\n}}\n", c_name(&format!("test.{i}")));
+        }
+        "0".to_string()
+    } else {
+        for stmt in &program.statements {
+            code += &lower::stmt_codegen(stmt, ctx, lower);
+        }
+
+        // then main, with argc and argv, and exit with what it returns
+        match program.functions.iter().find(|function| function.name == "main") {
+            Some(main) => {
+                uses_args = !main.params.is_empty();
+                let args = if main.params.is_empty() { "" } else { "(uint64_t)argc, stem_address(argv)" };
+                let call = format!("{}({args})", c_name("main"));
+                if main.returns.is_none() {
+                    code += &format!("{call};\n");
+                    "0".to_string()
+                } else {
+                    call
+                }
+            }
+            None => "0".to_string(),
+        }
+    };
+    code += &format!("runtime_exit({status}, \"\");\n");
+    ctx.use_runtime("exit");
+    // the C compilers that don't know runtime_exit never returns want a value
+    let header = if uses_args { "int main(int argc, char **argv)" } else { "int main(void)" };
+    let body = lower::body(code, ctx, lower);
+    return lower.backend.function_codegen(header, &body, "return 0;\n");
+}
+
+/*
+ * Take a Program and return its C file: the runtime, the C functions, the globals, the functions and main
+ */
+pub fn generate_c(program: &Program, sources: &SourceMap, options: &Options) -> String {
+    let mut ctx = new_context(program, sources, options);
+    let externs = externs_codegen(&program.externs, &ctx);
+    let mut lower = Lowering::new(C { globals: "".to_string(), temporaries: 0, declared: vec![] });
+    lower::globals_codegen(&program.globals, &mut ctx, &mut lower);
+    let mut decls = program.functions.clone();
+    if options.tests {
+        check_tests(&program.tests, sources);
+        for (i, test) in program.tests.iter().enumerate() {
+            decls.push(FnDecl { name: format!("test.{i}"), params: vec![], returns: None, body: test.body.clone(), export: None, span: test.span });
+        }
+    }
+    let mut functions = "".to_string();
+    for decl in &decls {
+        functions += "\n";
+        functions += &lower::fn_codegen(decl, &mut ctx, &mut lower);
+    }
+    let main = main_codegen(program, &mut ctx, &mut lower, options);
+
+    let mut code = runtime::C.prelude.to_string();
+    code += &runtime::functions_codegen(&runtime::C, &ctx.runtime);
+    if !externs.is_empty() {
+        code += "\n/* the C functions of the program */\n";
+        code += &externs;
+    }
+    code += "\n/* the program */\n";
+    for decl in &decls {
+        code += &format!("{};\n", prototype(decl, None));
+    }
+    if !lower.backend.globals.is_empty() {
+        code += "\n";
+        code += &lower.backend.globals;
+    }
+    code += &functions;
+    code += "\n";
+    code += &main;
+    return code;
+}
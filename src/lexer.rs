@@ -0,0 +1,415 @@
+/*
+ * Turn the text of a source file into tokens, one at a time
+ */
+use crate::ast::Type;
+use crate::source::{SourceMap, Span};
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+#[derive(Copy,PartialEq, Eq, Debug, Clone)]
+pub enum TokenType {
+    Plus,
+    Minus,
+    Mult,
+    Div,
+    Mod,
+    BitAnd,
+    BitOr,
+    BitXor,
+    BitNot,
+    ShiftLeft,
+    ShiftRight,
+    PlusAssign,
+    MinusAssign,
+    MultAssign,
+    DivAssign,
+    ModAssign,
+    AndAssign,
+    OrAssign,
+    XorAssign,
+    ShiftLeftAssign,
+    ShiftRightAssign,
+    Increment,
+    Decrement,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Semicolon,
+    Put,
+    OpenParen,
+    CloseParen,
+    Assign,
+    Dot,
+    OpenBrace,
+    CloseBrace,
+    Colon,
+    Comma,
+    Struct,
+    Let,
+    If,
+    Else,
+    While,
+    Fn,
+    Return,
+    Static,
+    Const,
+    Arrow,
+    Assert,
+    Test,
+    Import,
+    Module,
+    Extern,
+    Pub,
+    // `...` after the parameters of a C function that takes more arguments
+    Ellipsis,
+    // `::` between the name of a module and the name of one of its items
+    PathSep,
+
+    Word,
+    Integer,
+    // `"..."`, the lexeme keeps the quotes and the escapes as written
+    String,
+    // `/// ...` up to the end of the line, trivia kept for tooling, the parser never sees it
+    DocComment,
+    EOF,
+}
+
+#[derive(Debug, Clone)]
+pub struct Token {
+    pub span: Span,
+    pub lexeme: String,
+    pub type_: TokenType,
+}
+
+/*
+ * the token of a word that is a keyword, None for a word that can name something
+ */
+pub fn keyword(word: &str) -> Option<TokenType> {
+    let type_ = match word {
+        "put" => TokenType::Put,
+        "struct" => TokenType::Struct,
+        "let" => TokenType::Let,
+        "if" => TokenType::If,
+        "else" => TokenType::Else,
+        "while" => TokenType::While,
+        "fn" => TokenType::Fn,
+        "return" => TokenType::Return,
+        "static" => TokenType::Static,
+        "const" => TokenType::Const,
+        "assert" => TokenType::Assert,
+        "test" => TokenType::Test,
+        "import" => TokenType::Import,
+        "mod" => TokenType::Module,
+        "extern" => TokenType::Extern,
+        "pub" => TokenType::Pub,
+        _ => return None,
+    };
+    Some(type_)
+}
+
+/*
+ * Read the lexeme of an Integer token: `0x`, `0o` and `0b` prefixes, `_` separators and a type suffix like `10u8`
+ * without suffix the literal is an i64, negated is true for the literal of `-128i8`
+ * returns the value and the type of the suffix, or the message of the error
+ */
+pub fn integer_literal(lexeme: &str, negated: bool) -> Result<(u64, Option<Type>), String> {
+    let (radix, base, body) = match lexeme.get(..2) {
+        Some("0x") => (16, "hexadecimal", &lexeme[2..]),
+        Some("0o") => (8, "octal", &lexeme[2..]),
+        Some("0b") => (2, "binary", &lexeme[2..]),
+        _ => (10, "decimal", lexeme),
+    };
+    let (digits, suffix) = match body.find(['i', 'u']) {
+        Some(i) => body.split_at(i),
+        None => (body, ""),
+    };
+    if let Some(c) = digits.chars().find(|c| *c != '_' && !c.is_digit(radix)) {
+        return Err(format!("invalid digit `{}` in {} literal `{}`", c, base, lexeme));
+    }
+    let digits = digits.replace('_', "");
+    if digits.is_empty() {
+        return Err(format!("{} literal `{}` has no digits", base, lexeme));
+    }
+    let type_ = match Type::from_name(suffix) {
+        _ if suffix.is_empty() => None,
+        Type::Struct(_) => return Err(format!("invalid suffix `{}` on integer literal `{}`", suffix, lexeme)),
+        type_ => Some(type_),
+    };
+
+    let range_type = type_.clone().unwrap_or(Type::I64);
+    match u64::from_str_radix(&digits, radix) {
+        Ok(value) if value <= range_type.max_literal(negated) => Ok((value, type_)),
+        _ => Err(format!("literal `{}` is out of range for `{}`", lexeme, range_type)),
+    }
+}
+
+/*
+ * Iterator over the tokens of a file, the last token is always an EOF
+ * chars are read through a Peekable cursor, so looking ahead never copies the source
+ */
+pub struct Lexer<'a> {
+    sources: &'a SourceMap,
+    file_id: u32,
+    text: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+    finished: bool,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(sources: &'a SourceMap, file_id: u32) -> Lexer<'a> {
+        let text = &sources.file(file_id).text;
+        Lexer {
+            sources,
+            file_id,
+            text,
+            chars: text.char_indices().peekable(),
+            finished: false,
+        }
+    }
+
+    fn span(&self, start: usize, end: usize) -> Span {
+        Span { file_id: self.file_id, start: start as u32, end: end as u32 }
+    }
+
+    /*
+     * byte offset of the next char, the length of the text at the end of the file
+     */
+    fn offset(&mut self) -> usize {
+        match self.chars.peek() {
+            Some((i, _)) => *i,
+            None => self.text.len(),
+        }
+    }
+
+    /*
+     * scan the next char if it is expected
+     */
+    fn eat(&mut self, expected: char) -> bool {
+        self.chars.next_if(|(_, c)| *c == expected).is_some()
+    }
+
+    /*
+     * scan chars as long as they match the predicate
+     */
+    fn eat_while(&mut self, predicate: impl Fn(char) -> bool) {
+        while self.chars.next_if(|(_, c)| predicate(*c)).is_some() {}
+    }
+
+    /*
+     * skip the block comment opened at start, block comments nest
+     */
+    fn skip_block_comment(&mut self, start: usize) {
+        self.chars.next();
+        self.chars.next();
+        let mut depth = 1;
+        while depth > 0 {
+            match self.chars.next() {
+                Some((_, '/')) if self.chars.next_if(|(_, c)| *c == '*').is_some() => depth += 1,
+                Some((_, '*')) if self.chars.next_if(|(_, c)| *c == '/').is_some() => depth -= 1,
+                Some(_) => {}
+                None => {
+                    let location = self.sources.location(self.span(start, start + 2));
+                    eprintln!("ERROR:{}: unterminated block comment", location);
+                    std::process::exit(1);
+                }
+            }
+        }
+    }
+}
+
+impl Iterator for Lexer<'_> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        if self.finished {
+            return None;
+        }
+        loop {
+            self.eat_while(char::is_whitespace);
+            let start = self.offset();
+            let rest = &self.text[start..];
+            if rest.starts_with("//") {
+                self.eat_while(|c| c != '\n');
+                if rest.starts_with("///") && !rest.starts_with("////") {
+                    let end = self.offset();
+                    return Some(Token { span: self.span(start, end), lexeme: self.text[start..end].to_string(), type_: TokenType::DocComment });
+                }
+            } else if rest.starts_with("/*") {
+                self.skip_block_comment(start);
+            } else {
+                break;
+            }
+        }
+        let Some((start, c)) = self.chars.next() else {
+            self.finished = true;
+            let end = self.text.len();
+            return Some(Token { span: self.span(end, end), lexeme: "EOF".to_string(), type_: TokenType::EOF });
+        };
+
+        let type_ = match c {
+            '=' if self.eat('=') => TokenType::Equal,
+            '=' => TokenType::Assign,
+            '!' if self.eat('=') => TokenType::NotEqual,
+            '.' if self.text[start..].starts_with("...") => {
+                self.chars.next();
+                self.chars.next();
+                TokenType::Ellipsis
+            }
+            '.' => TokenType::Dot,
+            '{' => TokenType::OpenBrace,
+            '}' => TokenType::CloseBrace,
+            ':' if self.eat(':') => TokenType::PathSep,
+            ':' => TokenType::Colon,
+            ',' => TokenType::Comma,
+            '(' => TokenType::OpenParen,
+            ')' => TokenType::CloseParen,
+            ';' => TokenType::Semicolon,
+            // operators of several chars are matched greedily: `<<=` before `<<` before `<`
+            '*' if self.eat('=') => TokenType::MultAssign,
+            '*' => TokenType::Mult,
+            '/' if self.eat('=') => TokenType::DivAssign,
+            '/' => TokenType::Div,
+            '+' if self.eat('+') => TokenType::Increment,
+            '+' if self.eat('=') => TokenType::PlusAssign,
+            '+' => TokenType::Plus,
+            '-' if self.eat('>') => TokenType::Arrow,
+            '-' if self.eat('-') => TokenType::Decrement,
+            '-' if self.eat('=') => TokenType::MinusAssign,
+            '-' => TokenType::Minus,
+            '%' if self.eat('=') => TokenType::ModAssign,
+            '%' => TokenType::Mod,
+            '&' if self.eat('=') => TokenType::AndAssign,
+            '&' => TokenType::BitAnd,
+            '|' if self.eat('=') => TokenType::OrAssign,
+            '|' => TokenType::BitOr,
+            '^' if self.eat('=') => TokenType::XorAssign,
+            '^' => TokenType::BitXor,
+            '~' => TokenType::BitNot,
+            '<' if self.eat('<') => if self.eat('=') { TokenType::ShiftLeftAssign } else { TokenType::ShiftLeft },
+            '<' if self.eat('=') => TokenType::LessEqual,
+            '<' => TokenType::Less,
+            '>' if self.eat('>') => if self.eat('=') { TokenType::ShiftRightAssign } else { TokenType::ShiftRight },
+            '>' if self.eat('=') => TokenType::GreaterEqual,
+            '>' => TokenType::Greater,
+            '"' => {
+                // the char after a `\` never closes the string, the parser checks the escapes
+                loop {
+                    match self.chars.next() {
+                        Some((_, '"')) => break,
+                        Some((_, '\\')) if self.chars.next_if(|(_, c)| *c != '\n').is_some() => {}
+                        Some((_, '\n')) | None => {
+                            let location = self.sources.location(self.span(start, start + 1));
+                            eprintln!("ERROR:{}: unterminated string", location);
+                            std::process::exit(1);
+                        }
+                        Some(_) => {}
+                    }
+                }
+                TokenType::String
+            }
+            _ if c.is_ascii_digit() => {
+                // prefixes, suffixes and invalid digits are all part of the token, the parser checks them
+                self.eat_while(|c| c.is_ascii_alphanumeric() || c == '_');
+                TokenType::Integer
+            }
+            _ if c.is_alphabetic() || c == '_' => {
+                self.eat_while(|c| c.is_alphanumeric() || c == '_');
+                let end = self.offset();
+                keyword(&self.text[start..end]).unwrap_or(TokenType::Word)
+            }
+            _ => {
+                let location = self.sources.location(self.span(start, start));
+                eprintln!("ERROR:{}: Unexpected token `{}`", location, c);
+                std::process::exit(1)
+            }
+        };
+        let end = self.offset();
+        Some(Token { span: self.span(start, end), lexeme: self.text[start..end].to_string(), type_ })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(text: &str) -> Vec<(TokenType, String)> {
+        let mut sources = SourceMap::new();
+        let file_id = sources.add_file("t.stm".to_string(), text.to_string());
+        return Lexer::new(&sources, file_id).map(|token| (token.type_, token.lexeme)).collect();
+    }
+
+    #[test]
+    fn integer_tokens_keep_prefix_separators_and_suffix() {
+        let integer = |lexeme: &str| (TokenType::Integer, lexeme.to_string());
+        assert_eq!(
+            tokens("0x1F 1_000u16 0b1010i8 -128i8"),
+            vec![integer("0x1F"), integer("1_000u16"), integer("0b1010i8"), (TokenType::Minus, "-".to_string()), integer("128i8"), (TokenType::EOF, "EOF".to_string())]
+        );
+    }
+
+    #[test]
+    fn accepts_prefixes_and_separators() {
+        assert_eq!(integer_literal("0", false), Ok((0, None)));
+        assert_eq!(integer_literal("0x1F", false), Ok((31, None)));
+        assert_eq!(integer_literal("0xff_ff", false), Ok((0xffff, None)));
+        assert_eq!(integer_literal("0o17", false), Ok((15, None)));
+        assert_eq!(integer_literal("0b1010", false), Ok((10, None)));
+        assert_eq!(integer_literal("1_000_000", false), Ok((1_000_000, None)));
+        assert_eq!(integer_literal("1__0_", false), Ok((10, None)));
+        assert_eq!(integer_literal("0x_1", false), Ok((1, None)));
+    }
+
+    #[test]
+    fn accepts_type_suffixes() {
+        assert_eq!(integer_literal("10u8", false), Ok((10, Some(Type::U8))));
+        assert_eq!(integer_literal("10_i16", false), Ok((10, Some(Type::I16))));
+        assert_eq!(integer_literal("0xffu32", false), Ok((255, Some(Type::U32))));
+        assert_eq!(integer_literal("0b1i64", false), Ok((1, Some(Type::I64))));
+        assert_eq!(integer_literal("7u64", false), Ok((7, Some(Type::U64))));
+    }
+
+    #[test]
+    fn rejects_empty_and_invalid_digits() {
+        assert_eq!(integer_literal("0x", false), Err("hexadecimal literal `0x` has no digits".to_string()));
+        assert_eq!(integer_literal("0b_", false), Err("binary literal `0b_` has no digits".to_string()));
+        assert_eq!(integer_literal("0xu8", false), Err("hexadecimal literal `0xu8` has no digits".to_string()));
+        assert_eq!(integer_literal("0b102", false), Err("invalid digit `2` in binary literal `0b102`".to_string()));
+        assert_eq!(integer_literal("0o8", false), Err("invalid digit `8` in octal literal `0o8`".to_string()));
+        assert_eq!(integer_literal("12a", false), Err("invalid digit `a` in decimal literal `12a`".to_string()));
+        assert_eq!(integer_literal("0xfg", false), Err("invalid digit `g` in hexadecimal literal `0xfg`".to_string()));
+    }
+
+    #[test]
+    fn rejects_unknown_suffixes() {
+        assert_eq!(integer_literal("1u7", false), Err("invalid suffix `u7` on integer literal `1u7`".to_string()));
+        assert_eq!(integer_literal("1i128", false), Err("invalid suffix `i128` on integer literal `1i128`".to_string()));
+        assert_eq!(integer_literal("1usize", false), Err("invalid suffix `usize` on integer literal `1usize`".to_string()));
+    }
+
+    #[test]
+    fn rejects_out_of_range_values() {
+        assert_eq!(integer_literal("255u8", false), Ok((255, Some(Type::U8))));
+        assert_eq!(integer_literal("256u8", false), Err("literal `256u8` is out of range for `u8`".to_string()));
+        assert_eq!(integer_literal("0x1_0000u16", false), Err("literal `0x1_0000u16` is out of range for `u16`".to_string()));
+        assert_eq!(integer_literal("18446744073709551615u64", false), Ok((u64::MAX, Some(Type::U64))));
+        assert_eq!(integer_literal("18446744073709551616u64", false), Err("literal `18446744073709551616u64` is out of range for `u64`".to_string()));
+        assert_eq!(integer_literal("9223372036854775807", false), Ok((i64::MAX as u64, None)));
+        assert_eq!(integer_literal("9223372036854775808", false), Err("literal `9223372036854775808` is out of range for `i64`".to_string()));
+    }
+
+    #[test]
+    fn negated_literals_reach_the_minimum() {
+        assert_eq!(integer_literal("127i8", false), Ok((127, Some(Type::I8))));
+        assert_eq!(integer_literal("128i8", false), Err("literal `128i8` is out of range for `i8`".to_string()));
+        assert_eq!(integer_literal("128i8", true), Ok((128, Some(Type::I8))));
+        assert_eq!(integer_literal("129i8", true), Err("literal `129i8` is out of range for `i8`".to_string()));
+        assert_eq!(integer_literal("32768i16", true), Ok((32768, Some(Type::I16))));
+        assert_eq!(integer_literal("9223372036854775808", true), Ok((1 << 63, None)));
+        assert_eq!(integer_literal("9223372036854775809", true), Err("literal `9223372036854775809` is out of range for `i64`".to_string()));
+        // unsigned types don't get one more value
+        assert_eq!(integer_literal("256u8", true), Err("literal `256u8` is out of range for `u8`".to_string()));
+    }
+}
@@ -0,0 +1,1058 @@
+/*
+ * The lowering shared by the backends that translate the program from its tree instead of going through a Target,
+ * wasm.rs and c.rs: it checks the program like main.rs does and breaks every expression into operations on values,
+ * in the order of the source, then a Backend writes each operation in its language
+ * a value is a constant, the address of a string, of a slot of the frame or of a global, or a temporary the code sets
+ * once: the code after it can't change it, so the lowering can use it more than once
+ * the scalar variables whose address is never taken are locals of their function, the others live in its frame
+ */
+use crate::ast::{BinaryOp, Expr, ExprKind, FnDecl, GlobalDecl, Stmt, StmtKind, Type, UnaryOp};
+use crate::source::Span;
+use crate::{
+    binary_result_type, binary_type, check_assignable, check_struct_literal, check_types, const_eval, field_of, global_type, literal_field, mentions_variable, pointee_of, root_variable, signed_min, truncate, type_of,
+    Context, Function, Global, Linkage, Place, Variable,
+};
+use std::collections::{HashMap, HashSet};
+
+/*
+ * A language the lowering writes: each method returns the code of an operation
+ * the destination of an operation is a temporary or a local, its operands are values
+ */
+pub trait Backend {
+    fn constant(&self, value: u64) -> String;
+    // the address of text followed by a 0 byte
+    fn string(&mut self, text: &str) -> String;
+    // the address of the slot offset bytes below the top of the frame
+    fn frame_address(&self, offset: u32) -> String;
+    // a global of size bytes aligned on align, holding bytes or zeroed, return its address
+    fn global(&mut self, name: &str, size: u32, align: u32, bytes: Option<Vec<u8>>, location: &str) -> String;
+    // a temporary free until the end of the statement being translated
+    fn temporary(&mut self) -> String;
+    // the name of a local for the variable name, i from 1 when the ones before are taken
+    fn local(&self, name: &str, i: u32) -> String;
+
+    fn set(&mut self, destination: &str, value: &str) -> String;
+    // op on 64 bits, signed chooses between the signed and unsigned division, shift and comparisons
+    // a comparison gives 0 or 1, a shift only uses the low 6 bits of its count, the lowering never divides by 0
+    // nor the smallest i64 by -1
+    fn operation(&mut self, destination: &str, op: BinaryOp, a: &str, b: &str, signed: bool) -> String;
+    // the low size bytes of value extended to 64 bits, size is below 8
+    fn extend(&mut self, destination: &str, value: &str, size: u32, signed: bool) -> String;
+    fn load(&mut self, destination: &str, address: &str, size: u32, signed: bool) -> String;
+    fn store(&mut self, address: &str, value: &str, size: u32) -> String;
+    fn copy(&mut self, destination: &str, source: &str, size: u32) -> String;
+    // call the function name with args, the value it returns goes to destination or is dropped without one
+    // a builtin also gets location, the `file:line:col: ` of the call, for its panics
+    fn call(&mut self, destination: Option<&str>, name: &str, function: &Function, args: &[(String, Type)], location: &str) -> String;
+    // a value computed for its effects only
+    fn discard(&mut self, value: &str) -> String;
+    fn put(&mut self, value: &str, signed: bool) -> String;
+    // message is the whole line, `file:line:col: message\n`
+    fn panic(&mut self, message: &str) -> String;
+    fn ret(&mut self, value: Option<&str>) -> String;
+
+    // the code up to if_else or if_end runs when value is not 0, or when it is 0 for if_zero
+    fn if_nonzero(&mut self, value: &str) -> String;
+    fn if_zero(&mut self, value: &str) -> String;
+    fn if_else(&mut self) -> String;
+    fn if_end(&mut self) -> String;
+    // the code up to loop_end runs again until loop_exit_if_zero gets a 0
+    fn loop_start(&mut self) -> String;
+    fn loop_exit_if_zero(&mut self, value: &str) -> String;
+    fn loop_end(&mut self) -> String;
+
+    // the temporaries of a statement are given back at its end, location is where it is in the source
+    fn statement_start(&mut self);
+    fn statement_end(&mut self, location: &str, code: String) -> String;
+    fn function(&mut self, decl: &FnDecl, location: &str, body: Body) -> String;
+}
+
+/*
+ * a function translated by the lowering, for its backend to write: its parameters and locals, the size of its frame
+ * when a variable lives in it, and its code
+ */
+pub struct Body {
+    pub params: Vec<String>,
+    pub locals: Vec<String>,
+    pub frame_size: Option<u32>,
+    pub code: String,
+}
+
+/*
+ * State of the lowering, next to the context the checks share with the other backends:
+ * the backend, the globals and the locals of the function being translated
+ */
+pub struct Lowering<B: Backend> {
+    pub backend: B,
+    // the address of each global that isn't folded, as a value
+    globals: HashMap<String, String>,
+    // the names of the variables of the whole function whose address is taken, they live in the frame
+    addressed: HashSet<String>,
+    // the parameters and the locals of the function in their order, the names of both, and the local of each
+    // variable that has one, by its offset
+    params: Vec<String>,
+    locals: Vec<String>,
+    taken: HashSet<String>,
+    variable_locals: HashMap<u32, String>,
+    // a variable or a copy lives in the frame, the function has to reserve it
+    frame_used: bool,
+    // inside a function, where `return` can be used
+    in_function: bool,
+    // the parameter holding where to store the struct the function returns
+    return_pointer: Option<String>,
+}
+
+impl<B: Backend> Lowering<B> {
+    pub fn new(backend: B) -> Self {
+        return Lowering {
+            backend,
+            globals: HashMap::new(),
+            addressed: HashSet::new(),
+            params: vec![],
+            locals: vec![],
+            taken: HashSet::new(),
+            variable_locals: HashMap::new(),
+            frame_used: false,
+            in_function: false,
+            return_pointer: None,
+        };
+    }
+
+    /*
+     * a new local for the variable name, named differently from the other locals and the parameters
+     */
+    fn local(&mut self, name: &str) -> String {
+        let mut i = 1;
+        loop {
+            let local = self.backend.local(name, i);
+            if self.taken.insert(local.clone()) {
+                self.locals.push(local.clone());
+                return local;
+            }
+            i += 1;
+        }
+    }
+
+    /*
+     * a parameter of the function, no local is given its name
+     */
+    fn param(&mut self, param: String) {
+        self.taken.insert(param.clone());
+        self.params.push(param);
+    }
+}
+
+/*
+ * where a value is stored: a local of the function, or the memory at an address
+ */
+enum Location {
+    Local(String),
+    Memory(String),
+}
+
+/*
+ * the names of the variables whose address is taken in stmt, the roots of `&a` and `&a.b`
+ */
+fn addressed_variables(stmt: &Stmt, names: &mut HashSet<String>) {
+    fn expr_variables(expr: &Expr, names: &mut HashSet<String>) {
+        match &expr.kind {
+            ExprKind::Integer { .. } | ExprKind::String(_) | ExprKind::Variable(_) => {}
+            ExprKind::StructLiteral { fields, .. } => fields.iter().for_each(|(_, value)| expr_variables(value, names)),
+            ExprKind::Field { base, .. } => expr_variables(base, names),
+            ExprKind::Unary { operand, .. } => expr_variables(operand, names),
+            ExprKind::Binary { lhs, rhs, .. } => {
+                expr_variables(lhs, names);
+                expr_variables(rhs, names);
+            }
+            ExprKind::Assign { target, value } | ExprKind::CompoundAssign { target, value, .. } => {
+                expr_variables(target, names);
+                expr_variables(value, names);
+            }
+            ExprKind::Postfix { target, .. } => expr_variables(target, names),
+            ExprKind::Call { args, .. } => args.iter().for_each(|arg| expr_variables(arg, names)),
+            ExprKind::AddressOf(place) => {
+                if let Some(root) = root_variable(place) {
+                    names.insert(root.to_string());
+                }
+                expr_variables(place, names);
+            }
+            ExprKind::Deref(pointer) => expr_variables(pointer, names),
+        }
+    }
+    match &stmt.kind {
+        StmtKind::Expr(expr) | StmtKind::Put(expr) | StmtKind::Let { value: expr, .. } => expr_variables(expr, names),
+        StmtKind::Assert { condition, .. } => expr_variables(condition, names),
+        StmtKind::Return(value) => value.iter().for_each(|value| expr_variables(value, names)),
+        StmtKind::Block(stmts) => stmts.iter().for_each(|stmt| addressed_variables(stmt, names)),
+        StmtKind::If { condition, then, otherwise } => {
+            expr_variables(condition, names);
+            addressed_variables(then, names);
+            otherwise.iter().for_each(|otherwise| addressed_variables(otherwise, names));
+        }
+        StmtKind::While { condition, body } => {
+            expr_variables(condition, names);
+            addressed_variables(body, names);
+        }
+    }
+}
+
+fn frame_address<B: Backend>(offset: u32, lower: &mut Lowering<B>) -> String {
+    lower.frame_used = true;
+    return lower.backend.frame_address(offset);
+}
+
+/*
+ * a new variable in the current scope: a local, or a slot of the frame for a struct or a variable whose address is taken
+ */
+fn declare<B: Backend>(name: &str, type_: Type, ctx: &mut Context, lower: &mut Lowering<B>) {
+    let offset = ctx.declare(name.to_string(), type_.clone());
+    bind(name, offset, &type_, lower);
+}
+
+/*
+ * give the variable name at offset its local, the other variables that had that offset are out of scope
+ */
+fn bind<B: Backend>(name: &str, offset: u32, type_: &Type, lower: &mut Lowering<B>) {
+    if type_.is_struct() || lower.addressed.contains(name) {
+        lower.frame_used = true;
+        lower.variable_locals.remove(&offset);
+    } else {
+        let local = lower.local(name);
+        lower.variable_locals.insert(offset, local);
+    }
+}
+
+/*
+ * the code loading the value of type_ at address to destination
+ */
+fn load<B: Backend>(destination: &str, address: &str, type_: &Type, span: Span, ctx: &Context, lower: &mut Lowering<B>) -> String {
+    if let Type::Struct(name) = type_ {
+        eprintln!("ERROR:{}: a value of type `{}` can't be used as a number", ctx.sources.location(span), name);
+        std::process::exit(1);
+    }
+    return lower.backend.load(destination, address, ctx.structs.size_of(type_), type_.is_signed());
+}
+
+/*
+ * the code storing value at address, truncated to the size of type_
+ */
+fn store<B: Backend>(address: &str, value: &str, type_: &Type, ctx: &Context, lower: &mut Lowering<B>) -> String {
+    return lower.backend.store(address, value, ctx.structs.size_of(type_));
+}
+
+/*
+ * the code setting destination to value with the bits of type_ extended to 64 bits
+ */
+fn extend<B: Backend>(destination: &str, value: &str, type_: &Type, ctx: &Context, lower: &mut Lowering<B>) -> String {
+    let size = ctx.structs.size_of(type_);
+    if size == 8 {
+        return lower.backend.set(destination, value);
+    }
+    return lower.backend.extend(destination, value, size, type_.is_signed());
+}
+
+/*
+ * the code panicking with `file:line:col: message`
+ */
+fn panic_codegen<B: Backend>(message: &str, span: Span, ctx: &mut Context, lower: &mut Lowering<B>) -> String {
+    let message = format!("{}: {message}\n", ctx.sources.location(span));
+    ctx.use_runtime("panic");
+    return lower.backend.panic(&message);
+}
+
+/*
+ * the code panicking when value is not 0
+ */
+fn panic_if<B: Backend>(value: &str, message: &str, span: Span, ctx: &mut Context, lower: &mut Lowering<B>) -> String {
+    let mut code = lower.backend.if_nonzero(value);
+    code += &panic_codegen(message, span, ctx, lower);
+    code += &lower.backend.if_end();
+    return code;
+}
+
+/*
+ * panic if pointer is null, span is the pointer in the source, only with overflow checks
+ */
+fn null_check_codegen<B: Backend>(pointer: &str, span: Span, ctx: &mut Context, lower: &mut Lowering<B>) -> String {
+    if !ctx.overflow_checks {
+        return String::new();
+    }
+    let mut code = lower.backend.if_zero(pointer);
+    code += &panic_codegen("attempt to dereference a null pointer", span, ctx, lower);
+    code += &lower.backend.if_end();
+    return code;
+}
+
+/*
+ * the code setting a new temporary to op applied to a and b, and the temporary
+ */
+fn operation<B: Backend>(op: BinaryOp, a: &str, b: &str, signed: bool, lower: &mut Lowering<B>) -> (String, String) {
+    let result = lower.backend.temporary();
+    let code = lower.backend.operation(&result, op, a, b, signed);
+    return (code, result);
+}
+
+/*
+ * Take an assignable expression (a variable, a field or a dereference) and return where it is, the code computing
+ * its address and its type
+ */
+fn location_codegen<B: Backend>(expr: &Expr, ctx: &mut Context, lower: &mut Lowering<B>) -> (Location, String, Type) {
+    match &expr.kind {
+        ExprKind::Variable(name) => match ctx.variable(name, expr.span) {
+            Place::Local(Variable { offset, type_ }) => match lower.variable_locals.get(&offset) {
+                Some(local) => (Location::Local(local.clone()), "".to_string(), type_),
+                None => (Location::Memory(frame_address(offset, lower)), "".to_string(), type_),
+            },
+            Place::Global(Global { value: Some(_), .. }) => {
+                eprintln!("ERROR:{}: constant `{}` has no address", ctx.sources.location(expr.span), name);
+                std::process::exit(1);
+            }
+            Place::Global(global) => (Location::Memory(lower.globals[name].clone()), "".to_string(), global.type_),
+        },
+        ExprKind::Field { .. } | ExprKind::Deref(_) => {
+            let (code, address, type_) = address_codegen(expr, ctx, lower);
+            (Location::Memory(address), code, type_)
+        }
+        _ => {
+            eprintln!("ERROR:{}: this expression is not a variable, a field or a dereference", ctx.sources.location(expr.span));
+            std::process::exit(1);
+        }
+    }
+}
+
+/*
+ * Take an expression that lives in the memory and return the code computing its address, the address and its type
+ */
+fn address_codegen<B: Backend>(expr: &Expr, ctx: &mut Context, lower: &mut Lowering<B>) -> (String, String, Type) {
+    match &expr.kind {
+        ExprKind::Field { base, field } => {
+            let field = field_of(base, field, expr.span, ctx);
+            let (mut code, base_address) = if type_of(base, ctx).is_pointer() {
+                // the address of the struct is the value of the pointer
+                let (mut code, pointer, _) = expr_codegen(base, ctx, lower);
+                code += &null_check_codegen(&pointer, base.span, ctx, lower);
+                (code, pointer)
+            } else {
+                let (code, address, _) = address_codegen(base, ctx, lower);
+                (code, address)
+            };
+            if field.offset == 0 {
+                return (code, base_address, field.type_);
+            }
+            let offset = lower.backend.constant(field.offset as u64);
+            let (code2, address) = operation(BinaryOp::Add, &base_address, &offset, false, lower);
+            code += &code2;
+            (code, address, field.type_)
+        }
+        ExprKind::Deref(pointer) => {
+            let (mut code, address, type_) = expr_codegen(pointer, ctx, lower);
+            let pointee = pointee_of(pointer, type_, ctx);
+            code += &null_check_codegen(&address, pointer.span, ctx, lower);
+            (code, address, pointee)
+        }
+        // the struct a call returns is in the frame of the caller
+        ExprKind::Call { .. } if type_of(expr, ctx).is_struct() => expr_codegen(expr, ctx, lower),
+        _ => match location_codegen(expr, ctx, lower) {
+            (Location::Memory(address), code, type_) => (code, address, type_),
+            (Location::Local(_), _, _) => unreachable!("a variable whose address is taken lives in the frame"),
+        },
+    }
+}
+
+/*
+ * Store the value of an expression of type type_ at address
+ * scalars are truncated to the size of type_, structs are copied
+ */
+fn store_codegen<B: Backend>(address: &str, value: &Expr, type_: &Type, ctx: &mut Context, lower: &mut Lowering<B>) -> String {
+    let value_type = type_of(value, ctx);
+    check_types(type_, value, &value_type, ctx);
+    if let ExprKind::StructLiteral { name, fields } = &value.kind {
+        return struct_literal_codegen(address, name, fields, value.span, ctx, lower);
+    }
+    if type_.is_struct() {
+        let (code, source, _) = address_codegen(value, ctx, lower);
+        return code + &lower.backend.copy(address, &source, ctx.structs.size_of(type_));
+    }
+    let (code, value, _) = expr_codegen(value, ctx, lower);
+    return code + &store(address, &value, type_, ctx, lower);
+}
+
+/*
+ * Build a struct literal directly at address
+ */
+fn struct_literal_codegen<B: Backend>(address: &str, name: &str, fields: &[(String, Expr)], span: Span, ctx: &mut Context, lower: &mut Lowering<B>) -> String {
+    check_struct_literal(name, fields, ctx);
+    let layout = ctx.structs.layout(name).clone();
+    let mut code = "".to_string();
+    for field in &layout.fields {
+        let value = literal_field(name, fields, &field.name, span, ctx);
+        let field_address = if field.offset == 0 {
+            address.to_string()
+        } else {
+            let offset = lower.backend.constant(field.offset as u64);
+            let (code2, field_address) = operation(BinaryOp::Add, address, &offset, false, lower);
+            code += &code2;
+            field_address
+        };
+        code += &store_codegen(&field_address, value, &field.type_, ctx, lower);
+    }
+    return code;
+}
+
+/*
+ * Take an expression and return the code computing it, its value and its type
+ * an assignment of a struct has the value 0, it is only ever dropped
+ */
+fn expr_codegen<B: Backend>(expr: &Expr, ctx: &mut Context, lower: &mut Lowering<B>) -> (String, String, Type) {
+    match &expr.kind {
+        ExprKind::Integer { value, type_ } => ("".to_string(), lower.backend.constant(*value), type_.clone().unwrap_or(Type::I64)),
+        ExprKind::String(text) => ("".to_string(), lower.backend.string(text), Type::Pointer(Box::new(Type::U8))),
+        ExprKind::Binary { op, op_span, lhs, rhs } => {
+            let (mut code, mut a, lhs_type) = number_codegen(lhs, ctx, lower);
+            let (code2, mut b, rhs_type) = number_codegen(rhs, ctx, lower);
+            code += &code2;
+            let type_ = binary_result_type(*op, lhs, lhs_type.clone(), rhs_type.clone(), expr.span, ctx);
+
+            if lhs_type.is_pointer() || rhs_type.is_pointer() {
+                // pointers move by whole elements
+                let moved = !op.is_comparison() && lhs_type.is_pointer() != rhs_type.is_pointer();
+                if moved && rhs_type.is_pointer() {
+                    a = scale_codegen(&a, &rhs_type, &mut code, ctx, lower);
+                }
+                if moved && lhs_type.is_pointer() {
+                    b = scale_codegen(&b, &lhs_type, &mut code, ctx, lower);
+                }
+                let pointer = if lhs_type.is_pointer() { &lhs_type } else { &rhs_type };
+                let (code2, mut value) = binary_op_codegen(*op, &a, &b, pointer, *op_span, ctx, lower);
+                code += &code2;
+                if lhs_type.is_pointer() && rhs_type.is_pointer() && *op == BinaryOp::Sub {
+                    let size = lower.backend.constant(ctx.structs.size_of(lhs_type.pointee().unwrap()) as u64);
+                    let (code2, elements) = operation(BinaryOp::Div, &value, &size, true, lower);
+                    code += &code2;
+                    value = elements;
+                }
+                (code, value, type_)
+            } else {
+                let (code2, value) = binary_op_codegen(*op, &a, &b, &binary_type(lhs, lhs_type, || rhs_type), *op_span, ctx, lower);
+                (code + &code2, value, type_)
+            }
+        }
+        ExprKind::Unary { op, operand } => {
+            let (mut code, value, type_) = number_codegen(operand, ctx, lower);
+            if type_.is_pointer() {
+                eprintln!("ERROR:{}: `{}` can't be applied to `{}`", ctx.sources.location(expr.span), op, type_);
+                std::process::exit(1);
+            }
+            let (code2, result) = match op {
+                UnaryOp::Neg => {
+                    // `-(-128i8)` doesn't fit in an i8, `-128i8` is a literal the lexer already checked
+                    let literal = matches!(operand.kind, ExprKind::Integer { .. });
+                    if ctx.overflow_checks && type_.is_signed() && !literal {
+                        let min = lower.backend.constant(signed_min(ctx.structs.size_of(&type_)) as u64);
+                        let (code2, overflow) = operation(BinaryOp::Eq, &value, &min, false, lower);
+                        code += &code2;
+                        code += &panic_if(&overflow, "attempt to negate with overflow", expr.span, ctx, lower);
+                    }
+                    let zero = lower.backend.constant(0);
+                    operation(BinaryOp::Sub, &zero, &value, false, lower)
+                }
+                UnaryOp::Not => {
+                    let ones = lower.backend.constant(u64::MAX);
+                    operation(BinaryOp::Xor, &value, &ones, false, lower)
+                }
+            };
+            code += &code2;
+            // `~0u8` is 255 and not the 64 bits of -1
+            if ctx.structs.size_of(&type_) < 8 {
+                code += &extend(&result, &result, &type_, ctx, lower);
+            }
+            (code, result, type_)
+        }
+        ExprKind::AddressOf(place) => {
+            if let Some(root) = root_variable(place) {
+                if let Some(Place::Global(Global { mutable: false, .. })) = ctx.lookup(root) {
+                    eprintln!("ERROR:{}: can't take the address of constant `{}`", ctx.sources.location(expr.span), root);
+                    std::process::exit(1);
+                }
+            }
+            let (code, address, type_) = address_codegen(place, ctx, lower);
+            (code, address, Type::Pointer(Box::new(type_)))
+        }
+        ExprKind::Variable(_) | ExprKind::Field { .. } | ExprKind::Deref(_) => {
+            if let ExprKind::Variable(name) = &expr.kind {
+                // a scalar const is an immediate
+                if let Some(Place::Global(Global { type_, value: Some(value), .. })) = ctx.lookup(name) {
+                    return ("".to_string(), lower.backend.constant(value), type_);
+                }
+            }
+            if let ExprKind::Variable(_) = &expr.kind {
+                if let (Location::Local(local), _, type_) = location_codegen(expr, ctx, lower) {
+                    // the value is read now, an assignment later in the expression doesn't change it
+                    let value = lower.backend.temporary();
+                    return (lower.backend.set(&value, &local), value, type_);
+                }
+            }
+            let (mut code, address, type_) = address_codegen(expr, ctx, lower);
+            let value = lower.backend.temporary();
+            code += &load(&value, &address, &type_, expr.span, ctx, lower);
+            (code, value, type_)
+        }
+        ExprKind::StructLiteral { name, .. } => {
+            eprintln!("ERROR:{}: a literal of struct `{}` can only be assigned to a variable or a field", ctx.sources.location(expr.span), name);
+            std::process::exit(1);
+        }
+        ExprKind::Assign { target, value } => {
+            let value_type = type_of(value, ctx);
+            // assigning to an unknown variable declares it in the current scope
+            if let ExprKind::Variable(name) = &target.kind {
+                if ctx.lookup(name).is_none() {
+                    declare(name, value_type, ctx, lower);
+                }
+            }
+            check_assignable(target, ctx);
+            let (location, mut code, target_type) = location_codegen(target, ctx, lower);
+            let address = match location {
+                Location::Local(local) => {
+                    let value_type = type_of(value, ctx);
+                    check_types(&target_type, value, &value_type, ctx);
+                    let (code2, value, _) = expr_codegen(value, ctx, lower);
+                    code += &code2;
+                    // the value of an assignment is the value stored, truncated to the type of target
+                    code += &extend(&local, &value, &target_type, ctx, lower);
+                    let result = lower.backend.temporary();
+                    code += &lower.backend.set(&result, &local);
+                    return (code, result, target_type);
+                }
+                Location::Memory(address) => address,
+            };
+
+            // a literal that reads the variable it is assigned to is built aside first
+            let reads_itself = matches!(value.kind, ExprKind::StructLiteral { .. })
+                && root_variable(target).is_some_and(|root| mentions_variable(value, root));
+            if reads_itself {
+                let aside = frame_address(ctx.allocate(&target_type), lower);
+                code += &store_codegen(&aside, value, &target_type, ctx, lower);
+                code += &lower.backend.copy(&address, &aside, ctx.structs.size_of(&target_type));
+            } else {
+                code += &store_codegen(&address, value, &target_type, ctx, lower);
+            }
+            if target_type.is_struct() {
+                return (code, lower.backend.constant(0), target_type);
+            }
+            let result = lower.backend.temporary();
+            code += &load(&result, &address, &target_type, expr.span, ctx, lower);
+            (code, result, target_type)
+        }
+        ExprKind::CompoundAssign { op, op_span, target, value } => compound_codegen(*op, *op_span, target, value, false, ctx, lower),
+        ExprKind::Postfix { op, op_span, target } => {
+            let one = Expr::new(ExprKind::Integer { value: 1, type_: None }, expr.span);
+            compound_codegen(*op, *op_span, target, &one, true, ctx, lower)
+        }
+        ExprKind::Call { name, args } => {
+            let type_ = type_of(expr, ctx);
+            if ctx.function(name, expr.span).returns.is_none() {
+                let code = call_codegen(None, name, args, expr.span, ctx, lower);
+                return (code, lower.backend.constant(0), type_);
+            }
+            // the value of a call returning a struct is the address of the struct
+            let result = lower.backend.temporary();
+            let mut code = call_codegen(Some(&result), name, args, expr.span, ctx, lower);
+            if !type_.is_struct() && ctx.structs.size_of(&type_) < 8 {
+                code += &extend(&result, &result, &type_, ctx, lower);
+            }
+            (code, result, type_)
+        }
+    }
+}
+
+/*
+ * Return the code applying op to the values a and b and its result
+ * type_ is the type of the operands, its sign chooses between the signed and unsigned operations
+ */
+fn binary_op_codegen<B: Backend>(op: BinaryOp, a: &str, b: &str, type_: &Type, span: Span, ctx: &mut Context, lower: &mut Lowering<B>) -> (String, String) {
+    let signed = type_.is_signed();
+    let size = ctx.structs.size_of(type_);
+    match op {
+        // pointer arithmetic is not checked, a pointer is an address and not a number
+        BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul if ctx.overflow_checks && !type_.is_pointer() => {
+            let message = match op {
+                BinaryOp::Add => "attempt to add with overflow",
+                BinaryOp::Sub => "attempt to subtract with overflow",
+                _ => "attempt to multiply with overflow",
+            };
+            let mut code = "".to_string();
+            if size < 8 {
+                // the operands are taken on the size of type_, the exact result has to fit in it
+                let (a2, b2) = (lower.backend.temporary(), lower.backend.temporary());
+                code += &extend(&a2, a, type_, ctx, lower);
+                code += &extend(&b2, b, type_, ctx, lower);
+                let (code2, r) = operation(op, &a2, &b2, signed, lower);
+                code += &code2;
+                let truncated = lower.backend.temporary();
+                code += &extend(&truncated, &r, type_, ctx, lower);
+                let (code2, overflow) = operation(BinaryOp::Ne, &truncated, &r, false, lower);
+                code += &code2;
+                code += &panic_if(&overflow, message, span, ctx, lower);
+                return (code, r);
+            }
+            let (code2, r) = operation(op, a, b, signed, lower);
+            code += &code2;
+            let zero = lower.backend.constant(0);
+            match (op, signed) {
+                // `+`: the sign of the result is neither the one of a nor the one of b
+                // `-`: the signs of a and b differ and the one of the result isn't the one of a
+                (BinaryOp::Add, true) | (BinaryOp::Sub, true) => {
+                    let (code2, x) = operation(BinaryOp::Xor, a, &r, false, lower);
+                    code += &code2;
+                    let (code2, y) = if op == BinaryOp::Add { operation(BinaryOp::Xor, b, &r, false, lower) } else { operation(BinaryOp::Xor, a, b, false, lower) };
+                    code += &code2;
+                    let (code2, both) = operation(BinaryOp::And, &x, &y, false, lower);
+                    code += &code2;
+                    let (code2, overflow) = operation(BinaryOp::Lt, &both, &zero, true, lower);
+                    code += &code2;
+                    code += &panic_if(&overflow, message, span, ctx, lower);
+                }
+                (BinaryOp::Add, false) => {
+                    let (code2, overflow) = operation(BinaryOp::Lt, &r, a, false, lower);
+                    code += &code2;
+                    code += &panic_if(&overflow, message, span, ctx, lower);
+                }
+                (BinaryOp::Sub, false) => {
+                    let (code2, overflow) = operation(BinaryOp::Lt, a, b, false, lower);
+                    code += &code2;
+                    code += &panic_if(&overflow, message, span, ctx, lower);
+                }
+                // dividing the result by a doesn't give b back, the smallest i64 divided by -1 wouldn't fit
+                (_, true) => {
+                    let minus_one = lower.backend.constant(u64::MAX);
+                    let min = lower.backend.constant(i64::MIN as u64);
+                    let (code2, is_minus_one) = operation(BinaryOp::Eq, a, &minus_one, false, lower);
+                    code += &code2;
+                    code += &lower.backend.if_nonzero(&is_minus_one);
+                    let (code2, overflow) = operation(BinaryOp::Eq, b, &min, false, lower);
+                    code += &code2;
+                    code += &panic_if(&overflow, message, span, ctx, lower);
+                    code += &lower.backend.if_else();
+                    code += &lower.backend.if_nonzero(a);
+                    let (code2, quotient) = operation(BinaryOp::Div, &r, a, true, lower);
+                    code += &code2;
+                    let (code2, overflow) = operation(BinaryOp::Ne, &quotient, b, false, lower);
+                    code += &code2;
+                    code += &panic_if(&overflow, message, span, ctx, lower);
+                    code += &lower.backend.if_end();
+                    code += &lower.backend.if_end();
+                }
+                (_, false) => {
+                    code += &lower.backend.if_nonzero(a);
+                    let (code2, quotient) = operation(BinaryOp::Div, &r, a, false, lower);
+                    code += &code2;
+                    let (code2, overflow) = operation(BinaryOp::Ne, &quotient, b, false, lower);
+                    code += &code2;
+                    code += &panic_if(&overflow, message, span, ctx, lower);
+                    code += &lower.backend.if_end();
+                }
+            }
+            return (code, r);
+        }
+        BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul => {
+            let (mut code, r) = operation(op, a, b, signed, lower);
+            if size < 8 {
+                code += &extend(&r, &r, type_, ctx, lower);
+            }
+            return (code, r);
+        }
+        BinaryOp::Div | BinaryOp::Rem => {
+            let (zero, overflow) = if op == BinaryOp::Div {
+                ("attempt to divide by zero", "attempt to divide with overflow")
+            } else {
+                ("attempt to calculate the remainder with a divisor of zero", "attempt to calculate the remainder with overflow")
+            };
+            let mut code = lower.backend.if_zero(b);
+            code += &panic_codegen(zero, span, ctx, lower);
+            code += &lower.backend.if_end();
+            if signed {
+                // the smallest value of type_ divided by -1 doesn't fit either
+                let minus_one = lower.backend.constant(u64::MAX);
+                let min = lower.backend.constant(signed_min(size) as u64);
+                let (code2, is_minus_one) = operation(BinaryOp::Eq, b, &minus_one, false, lower);
+                code += &code2;
+                let (code2, is_min) = operation(BinaryOp::Eq, a, &min, false, lower);
+                code += &code2;
+                let (code2, both) = operation(BinaryOp::And, &is_minus_one, &is_min, false, lower);
+                code += &code2;
+                code += &panic_if(&both, overflow, span, ctx, lower);
+            }
+            let (code2, r) = operation(op, a, b, signed, lower);
+            code += &code2;
+            if size < 8 {
+                code += &extend(&r, &r, type_, ctx, lower);
+            }
+            return (code, r);
+        }
+        // `1u8 << 9` is 0, the bits shifted out of type_ are lost
+        BinaryOp::Shl | BinaryOp::Shr => {
+            let (mut code, r) = operation(op, a, b, signed, lower);
+            if size < 8 {
+                code += &extend(&r, &r, type_, ctx, lower);
+            }
+            return (code, r);
+        }
+        _ => return operation(op, a, b, signed, lower),
+    }
+}
+
+/*
+ * the offset value multiplied by the size of what pointer points to
+ */
+fn scale_codegen<B: Backend>(value: &str, pointer: &Type, code: &mut String, ctx: &Context, lower: &mut Lowering<B>) -> String {
+    let size = ctx.structs.size_of(pointer.pointee().unwrap());
+    if size == 1 {
+        return value.to_string();
+    }
+    let size = lower.backend.constant(size as u64);
+    let (code2, scaled) = operation(BinaryOp::Mul, value, &size, false, lower);
+    *code += &code2;
+    return scaled;
+}
+
+/*
+ * `target op= value`, or `target++` and `target--` when keep_old is set (value is then the literal 1)
+ * the address of target is computed once, the value is the one stored, or the one before for keep_old
+ */
+fn compound_codegen<B: Backend>(op: BinaryOp, op_span: Span, target: &Expr, value: &Expr, keep_old: bool, ctx: &mut Context, lower: &mut Lowering<B>) -> (String, String, Type) {
+    check_assignable(target, ctx);
+    let (location, mut code, type_) = location_codegen(target, ctx, lower);
+    if type_.is_struct() {
+        eprintln!("ERROR:{}: a value of type `{}` can't be used as a number", ctx.sources.location(target.span), type_);
+        std::process::exit(1);
+    }
+    let old = lower.backend.temporary();
+    match &location {
+        Location::Local(local) => code += &lower.backend.set(&old, local),
+        Location::Memory(address) => code += &load(&old, address, &type_, target.span, ctx, lower),
+    }
+
+    let (code2, mut operand, value_type) = number_codegen(value, ctx, lower);
+    code += &code2;
+    if type_.is_pointer() {
+        binary_result_type(op, target, type_.clone(), value_type.clone(), value.span, ctx);
+        if value_type.is_pointer() {
+            eprintln!("ERROR:{}: mismatched types: expected an integer, found `{}`", ctx.sources.location(value.span), value_type);
+            std::process::exit(1);
+        }
+        operand = scale_codegen(&operand, &type_, &mut code, ctx, lower);
+    } else if value_type.is_pointer() {
+        eprintln!("ERROR:{}: `{}=` can't be applied to `{}` and `{}`", ctx.sources.location(value.span), op, type_, value_type);
+        std::process::exit(1);
+    }
+    let (code2, result) = binary_op_codegen(op, &old, &operand, &type_, op_span, ctx, lower);
+    code += &code2;
+
+    // the value of an assignment is the value stored, truncated to the type of target
+    let stored = if keep_old { old } else { lower.backend.temporary() };
+    match &location {
+        Location::Local(local) => {
+            code += &extend(local, &result, &type_, ctx, lower);
+            if !keep_old {
+                code += &lower.backend.set(&stored, local);
+            }
+        }
+        Location::Memory(address) => {
+            code += &store(address, &result, &type_, ctx, lower);
+            if !keep_old {
+                code += &load(&stored, address, &type_, target.span, ctx, lower);
+            }
+        }
+    }
+    return (code, stored, type_);
+}
+
+/*
+ * Return the code computing the arguments of a call to the function name and the call, its value if it returns one
+ * goes to destination
+ */
+fn call_codegen<B: Backend>(destination: Option<&str>, name: &str, args: &[Expr], span: Span, ctx: &mut Context, lower: &mut Lowering<B>) -> String {
+    let params = ctx.function(name, span).params.clone();
+    if ctx.function(name, span).linkage == (Linkage::C { variadic: true }) {
+        if args.len() < params.len() {
+            eprintln!("ERROR:{}: function `{}` takes at least {} arguments but {} were given", ctx.sources.location(span), name, params.len(), args.len());
+            std::process::exit(1);
+        }
+    } else if args.len() != params.len() {
+        eprintln!("ERROR:{}: function `{}` takes {} arguments but {} were given", ctx.sources.location(span), name, params.len(), args.len());
+        std::process::exit(1);
+    }
+    let mut code = "".to_string();
+    let mut values = vec![];
+    for (i, arg) in args.iter().enumerate() {
+        if let Some(param @ Type::Struct(_)) = params.get(i) {
+            // the callee gets the address of a copy, it can't see the changes the next arguments make
+            let aside = frame_address(ctx.allocate(param), lower);
+            code += &store_codegen(&aside, arg, param, ctx, lower);
+            values.push((aside, param.clone()));
+            continue;
+        }
+        let (code2, value, type_) = number_codegen(arg, ctx, lower);
+        if let Some(param) = params.get(i) {
+            check_types(param, arg, &type_, ctx);
+        }
+        code += &code2;
+        values.push((value, type_));
+    }
+    // a struct is returned through a pointer given as the first argument
+    if let Some(type_ @ Type::Struct(_)) = ctx.function(name, span).returns.clone() {
+        let aside = frame_address(ctx.allocate(&type_), lower);
+        values.insert(0, (aside, Type::Pointer(Box::new(type_))));
+    }
+    if ctx.function(name, span).linkage == Linkage::Builtin {
+        ctx.use_runtime(name);
+    }
+    let location = format!("{}: ", ctx.sources.location(span));
+    code += &lower.backend.call(destination, name, ctx.function(name, span), &values, &location);
+    return code;
+}
+
+/*
+ * expr_codegen for the operands of arithmetic, exit with an error if expr is a struct
+ */
+fn number_codegen<B: Backend>(expr: &Expr, ctx: &mut Context, lower: &mut Lowering<B>) -> (String, String, Type) {
+    let (code, value, type_) = expr_codegen(expr, ctx, lower);
+    if type_.is_struct() {
+        eprintln!("ERROR:{}: a value of type `{}` can't be used as a number", ctx.sources.location(expr.span), type_);
+        std::process::exit(1);
+    }
+    return (code, value, type_);
+}
+
+/*
+ * Take a statement and return its code, the backend puts its location next to it
+ */
+pub fn stmt_codegen<B: Backend>(stmt: &Stmt, ctx: &mut Context, lower: &mut Lowering<B>) -> String {
+    lower.backend.statement_start();
+    let mut code = "".to_string();
+    match &stmt.kind {
+        StmtKind::Expr(Expr { kind: ExprKind::Call { name, args }, span }) => {
+            // the value, if any, is dropped
+            code += &call_codegen(None, name, args, *span, ctx, lower);
+        }
+        StmtKind::Expr(expr) => {
+            let (code2, value, _) = expr_codegen(expr, ctx, lower);
+            code += &code2;
+            code += &lower.backend.discard(&value);
+        }
+        StmtKind::Return(value) => {
+            if !lower.in_function {
+                eprintln!("ERROR:{}: `return` outside of a function", ctx.sources.location(stmt.span));
+                std::process::exit(1);
+            }
+            match (value, ctx.return_type.clone()) {
+                // stored where the caller said, and that address is returned
+                (Some(value), Some(type_ @ Type::Struct(_))) => {
+                    let pointer = lower.return_pointer.clone().expect("a function returning a struct has a return pointer");
+                    code += &store_codegen(&pointer, value, &type_, ctx, lower);
+                    code += &lower.backend.ret(Some(&pointer));
+                }
+                (Some(value), Some(type_)) => {
+                    let (code2, value2, value_type) = number_codegen(value, ctx, lower);
+                    check_types(&type_, value, &value_type, ctx);
+                    code += &code2;
+                    code += &lower.backend.ret(Some(&value2));
+                }
+                (None, None) => code += &lower.backend.ret(None),
+                (Some(value), None) => {
+                    eprintln!("ERROR:{}: this function returns no value", ctx.sources.location(value.span));
+                    std::process::exit(1);
+                }
+                (None, Some(type_)) => {
+                    eprintln!("ERROR:{}: expected a value of type `{}` after `return`", ctx.sources.location(stmt.span), type_);
+                    std::process::exit(1);
+                }
+            }
+        }
+        StmtKind::Put(expr) => {
+            let (code2, value, type_) = number_codegen(expr, ctx, lower);
+            code += &code2;
+            code += &lower.backend.put(&value, type_.is_signed());
+            ctx.use_runtime("put");
+        }
+        StmtKind::Assert { condition, message } => {
+            let (code2, value, _) = number_codegen(condition, ctx, lower);
+            let message = match message {
+                Some(message) => format!("assertion failed: {message}"),
+                None => "assertion failed".to_string(),
+            };
+            code += &code2;
+            code += &lower.backend.if_zero(&value);
+            code += &panic_codegen(&message, stmt.span, ctx, lower);
+            code += &lower.backend.if_end();
+        }
+        StmtKind::Let { name, type_, value } => {
+            // the value is computed before the variable exists: `let x = x + 1;` reads the outer x
+            let type_ = type_.clone().unwrap_or_else(|| type_of(value, ctx));
+            let offset = ctx.allocate(&type_);
+            bind(name, offset, &type_, lower);
+            match lower.variable_locals.get(&offset).cloned() {
+                Some(local) => {
+                    let value_type = type_of(value, ctx);
+                    check_types(&type_, value, &value_type, ctx);
+                    let (code2, value, _) = expr_codegen(value, ctx, lower);
+                    code += &code2;
+                    code += &extend(&local, &value, &type_, ctx, lower);
+                }
+                None => code += &store_codegen(&frame_address(offset, lower), value, &type_, ctx, lower),
+            }
+            ctx.bind(name.clone(), Variable { offset, type_ });
+        }
+        StmtKind::Block(stmts) => {
+            ctx.enter_scope();
+            for stmt in stmts {
+                code += &stmt_codegen(stmt, ctx, lower);
+            }
+            ctx.exit_scope();
+        }
+        StmtKind::If { condition, then, otherwise } => {
+            let (code2, value, _) = number_codegen(condition, ctx, lower);
+            code += &code2;
+            code += &lower.backend.if_nonzero(&value);
+            code += &stmt_codegen(then, ctx, lower);
+            if let Some(otherwise) = otherwise {
+                code += &lower.backend.if_else();
+                code += &stmt_codegen(otherwise, ctx, lower);
+            }
+            code += &lower.backend.if_end();
+        }
+        StmtKind::While { condition, body } => {
+            code += &lower.backend.loop_start();
+            let (code2, value, _) = number_codegen(condition, ctx, lower);
+            code += &code2;
+            code += &lower.backend.loop_exit_if_zero(&value);
+            code += &stmt_codegen(body, ctx, lower);
+            code += &lower.backend.loop_end();
+        }
+    }
+    let location = ctx.sources.location(stmt.span).to_string();
+    return lower.backend.statement_end(&location, code);
+}
+
+/*
+ * the state to translate a function: its variables and its locals start empty
+ */
+pub fn function_start<B: Backend>(body: &[Stmt], ctx: &mut Context, lower: &mut Lowering<B>) {
+    ctx.clear_variables();
+    ctx.stack_size = 0;
+    ctx.frame_size = 0;
+    lower.addressed.clear();
+    for stmt in body {
+        addressed_variables(stmt, &mut lower.addressed);
+    }
+    lower.params.clear();
+    lower.locals.clear();
+    lower.taken.clear();
+    lower.variable_locals.clear();
+    lower.frame_used = false;
+}
+
+/*
+ * the Body of the function translated since function_start, its code being code
+ */
+pub fn body<B: Backend>(code: String, ctx: &Context, lower: &Lowering<B>) -> Body {
+    return Body { params: lower.params.clone(), locals: lower.locals.clone(), frame_size: lower.frame_used.then_some(ctx.frame_size), code };
+}
+
+/*
+ * Take a function and return it written by the backend, the parameters narrower than 64 bits are truncated to their
+ * type and the ones whose address is taken are copied to the frame
+ * a struct parameter is the address of a copy made by the caller, the struct is copied again to the frame, and a
+ * function returning a struct gets first the address to store it at, and returns it
+ */
+pub fn fn_codegen<B: Backend>(decl: &FnDecl, ctx: &mut Context, lower: &mut Lowering<B>) -> String {
+    function_start(std::slice::from_ref(&decl.body), ctx, lower);
+    ctx.return_type = decl.returns.clone();
+    lower.in_function = true;
+
+    let mut code = "".to_string();
+    ctx.enter_scope();
+    // `return` is a keyword, no parameter has its local
+    lower.return_pointer = decl.returns.as_ref().is_some_and(Type::is_struct).then(|| lower.backend.local("return", 1));
+    if let Some(pointer) = lower.return_pointer.clone() {
+        lower.param(pointer);
+    }
+    for (name, type_, _) in &decl.params {
+        let param = lower.backend.local(name, 1);
+        lower.param(param.clone());
+        let offset = ctx.declare(name.clone(), type_.clone());
+        if type_.is_struct() {
+            let address = frame_address(offset, lower);
+            code += &lower.backend.copy(&address, &param, ctx.structs.size_of(type_));
+        } else if lower.addressed.contains(name) {
+            let address = frame_address(offset, lower);
+            code += &store(&address, &param, type_, ctx, lower);
+        } else {
+            lower.variable_locals.insert(offset, param.clone());
+            if ctx.structs.size_of(type_) < 8 {
+                code += &extend(&param, &param, type_, ctx, lower);
+            }
+        }
+    }
+    code += &stmt_codegen(&decl.body, ctx, lower);
+    ctx.exit_scope();
+    // falling off the end of the function returns the address too
+    if let Some(pointer) = lower.return_pointer.take() {
+        code += &lower.backend.ret(Some(&pointer));
+    }
+
+    ctx.return_type = None;
+    lower.in_function = false;
+    let location = ctx.sources.location(decl.span).to_string();
+    let body = body(code, ctx, lower);
+    return lower.backend.function(decl, &location, body);
+}
+
+/*
+ * the bytes of a value of type type_ in the memory, a struct value is a literal and its padding is zeroed
+ */
+fn data_bytes(type_: &Type, value: &Expr, ctx: &Context) -> Vec<u8> {
+    let value_type = type_of(value, ctx);
+    check_types(type_, value, &value_type, ctx);
+    let Type::Struct(name) = type_ else {
+        let size = ctx.structs.size_of(type_) as usize;
+        return truncate(const_eval(value, ctx), type_).to_le_bytes()[..size].to_vec();
+    };
+    let ExprKind::StructLiteral { fields, .. } = &value.kind else {
+        eprintln!("ERROR:{}: the value of a global struct must be a struct literal", ctx.sources.location(value.span));
+        std::process::exit(1);
+    };
+    check_struct_literal(name, fields, ctx);
+    let layout = ctx.structs.layout(name).clone();
+    let mut bytes = vec![0; layout.size as usize];
+    for field in &layout.fields {
+        let field_bytes = data_bytes(&field.type_, literal_field(name, fields, &field.name, value.span, ctx), ctx);
+        let offset = field.offset as usize;
+        bytes[offset..offset + field_bytes.len()].copy_from_slice(&field_bytes);
+    }
+    return bytes;
+}
+
+/*
+ * evaluate the globals in declaration order and give the backend the ones that aren't folded
+ */
+pub fn globals_codegen<B: Backend>(decls: &[GlobalDecl], ctx: &mut Context, lower: &mut Lowering<B>) {
+    for (i, decl) in decls.iter().enumerate() {
+        let type_ = global_type(decls, i, ctx);
+        let mut value = None;
+        let bytes = match &decl.value {
+            Some(expr) if !decl.mutable && !type_.is_struct() => {
+                value = Some(truncate(const_eval(expr, ctx), &type_));
+                None
+            }
+            Some(expr) => Some(data_bytes(&type_, expr, ctx)),
+            None => None,
+        };
+        if value.is_none() {
+            let location = ctx.sources.location(decl.span).to_string();
+            let address = lower.backend.global(&decl.name, ctx.structs.size_of(&type_), ctx.structs.align_of(&type_), bytes, &location);
+            lower.globals.insert(decl.name.clone(), address);
+        }
+        ctx.globals.insert(decl.name.clone(), Global { type_, mutable: decl.mutable, value });
+    }
+}
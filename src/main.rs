@@ -1,142 +1,165 @@
+#![allow(clippy::needless_return, clippy::upper_case_acronyms, clippy::enum_variant_names)]
+
+mod ast;
+mod c;
+mod lexer;
+mod lower;
+mod modules;
+mod runtime;
+mod source;
+mod target;
+mod wasm;
+
+use ast::{BinaryOp, Expr, ExprKind, ExternDecl, FnDecl, GlobalDecl, ImportDecl, Program, Stmt, StmtKind, StructDecl, TestDecl, Type, UnaryOp};
+use lexer::{Token, TokenType};
+use modules::Module;
+use source::{SourceMap, Span};
+use std::collections::{HashMap, HashSet};
 use std::fs;
+use std::process::Command;
+use target::{Section, Target};
 
-#[derive(Copy, PartialEq, Clone, Debug)]
-enum Operators {
-    Plus,
-    Put,
-    Mult,
-    Div,
-    Minus,
-    Assign,
+#[derive(Debug, Clone)]
+struct Field {
+    name: String,
+    type_: Type,
+    offset: u32,
 }
 
-impl core::fmt::Display for Operators {
-    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
-        match *self {
-            Self::Plus => write!(f, "+"),
-            Self::Put => write!(f, "put"),
-            Self::Minus => write!(f, "-"),
-            Self::Div => write!(f, "/"),
-            Self::Mult => write!(f, "*"),
-            Self::Assign => write!(f, "="),
-        }
-    }
+/*
+ * fields are kept in declaration order, each one at an offset aligned on its own alignment
+ * size is rounded up to the alignment of the struct so arrays of structs stay aligned
+ */
+#[derive(Debug, Clone)]
+struct StructLayout {
+    size: u32,
+    align: u32,
+    fields: Vec<Field>,
+    // the index of each field in fields, by name
+    indices: HashMap<String, usize>,
 }
 
-#[derive(PartialEq, Clone, Debug)]
-enum Literals {
-    EmptyLiterals,
-    Operator(Operators),
-    Integer(u64),
-    Word(String), 
-}
-/*
-impl Literals {
-    fn is_operator(&self) -> bool {
-        match &*self {
-            Literals::Operator(_) => true,
-            _ => false,
-        }
+impl StructLayout {
+    fn field(&self, name: &str) -> Option<&Field> {
+        self.indices.get(name).map(|&i| &self.fields[i])
     }
-    fn is_integer(&self) -> bool {
-        match &*self {
-            Literals::Integer(_) => true,
-            _ => false,
-        }
-    }
-
 }
-*/
 
-impl core::fmt::Display for Literals {
-    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
-        match *self {
-            Self::EmptyLiterals => write!(f, "EMPTYLITERALS (ERROR)"),
-            Self::Integer(int) => write!(f, "{}", int),
-            Self::Operator(op) => write!(f, "{}", op),
-            Self::Word(w) => write!(f, "{}", w),
-        }
-    }
+fn align_up(value: u32, align: u32) -> u32 {
+    value.div_ceil(align) * align
 }
 
-#[derive(Debug, PartialEq, Clone)]
-struct AST {
-    node: Literals,
-    right_node: Option<Box<AST>>,
-    left_node: Option<Box<AST>>,
+/*
+ * Layouts of all the structs of the program, indexed by name
+ */
+struct StructTable {
+    layouts: HashMap<String, StructLayout>,
 }
 
-impl AST {
-    fn is_empty(self) -> bool{
-        if self.left_node.is_none() && self.right_node.is_none() && self.node == Literals::EmptyLiterals {
-            return true
+impl StructTable {
+    fn new(decls: &[StructDecl], sources: &SourceMap) -> StructTable {
+        let mut table = StructTable { layouts: HashMap::new() };
+        let mut by_name: HashMap<&str, &StructDecl> = HashMap::new();
+        for decl in decls {
+            if let Some(first) = by_name.insert(&decl.name, decl) {
+                eprintln!("ERROR:{}: struct `{}` is already declared at {}", sources.location(decl.span), decl.name, sources.location(first.span));
+                std::process::exit(1);
+            }
         }
-        false
-    }
-    
-    fn root(self) -> Literals {
-        return self.node;
-    }
-
-    fn lhs(self) -> AST {
-        return *self.left_node.expect("ERROR: AST was empty");
-    }
-    
-    fn rhs(self) -> AST {
-        return *self.right_node.expect("ERROR: AST was empty");
+        for decl in decls {
+            table.compute_layout(decl, &by_name, &mut vec![], sources);
+        }
+        table
     }
 
-    fn new(literal: Literals, lhs: AST, rhs: AST) -> AST {
-        AST{ 
-            node: literal,
-            left_node: Some(Box::new(lhs)),
-            right_node: Some(Box::new(rhs)),
+    /*
+     * compute the layout of decl, and before it the layouts of the structs it contains
+     * visiting holds the structs being computed, to catch structs that contain themselves
+     */
+    fn compute_layout(&mut self, decl: &StructDecl, decls: &HashMap<&str, &StructDecl>, visiting: &mut Vec<String>, sources: &SourceMap) {
+        if self.layouts.contains_key(&decl.name) {
+            return;
         }
-    }
+        if visiting.contains(&decl.name) {
+            eprintln!("ERROR:{}: struct `{}` contains itself and has an infinite size", sources.location(decl.span), decl.name);
+            std::process::exit(1);
+        }
+        visiting.push(decl.name.clone());
 
-    fn create_empty() -> AST {
-        AST {
-            node: Literals::EmptyLiterals,
-            left_node: None,
-            right_node: None,
+        let mut size = 0;
+        let mut align = 1;
+        let mut fields: Vec<Field> = vec![];
+        let mut indices: HashMap<String, usize> = HashMap::new();
+        for (name, type_, span) in &decl.fields {
+            if indices.insert(name.clone(), fields.len()).is_some() {
+                eprintln!("ERROR:{}: field `{}` is declared twice in struct `{}`", sources.location(*span), name, decl.name);
+                std::process::exit(1);
+            }
+            // a struct can point to itself, only the structs it contains directly need a layout first
+            let mut base = type_;
+            while let Type::Pointer(pointee) = base {
+                base = pointee;
+            }
+            if let Type::Struct(inner) = base {
+                match decls.get(inner.as_str()) {
+                    Some(inner_decl) if base == type_ => self.compute_layout(inner_decl, decls, visiting, sources),
+                    Some(_) => {}
+                    None => {
+                        eprintln!("ERROR:{}: unknown type `{}`", sources.location(*span), inner);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            let field_align = self.align_of(type_);
+            let offset = align_up(size, field_align);
+            size = offset + self.size_of(type_);
+            align = align.max(field_align);
+            fields.push(Field { name: name.clone(), type_: type_.clone(), offset });
         }
-    }
-/*
-    fn modify_root(mut self, node: Literals) {
-        self.node = node;
+
+        visiting.pop();
+        self.layouts.insert(decl.name.clone(), StructLayout { size: align_up(size, align), align, fields, indices });
     }
 
-    fn modify_lhs(mut self, lhs: AST) {
-        self.left_node = Some(Box::new(lhs));
+    fn layout(&self, name: &str) -> &StructLayout {
+        match self.layouts.get(name) {
+            Some(layout) => layout,
+            None => {
+                eprintln!("ERROR: unknown struct `{}`", name);
+                std::process::exit(1);
+            }
+        }
     }
 
-    fn modify_rhs(mut self, rhs: AST) {
-        self.right_node = Some(Box::new(rhs));
+    fn size_of(&self, type_: &Type) -> u32 {
+        match type_ {
+            Type::I8 | Type::U8 => 1,
+            Type::I16 | Type::U16 => 2,
+            Type::I32 | Type::U32 => 4,
+            Type::I64 | Type::U64 | Type::Pointer(_) => 8,
+            Type::Struct(name) => self.layout(name).size,
+        }
     }
 
-    fn print(self) {
-        if !self.clone().is_empty() {
-            print!("("); 
-            self.clone().lhs().print();
-            print!(" {} ", self.node);
-            self.clone().rhs().print(); print!(")");
+    fn align_of(&self, type_: &Type) -> u32 {
+        match type_ {
+            Type::Struct(name) => self.layout(name).align,
+            _ => self.size_of(type_),
         }
     }
-    */
 }
 
 #[derive(Clone, Copy)]
 struct ScratchRegisterManagement {
-    in_use: [bool; 7],
+    in_use: [bool; target::SCRATCH_COUNT],
 }
 
-
 impl ScratchRegisterManagement {
     /*
      * search an empty register and return is index
      */
     fn scratch_alloc(&mut self) -> u8 {
-        for i in 0..REG_NAMES.len() {
+        for i in 0..self.in_use.len() {
             if !self.in_use[i] {
                 self.in_use[i] = true;
                 return i as u8;
@@ -149,16 +172,9 @@ impl ScratchRegisterManagement {
     /*
      * free the state of the register in the bool array
      */
-    fn scratch_free(mut self, r: u8) {
+    fn scratch_free(&mut self, r: u8) {
         self.in_use[r as usize] = false;
     }
-
-    /*
-     * return the name of the register via his array
-     */
-    fn scratch_name(self, r: u8) -> String {
-        REG_NAMES[r as usize].to_string()
-    }
 }
 #[derive(Copy, Clone)]
 struct LabelGenerator {
@@ -176,697 +192,2658 @@ impl LabelGenerator {
     }
 }
 
-const REG_NAMES: [&str; 7] = ["rbx", "r10", "r11","r12", "r13", "r14", "r15"];
-static mut SRM: ScratchRegisterManagement = ScratchRegisterManagement { in_use: [false;7] };
-
 /*
- * UNSAFE: needs the state of the bool array
- *
- * Take an AST and return his equivalent in assembly as a String
- * get u8 to keep track of the registers of the childs nodes
- *
+ * the assembly name of a function or of a global of the program,
+ * prefixed so it can't be mistaken for a register, an instruction or a runtime label
  */
-unsafe fn expr_codegen(ast: AST, var: &std::collections::HashMap<String, u32>, label_gen: LabelGenerator) -> (u8, String) {
-    //println!("{:?}", SRM.in_use); // TODO no more Int after 7 in a row
-
-    if ast.clone().is_empty() {
-        return (0, "".to_string());
-    }
-    else {
-        match ast.clone().root() {
-            Literals::EmptyLiterals => {
-                eprintln!("ERROR: EmptyLiterals in expr_codegen()"); 
-                std::process::exit(1);
-            }
-            Literals::Integer(int) => {
-                let regu = SRM.scratch_alloc();
-                let reg = SRM.scratch_name(regu); 
-                return (regu, format!("        mov    {reg}, {int}\n"));
-            }
-            Literals::Operator(Operators::Plus) => {
-                let lhs = ast.clone().lhs();
-                let rhs = ast.clone().rhs();
-                let (regle, mut code) = expr_codegen(lhs, var, label_gen);
-                let (regri, code2)    = expr_codegen(rhs, var, label_gen);
-                
-                code += &code2; 
-
-                let reg_left = SRM.scratch_name(regle);
-                let reg_right = SRM.scratch_name(regri);
-                code += &format!("        add    {reg_right}, {reg_left}\n");
-                SRM.scratch_free(regle);
-                return (regri, code);
-            }
-            Literals::Operator(Operators::Minus) => {
-                let lhs = ast.clone().lhs();
-                let rhs = ast.clone().rhs();
-                let (regle, mut code) = expr_codegen(lhs, var, label_gen);
-                let (regri, code2)    = expr_codegen(rhs, var, label_gen);
-                
-                code += &code2; 
-
-                let reg_left = SRM.scratch_name(regle);
-                let reg_right = SRM.scratch_name(regri);
-                code += &format!("        sub    {reg_left}, {reg_right}\n");
-                SRM.scratch_free(regri);
-                return (regle, code);
-
-            },
-            Literals::Operator(Operators::Mult) => {
-                let lhs = ast.clone().lhs();
-                let rhs = ast.clone().rhs();
-                let (regle, mut code) = expr_codegen(lhs, var, label_gen);
-                let (regri, code2)    = expr_codegen(rhs, var, label_gen);
-                
-                code += &code2; 
-
-                let reg_left = SRM.scratch_name(regle);
-                let reg_right = SRM.scratch_name(regri);
-                code += &format!("        mov    rax, {reg_right}\n");
-                code += &format!("        mul    {reg_left}\n");
-                code += &format!("        mov    {reg_right}, rax\n");
-                SRM.scratch_free(regle);
-                return (regri, code);
-            },
-            Literals::Operator(Operators::Div) => {
-                let lhs = ast.clone().lhs();
-                let rhs = ast.clone().rhs();
-                let (regle, mut code) = expr_codegen(lhs, var, label_gen);
-                let (regri, code2)    = expr_codegen(rhs, var, label_gen);
-                
-                code += &code2; 
-
-                let reg_left = SRM.scratch_name(regle);
-                let reg_right = SRM.scratch_name(regri);
-                code += &format!("        mov    rdx, 0\n");
-                code += &format!("        mov    rax, {reg_left}\n");
-                code += &format!("        div    {reg_right}\n");
-                code += &format!("        mov    {reg_left}, rax\n");
-                SRM.scratch_free(regri);
-                return (regle, code);
-            },
-            Literals::Operator(Operators::Put) => {
-                let (regri, mut code) = expr_codegen(ast.clone().rhs(), var, label_gen);
-                let reg_right = SRM.scratch_name(regri);
-                code += &format!("        mov    rdi, {reg_right}\n");
-                code += &format!("        call put\n");
-                SRM.scratch_free(regri);
-                return (0, code);
-            }
-            Literals::Word(w) => {
-                let value = var.get_key_value(&w);
-                if value == None { 
-                    var.insert(w, generate_var());
-                }
-                return (0, )  
-            },
-            Literals::Operator(Operators::Assign) => {
-                todo!("Lit::Op(Ops::Assign) in generate_code")
-            },
-        }
-    }
+fn symbol(name: &str) -> String {
+    format!("stem.{}", name.replace("::", "."))
 }
 
 /*
- * Take a Vector<AST> (the program) and return a String (all the program as assembly)
+ * a variable lives offset bytes below the frame pointer, in the stack frame of _start or of its function
+ * the slot is given back when the scope of the variable ends
  */
-fn generate_code(program: Vec<AST>) -> String {
-    let mut label_gen: LabelGenerator = LabelGenerator { counter: 1 };
-    let mut code = "".to_string();
-    println!("\n");
-    let header = "
-BITS 64
-%define SYS_EXIT 60
-segment .text
-global _start
-put:
-        push    rbp
-        mov     rbp, rsp
-        sub     rsp, 64
-        mov     QWORD [rbp-56], rdi
-        mov     DWORD [rbp-4], 1
-        mov     eax, DWORD [rbp-4]
-        cdqe
-        mov     edx, 32
-        sub     rdx, rax
-        mov     BYTE [rbp-48+rdx], 10
-.L0:
-        mov     rcx, QWORD [rbp-56]
-        mov     rdx, 7378697629483820647
-        mov     rax, rcx
-        imul    rdx
-        sar     rdx, 2
-        mov     rax, rcx
-        sar     rax, 63
-        sub     rdx, rax
-        mov     rax, rdx
-        sal     rax, 2
-        add     rax, rdx
-        add     rax, rax
-        sub     rcx, rax
-        mov     rdx, rcx
-        mov     eax, edx
-        lea     ecx, [rax+48]
-        mov     eax, DWORD [rbp-4]
-        lea     edx, [rax+1]
-        mov     DWORD [rbp-4], edx
-        cdqe
-        mov     edx, 31
-        sub     rdx, rax
-        mov     eax, ecx
-        mov     BYTE [rbp-48+rdx], al
-        mov     rcx, QWORD [rbp-56]
-        mov     rdx, 7378697629483820647
-        mov     rax, rcx
-        imul    rdx
-        mov     rax, rdx
-        sar     rax, 2
-        sar     rcx, 63
-        mov     rdx, rcx
-        sub     rax, rdx
-        mov     QWORD [rbp-56], rax
-        cmp     QWORD [rbp-56], 0
-        jg      .L0
-        mov     eax, DWORD [rbp-4]
-        cdqe
-        mov     edx, DWORD [rbp-4]
-        movsxd  rdx, DWORD edx
-        mov     ecx, 32
-        sub     rcx, rdx
-        lea     rdx, [rbp-48]
-        add     rcx, rdx
-        mov     rdx, rax
-        mov     rsi, rcx
-        mov     edi, 1
-        mov     rax, 1
-        syscall
-        nop
-        leave
-        ret
-_start:
-        push    rbp
-        mov     rbp, rsp
-        sub     rsp, 16
-"; // Magic code to put an integer + \n
-    code = code.to_owned() + header;
-    
-    for ast in program {
-        code += "\n";
-        code += &LabelGenerator::label_name(label_gen.label_create());
-        code += "\n";
-       unsafe { 
-            SRM.in_use = [false; 7];
-            let (_, code2) = expr_codegen(ast, label_gen); 
-            code += &code2;
-       }
-    }
-    
-    code +=".LEND:\n        mov     rdi, 0\n        mov    rax, 60\n        syscall"; // magic code to
-    return code.to_string();
+#[derive(Debug, Clone)]
+struct Variable {
+    offset: u32,
+    type_: Type,
 }
 
-
 /*
- * Technically run the program (j'ai des remorts à le supprimer)
-fn run(ast: AST) -> u64{
-    if !ast.clone().is_empty() {
-        match ast.clone().root() {
-            Literals::Operator(Operators::Put) => {
-                let rhs = run(ast.clone().rhs());
-                print!("{}", rhs);
-                return 0;
-            },
-            Literals::Operator(Operators::Plus) => {
-                let lhs = run(ast.clone().lhs());
-                let rhs = run(ast.clone().rhs());
-                return lhs + rhs;
-            },
-            Literals::Operator(Operators::Minus) => {
-                let lhs = run(ast.clone().lhs());
-                let rhs = run(ast.clone().rhs());
-                return lhs - rhs;
-            },
-            Literals::Operator(Operators::Mult) => {
-                let lhs = run(ast.clone().lhs());
-                let rhs = run(ast.clone().rhs());
-                return lhs * rhs;
-            }
-            Literals::Operator(Operators::Div) => {
-                let lhs = run(ast.clone().lhs());
-                let rhs = run(ast.clone().rhs());
-                return lhs / rhs;
-            }
-            Literals::EmptyLiterals => {
-                eprintln!("ERROR: EmptyLiterals is being traversed"); // @error            
-                std::process::exit(1);
-            },
-            Literals::Integer(number) => {
-                return number;
-            },
-        }
-    }
-    else {
-        return 0;
-    }
-}
-*/
-#[derive(Copy,PartialEq, Eq, Debug, Clone)]
-enum TokenType {
-    Plus,
-    Minus,
-    Mult,
-    Div,
-    Semicolon,
-    Put,
-    OpenParen,
-    CloseParen,
-    Assign,
-
-    Word,
-    Integer,
-    EOF,
+ * a static lives at its symbol in .data or .bss and a struct const at its symbol in .rodata,
+ * a scalar const has no address, its value is folded into the code
+ */
+#[derive(Debug, Clone)]
+struct Global {
+    type_: Type,
+    mutable: bool,
+    value: Option<u64>,
 }
 
+/*
+ * what a name refers to, the innermost local first
+ */
 #[derive(Debug, Clone)]
-struct Position {
-    line: u32,
-    col:  u32,
-    file: String,
+enum Place {
+    Local(Variable),
+    Global(Global),
 }
 
-#[derive(Debug, Clone)]
-struct Token {
-    position: Position,
-    lexeme: String,
-    type_: TokenType,
-    literal: Literals,
-}
-
-impl Token {
-    fn new(position: Position, lexeme: String, type_: TokenType, literal: Literals) -> Token {
-        Token {
-            position,
-            lexeme,
-            type_,
-            literal,
-        }
-    }
-}
-
-/*
- * Take a program as a string and his path, return a Vector of Tokens
- * compare char by char
- */
-fn tokenize(program_str: String , file_path: String) -> Vec<Token> {
-    let mut line: u32 = 0;
-    let mut col: u32  = 0;
-    let mut tokens: Vec<Token> = vec![];
-    let mut program_slice = program_str.chars().collect::<Vec<char>>().into_iter(); 
-    while !program_str.is_empty() {
-        let mut c = program_slice.next().unwrap_or('\0'); 
-        match c {
-            '=' => {
-                col += 1;
-                let token = Token::new(
-                    Position {line, col, file: file_path.clone() },
-                    "=".to_string(),
-                    TokenType::Assign,
-                    Literals::Operator(Operators::Assign),
-                );
-                tokens.push(token)
-            }
-
-            ')' => {
-                col += 1;
-                let token = Token::new(
-                    Position {line, col, file: file_path.clone() },
-                    ")".to_string(),
-                    TokenType::CloseParen,
-                    Literals::EmptyLiterals,
-                );
-                tokens.push(token)
-            }
-            '(' => {
-                col += 1;
-                let token = Token::new(
-                    Position {line, col, file: file_path.clone() },
-                    "(".to_string(),
-                    TokenType::OpenParen,
-                    Literals::EmptyLiterals,
-                );
-                tokens.push(token)
-            },
-            '\0' => {
-                let token = Token::new(
-                    Position { line, col, file: file_path.clone() },
-                    "EOF".to_string(),
-                    TokenType::EOF,
-                    Literals::EmptyLiterals,
-                );
-                tokens.push(token);
-                break;
-            },
-            ';' => {
-                col += 1;
-                let token = Token::new(
-                    Position { line, col, file: file_path.clone() },
-                    ";".to_string(),
-                    TokenType::Semicolon,
-                    Literals::EmptyLiterals,
-                );
-                tokens.push(token);
-            },
-            '*' =>  {
-                col += 1;
-                let token = Token::new(
-                    Position { line, col, file: file_path.clone() },
-                    "*".to_string(),
-                    TokenType::Mult,
-                    Literals::Operator(Operators::Mult),
-                );
-                tokens.push(token);
-            },
-            '/' =>  {
-                col += 1;
-                let token = Token::new(
-                    Position { line, col, file: file_path.clone() }, 
-                    "/".to_string(),
-                    TokenType::Div,
-                    Literals::Operator(Operators::Div),
-                );
-                tokens.push(token);
-            },
-            '+' =>  {
-                col += 1;
-                let token = Token::new(
-                    Position { line, col, file: file_path.clone() },
-                    "+".to_string(),
-                    TokenType::Plus,
-                    Literals::Operator(Operators::Plus),
-                );
-                tokens.push(token);
-            },
-            '-' =>  {
-                col += 1;
-                let token = Token::new(
-                    Position { line, col, file: file_path.clone() }, 
-                    "-".to_string(),
-                    TokenType::Minus,
-                    Literals::Operator(Operators::Minus),
-                );
-                tokens.push(token);
-            },
-            _ => { 
-                if c.is_whitespace() { // TODO Tabs pass 1 cols
-                    if c == '\n' {
-                        col   = 0;
-                        line += 1; 
-                    } else {
-                        col += 1;
-                    }
-                } else if c.is_numeric() {
-                    let mut number_lexeme: Vec<char> = vec![];
-                    let mut i = 0;
-                    let mut prg_slice_cln = program_slice.clone();
-                    while c.is_numeric() {
-                        number_lexeme.push(c);
-                        c = prg_slice_cln.next().unwrap_or('\0');
-                        i += 1;
-                    }
-                    for _ in 0..i-1 {
-                        program_slice.next();
-                    }
-                    let lex = number_lexeme.iter().cloned().collect::<String>();
-                    let token = Token::new(
-                        Position { line, col: col+1, file: file_path.clone() }, 
-                        lex.clone(),
-                        TokenType::Integer,
-                        Literals::Integer(lex.clone().parse().unwrap_or(0)),
-                    );
-                    tokens.push(token);
-                    col += lex.len() as u32;
-                } else if c.is_alphabetic() {
-                    let mut number_lexeme: Vec<char> = vec![];
-                    let mut i = 0;
-                    let mut prg_slice_cln = program_slice.clone();
-
-                    while c.is_alphabetic() || c.is_numeric() {
-                        number_lexeme.push(c);
-                        c = prg_slice_cln.next().unwrap_or('\0'); 
-                        i += 1;
-                    }
-                    let old_col = col;
-                    for _ in 0..i-1 {
-                        program_slice.next();
-                    }
-                    let lex = number_lexeme.iter().cloned().collect::<String>();
-                    col += lex.clone().len() as u32;
-                    match lex.as_str() {
-                    "put" => {
-                            let token = Token::new(
-                                Position { line, col: old_col+1, file: file_path.clone() }, 
-                                lex.clone(),
-                                TokenType::Put,
-                                Literals::Operator(Operators::Put),
-                            );
-                            tokens.push(token);
-                        }
-                    _ => {
-                            let token = Token::new(
-                                Position { line, col: old_col+1, file: file_path.clone()},
-                                lex.clone(),
-                                TokenType::Word,
-                                Literals::Word,
-                            );
-                            tokens.push(token);
-                        }
-                    }
-                }
-                else {
-                    eprintln!("ERROR:{}:{}:{} Unexpected token `{}`", line, col, file_path.clone(), c);
-                    std::process::exit(1)
-                }
-            }
+impl Place {
+    fn type_(&self) -> &Type {
+        match self {
+            Place::Local(variable) => &variable.type_,
+            Place::Global(global) => &global.type_,
         }
     }
-    return tokens;
 }
 
 /*
- * next_token => the next token
- * pointer to token, indice to the current token in tokens
+ * signature of a function, known before any body is generated so a call can come before the declaration
  */
-struct ParsingStruct {
-    tokens: Vec<Token>,
-    next_token: Token,
-    pointer_to_tokens: i32,
+struct Function {
+    params: Vec<Type>,
+    returns: Option<Type>,
+    linkage: Linkage,
 }
 
-impl ParsingStruct {
+/*
+ * how a function is called
+ */
+#[derive(Clone, Copy, PartialEq)]
+enum Linkage {
+    // a function of the program, its symbol is `stem.name`
+    Stem,
+    // a routine of the runtime, it also gets the location of the call
+    Builtin,
+    // a function of C called by its own name, variadic when it takes arguments after params
+    C { variadic: bool },
+}
 
-    fn new(tokens: Vec<Token>) -> ParsingStruct {
-        ParsingStruct {
-            tokens: tokens.clone(),
-            pointer_to_tokens: -1,
-            next_token: tokens.get(0).
-                unwrap_or(&Token::new(
-                        Position{line: 0, col: 0, file: "No file path".to_string()},
-                        "".to_string(),
-                        TokenType::EOF,
-                        Literals::EmptyLiterals,
-                        )).to_owned(),
+/*
+ * State of the code generation: the scratch registers, the labels,
+ * the variables of the stack frame and the layouts of the structs
+ */
+struct Context<'a> {
+    sources: &'a SourceMap,
+    srm: ScratchRegisterManagement,
+    labels: LabelGenerator,
+    // the variables in scope by name, the innermost last so that it shadows the others
+    variables: HashMap<String, Vec<Variable>>,
+    // the names of the variables in scope in the order they were declared
+    declared: Vec<String>,
+    // number of declared variables and stack_size when each enclosing scope was entered
+    scopes: Vec<(usize, u32)>,
+    // bytes of the stack in use, and the most ever used that the frame has to reserve
+    stack_size: u32,
+    frame_size: u32,
+    structs: StructTable,
+    globals: HashMap<String, Global>,
+    functions: HashMap<String, Function>,
+    // the label of the epilogue and the return type of the function being generated, None in _start
+    return_label: Option<u32>,
+    return_type: Option<Type>,
+    // the slot of the frame holding where to store the struct the function returns
+    return_pointer: Option<u32>,
+    // the texts of the messages used by the code, emitted in .rodata
+    strings: Vec<String>,
+    // the routines of the runtime called by the code
+    runtime: Vec<String>,
+    // panic when `+`, `-` or `*` overflow, off with --release
+    overflow_checks: bool,
+    target: &'static dyn Target,
+}
+
+impl Context<'_> {
+    /*
+     * reserve a slot aligned for type_ in the stack frame and return its offset below the frame pointer
+     */
+    fn allocate(&mut self, type_: &Type) -> u32 {
+        let size = self.structs.size_of(type_);
+        let align = self.structs.align_of(type_);
+        self.stack_size = align_up(self.stack_size + size, align);
+        self.frame_size = self.frame_size.max(self.stack_size);
+        self.stack_size
+    }
+
+    /*
+     * a new variable in the current scope, it shadows any variable with the same name
+     */
+    fn declare(&mut self, name: String, type_: Type) -> u32 {
+        let offset = self.allocate(&type_);
+        self.bind(name, Variable { offset, type_ });
+        offset
+    }
+
+    /*
+     * put a variable in the current scope, in a slot already allocated
+     */
+    fn bind(&mut self, name: String, variable: Variable) {
+        self.variables.entry(name.clone()).or_default().push(variable);
+        self.declared.push(name);
+    }
+
+    /*
+     * forget every variable, at the start of a function
+     */
+    fn clear_variables(&mut self) {
+        self.variables.clear();
+        self.declared.clear();
+    }
+
+    fn enter_scope(&mut self) {
+        self.scopes.push((self.declared.len(), self.stack_size));
+    }
+
+    /*
+     * forget the variables of the scope and give their slots back
+     */
+    fn exit_scope(&mut self) {
+        let (variables, stack_size) = self.scopes.pop().expect("exit_scope without enter_scope");
+        for name in self.declared.drain(variables..) {
+            let shadowed = self.variables.get_mut(&name).expect("a declared variable");
+            shadowed.pop();
+            if shadowed.is_empty() {
+                self.variables.remove(&name);
+            }
+        }
+        self.stack_size = stack_size;
+    }
+
+    fn lookup(&self, name: &str) -> Option<Place> {
+        if let Some(variable) = self.variables.get(name).and_then(|shadowed| shadowed.last()) {
+            return Some(Place::Local(variable.clone()));
+        }
+        self.globals.get(name).map(|global| Place::Global(global.clone()))
+    }
+
+    fn variable(&self, name: &str, span: Span) -> Place {
+        match self.lookup(name) {
+            Some(place) => place,
+            None => {
+                eprintln!("ERROR:{}: unknown variable `{}`", self.sources.location(span), name);
+                std::process::exit(1);
+            }
         }
     }
 
     /*
-     * current token <- next_token
-     * next_token <- next_next_token
+     * the label of a string put in .rodata
      */
-    fn scan_token(&mut self) {
-           self.pointer_to_tokens += 1;
-           self.next_token = self.tokens
-               .get((self.pointer_to_tokens + 1) as usize).unwrap_or(
-                   &Token::new(
-                        Position{line: 0, col: 0, file: "No file path".to_string()},
-                        "".to_string(),
-                        TokenType::EOF,
-                        Literals::EmptyLiterals,
-                        )
-                   ).to_owned();
+    fn string(&mut self, text: String) -> String {
+        self.strings.push(text);
+        format!("string.{}", self.strings.len() - 1)
     }
-}
 
-    /* parse the vec of token in vec of AST
-     *
-     * Scaning scheme
-     * E ->  T {+|-} T
-     * T -> F {* | /} F 
-     * F -> ID | Integer | (E) | -F | put F
+    /*
+     * link the routine name of the runtime in the program
      */
-fn parse(tokens: Vec<Token>) -> Vec<AST> {
-   let mut expr_list: Vec<Vec<Token>> = vec![];
-    let mut i = 0;
-    let mut token = &tokens[i];
-    let mut expr: Vec<Token> = vec![];
-    while token.type_ != TokenType::EOF {
-        if token.type_ != TokenType::Semicolon {
-            expr.push(token.clone());
-        } else {
-            expr_list.push(expr.clone());
-            expr = vec![];
+    fn use_runtime(&mut self, name: &str) {
+        if !self.runtime.iter().any(|other| other == name) {
+            self.runtime.push(name.to_string());
         }
-        i+=1;
-        token = &tokens[i];
     }
 
-    let mut  program: Vec<AST> = vec![];
-    for expr in expr_list {
-        let mut toks = ParsingStruct::new(expr);
-        let parsed_ast = parse_e(&mut toks);
-//        parsed_ast.clone().print();print!("\n");
-        program.push(parsed_ast);
+    fn function(&self, name: &str, span: Span) -> &Function {
+        match self.functions.get(name) {
+            Some(function) => function,
+            None => {
+                eprintln!("ERROR:{}: unknown function `{}`", self.sources.location(span), name);
+                std::process::exit(1);
+            }
+        }
     }
-    return program;
 }
 
 /*
- * 2nd part of the parsing scheme for operand with priority 2;
+ * Return the type of an expression without generating any code
  */
-fn parse_t(token_str: &mut ParsingStruct) -> AST {
-    // println!("T: {:?}", token_str.next_token);
-    let mut a = parse_f(token_str);
-    loop {
-        if token_str.next_token.type_ == TokenType::Mult {
-            token_str.scan_token();
-            let b = parse_f(token_str);
-            a = AST::new(
-                Literals::Operator(Operators::Mult),
-                a,
-                b)
-        } else if token_str.next_token.type_ == TokenType::Div {
-            token_str.scan_token();
-            let b = parse_f(token_str);
-            a = AST::new(
-                Literals::Operator(Operators::Div),
-                a,
-                b)
-        } else { 
-            return a;
+fn type_of(expr: &Expr, ctx: &Context) -> Type {
+    match &expr.kind {
+        ExprKind::Integer { type_, .. } => type_.clone().unwrap_or(Type::I64),
+        ExprKind::String(_) => Type::Pointer(Box::new(Type::U8)),
+        ExprKind::Variable(name) => ctx.variable(name, expr.span).type_().clone(),
+        ExprKind::StructLiteral { name, fields } => {
+            for (_, value) in fields {
+                type_of(value, ctx);
+            }
+            Type::Struct(name.clone())
         }
+        ExprKind::Field { base, field } => field_of(base, field, expr.span, ctx).type_,
+        ExprKind::Assign { value, .. } => type_of(value, ctx),
+        ExprKind::CompoundAssign { target, .. } | ExprKind::Postfix { target, .. } => type_of(target, ctx),
+        ExprKind::Unary { operand, .. } => type_of(operand, ctx),
+        ExprKind::Binary { op, lhs, rhs, .. } => binary_result_type(*op, lhs, type_of(lhs, ctx), type_of(rhs, ctx), expr.span, ctx),
+        ExprKind::Call { name, .. } => match &ctx.function(name, expr.span).returns {
+            Some(type_) => type_.clone(),
+            None => {
+                eprintln!("ERROR:{}: function `{}` returns no value", ctx.sources.location(expr.span), name);
+                std::process::exit(1);
+            }
+        },
+        ExprKind::AddressOf(place) => Type::Pointer(Box::new(type_of(place, ctx))),
+        ExprKind::Deref(pointer) => pointee_of(pointer, type_of(pointer, ctx), ctx),
     }
 }
 
 /*
- * 1nd part of the parsing scheme for operand with priority 3;
+ * the type pointed to by pointer, exit with an error if it isn't a pointer
  */
-fn parse_e(token_str: &mut ParsingStruct) -> AST {
-    // println!("E: {:?}", token_str.next_token);
-    let mut a = parse_t(token_str);
-    loop {
-        if token_str.tokens.len() <= token_str.pointer_to_tokens as usize {
-            break a;
+fn pointee_of(pointer: &Expr, type_: Type, ctx: &Context) -> Type {
+    match type_ {
+        Type::Pointer(pointee) => *pointee,
+        type_ => {
+            eprintln!("ERROR:{}: a value of type `{}` can't be dereferenced", ctx.sources.location(pointer.span), type_);
+            std::process::exit(1);
         }
-        if token_str.next_token.type_ == TokenType::Assign {
-            token_str.scan_token();
-            let b = parse_t(token_str);
-            a = AST::new(Literals::Operator(Operators::Assign),
-                a,
-                b)
+    }
+}
+
+/*
+ * the type of `lhs op rhs`: comparisons give an i64,
+ * a pointer can be moved by an integer and two pointers of the same type subtracted
+ */
+fn binary_result_type(op: BinaryOp, lhs: &Expr, lhs_type: Type, rhs_type: Type, span: Span, ctx: &Context) -> Type {
+    if op.is_comparison() {
+        return Type::I64;
+    }
+    match (lhs_type.is_pointer(), rhs_type.is_pointer(), op) {
+        (false, false, _) => binary_type(lhs, lhs_type, || rhs_type),
+        (true, false, BinaryOp::Add | BinaryOp::Sub) => lhs_type,
+        (false, true, BinaryOp::Add) => rhs_type,
+        (true, true, BinaryOp::Sub) if lhs_type == rhs_type => Type::I64,
+        _ => {
+            eprintln!("ERROR:{}: `{}` can't be applied to `{}` and `{}`", ctx.sources.location(span), op, lhs_type, rhs_type);
+            std::process::exit(1);
         }
-        if token_str.next_token.type_ == TokenType::Plus {
-            token_str.scan_token();
-            let b = parse_t(token_str);
-            a = AST::new(
-                Literals::Operator(Operators::Plus),
-                a,
-                b)
-        } else if token_str.next_token.type_ == TokenType::Minus {
-            token_str.scan_token();
-            let b = parse_t(token_str);
-            a = AST::new(
-                Literals::Operator(Operators::Minus),
-                a,
-                b)
-        } else { 
-            return a;
+    }
+}
+
+/*
+ * exit with an error if value, of type value_type, can't be stored in a place of type expected
+ * integers convert to each other, a `*u8` to and from any pointer, and a literal (0) to a pointer
+ */
+fn check_types(expected: &Type, value: &Expr, value_type: &Type, ctx: &Context) {
+    let raw = Type::Pointer(Box::new(Type::U8));
+    let compatible = match (expected.is_pointer(), value_type.is_pointer()) {
+        _ if expected == value_type => true,
+        _ if expected.is_struct() || value_type.is_struct() => false,
+        (true, true) => *expected == raw || *value_type == raw,
+        (true, false) => matches!(value.kind, ExprKind::Integer { .. }),
+        (false, true) => false,
+        (false, false) => true,
+    };
+    if !compatible {
+        eprintln!("ERROR:{}: mismatched types: expected `{}`, found `{}`", ctx.sources.location(value.span), expected, value_type);
+        std::process::exit(1);
+    }
+}
+
+/*
+ * arithmetic is done on 64 bits and takes the type of its left operand,
+ * unless it is an unsuffixed literal: `x + 1` and `1 + x` both have the type of x
+ */
+fn binary_type(lhs: &Expr, lhs_type: Type, rhs_type: impl FnOnce() -> Type) -> Type {
+    match lhs.kind {
+        ExprKind::Integer { type_: None, .. } => rhs_type(),
+        _ => lhs_type,
+    }
+}
+
+/*
+ * Return the field accessed by a `base.field` expression
+ */
+fn field_of(base: &Expr, field: &str, span: Span, ctx: &Context) -> Field {
+    let mut type_ = type_of(base, ctx);
+    // `p.field` goes through a pointer to a struct
+    if let Type::Pointer(pointee) = &type_ {
+        if pointee.is_struct() {
+            type_ = *pointee.clone();
         }
-    } 
+    }
+    match type_ {
+        Type::Struct(name) => match ctx.structs.layout(&name).field(field) {
+            Some(field) => field.clone(),
+            None => {
+                eprintln!("ERROR:{}: struct `{}` has no field `{}`", ctx.sources.location(span), name, field);
+                std::process::exit(1);
+            }
+        },
+        type_ => {
+            eprintln!("ERROR:{}: can't access field `{}` of a value of type `{}`", ctx.sources.location(span), field, type_);
+            std::process::exit(1);
+        }
+    }
 }
 
 /*
- * third part of the parsing scheme for operand whith priority 1
+ * the variable at the root of a `a.b.c` expression
  */
-fn parse_f(token_str: &mut ParsingStruct) -> AST {
-    // println!("F: {:?}", token_str.next_token);
-    if token_str.next_token.type_ == TokenType::Put {
-        token_str.scan_token(); 
-        return AST::new(
-            Literals::Operator(Operators::Put),
-            AST::create_empty(),
-            parse_f(token_str)
-            );
-    } else if token_str.next_token.type_ == TokenType::Integer {
-        let ast = AST::new(token_str.next_token.literal, AST::create_empty(), AST::create_empty());
-        if token_str.tokens.len() > (token_str.pointer_to_tokens -1) as usize {
-            token_str.scan_token();
+fn root_variable(expr: &Expr) -> Option<&str> {
+    match &expr.kind {
+        ExprKind::Variable(name) => Some(name),
+        ExprKind::Field { base, .. } => root_variable(base),
+        _ => None,
+    }
+}
+
+fn mentions_variable(expr: &Expr, name: &str) -> bool {
+    match &expr.kind {
+        ExprKind::Integer { .. } | ExprKind::String(_) => false,
+        ExprKind::Variable(variable) => variable == name,
+        ExprKind::StructLiteral { fields, .. } => fields.iter().any(|(_, value)| mentions_variable(value, name)),
+        ExprKind::Field { base, .. } => mentions_variable(base, name),
+        ExprKind::Unary { operand, .. } => mentions_variable(operand, name),
+        ExprKind::Binary { lhs, rhs, .. } => mentions_variable(lhs, name) || mentions_variable(rhs, name),
+        ExprKind::Assign { target, value } | ExprKind::CompoundAssign { target, value, .. } => mentions_variable(target, name) || mentions_variable(value, name),
+        ExprKind::Postfix { target, .. } => mentions_variable(target, name),
+        ExprKind::Call { args, .. } => args.iter().any(|arg| mentions_variable(arg, name)),
+        ExprKind::AddressOf(inner) | ExprKind::Deref(inner) => mentions_variable(inner, name),
+    }
+}
+
+/*
+ * exit with an error if target is a const or a field of one
+ */
+fn check_assignable(target: &Expr, ctx: &Context) {
+    if let ExprKind::Call { name, .. } = &target.kind {
+        eprintln!("ERROR:{}: can't assign to the value returned by `{}`", ctx.sources.location(target.span), name);
+        std::process::exit(1);
+    }
+    if let Some(root) = root_variable(target) {
+        if let Some(Place::Global(Global { mutable: false, .. })) = ctx.lookup(root) {
+            eprintln!("ERROR:{}: cannot assign to constant `{}`", ctx.sources.location(target.span), root);
+            std::process::exit(1);
         }
-        return ast;
-    } else if token_str.next_token.type_ == TokenType::Word {
-        let ast = AST::new(token_str.next_token.literal, AST::create_empty(), AST::create_empty());
-        if token_str.tokens.len() > (token_str.pointer_to_tokens -1) as usize {
-            token_str.scan_token();
+    }
+}
+
+/*
+ * Take an assignable expression (a variable or a field of one)
+ * and return the register holding its address and its type
+ */
+fn address_codegen(expr: &Expr, ctx: &mut Context) -> (u8, String, Type) {
+    match &expr.kind {
+        ExprKind::Variable(name) => {
+            let place = ctx.variable(name, expr.span);
+            let regu = ctx.srm.scratch_alloc();
+            match place {
+                Place::Local(variable) => (regu, ctx.target.frame_address(regu, variable.offset), variable.type_),
+                Place::Global(Global { value: Some(_), .. }) => {
+                    eprintln!("ERROR:{}: constant `{}` has no address", ctx.sources.location(expr.span), name);
+                    std::process::exit(1);
+                }
+                Place::Global(global) => (regu, ctx.target.symbol_address(regu, &symbol(name)), global.type_),
+            }
         }
-        return ast
-    } else if token_str.next_token.type_ == TokenType::Minus {
-        token_str.scan_token();
-        return AST::new(
-            Literals::Operator(Operators::Minus),
-            AST::create_empty(),
-            parse_f(token_str)
-            );
-    } else if token_str.next_token.type_ == TokenType::OpenParen {
-        let open_paren_col = token_str.next_token.position.col;
-        let open_paren_line = token_str.next_token.position.line;
-        token_str.scan_token();
-        let expr = parse_e(token_str);
-        if token_str.next_token.type_ == TokenType::CloseParen {
-            return expr;
-        } else {
-            eprintln!("last expr war type {:?}", token_str.next_token.type_);
-            eprintln!("ERROR:{}:{}: `(` is never closed", open_paren_line, open_paren_col); 
+        ExprKind::Field { base, field } => {
+            let field = field_of(base, field, expr.span, ctx);
+            let (regu, mut code) = if type_of(base, ctx).is_pointer() {
+                // the address of the struct is the value of the pointer
+                let (regu, mut code, _) = expr_codegen(base, ctx);
+                code += &null_check_codegen(regu, base.span, ctx);
+                (regu, code)
+            } else {
+                let (regu, code, _) = address_codegen(base, ctx);
+                (regu, code)
+            };
+            if field.offset != 0 {
+                code += &ctx.target.offset(regu, regu, field.offset as i64);
+            }
+            (regu, code, field.type_)
+        }
+        ExprKind::Deref(pointer) => {
+            let (regu, mut code, type_) = expr_codegen(pointer, ctx);
+            let pointee = pointee_of(pointer, type_, ctx);
+            code += &null_check_codegen(regu, pointer.span, ctx);
+            (regu, code, pointee)
+        }
+        // the struct a call returns is in the frame of the caller
+        ExprKind::Call { name, args } if type_of(expr, ctx).is_struct() => {
+            let type_ = type_of(expr, ctx);
+            let code = call_codegen(name, args, expr.span, ctx);
+            let regu = ctx.srm.scratch_alloc();
+            (regu, code + &ctx.target.result(regu, 8, false), type_)
+        }
+        _ => {
+            eprintln!("ERROR:{}: this expression is not a variable, a field or a dereference", ctx.sources.location(expr.span));
             std::process::exit(1);
         }
-    } else {
-        eprintln!("ERROR: Unknown token type `{:?}` in parse_f", token_str.next_token.type_);
+    }
+}
+
+/*
+ * panic with `file:line:col: message`, the code before it branches to ok_label (emitted after the panic)
+ * when the check passes
+ */
+fn panic_unless_codegen(ok_label: u32, message: &str, span: Span, ctx: &mut Context) -> String {
+    let message = format!("{}: {message}\n", ctx.sources.location(span));
+    let len = message.len();
+    let string = ctx.string(message);
+    let mut code = ctx.target.argument_address(0, &string);
+    code += &ctx.target.argument_immediate(1, len as u64);
+    code += &ctx.target.call(&runtime::symbol("panic"));
+    ctx.use_runtime("panic");
+    code += &LabelGenerator::label_name(ok_label);
+    code += "\n";
+    return code;
+}
+
+/*
+ * panic if the pointer in the register r is null, span is the pointer in the source, only with overflow checks
+ */
+fn null_check_codegen(r: u8, span: Span, ctx: &mut Context) -> String {
+    if !ctx.overflow_checks {
+        return String::new();
+    }
+    let ok_label = ctx.labels.label_create();
+    let mut code = ctx.target.branch_nonzero(r, ok_label);
+    code += &panic_unless_codegen(ok_label, "attempt to dereference a null pointer", span, ctx);
+    return code;
+}
+
+/*
+ * replace the address in the register r by the value of type_ it points to
+ */
+fn load_codegen(r: u8, type_: &Type, span: Span, ctx: &Context) -> String {
+    if let Type::Struct(name) = type_ {
+        eprintln!("ERROR:{}: a value of type `{}` can't be used as a number", ctx.sources.location(span), name);
         std::process::exit(1);
     }
+    return ctx.target.load(r, ctx.structs.size_of(type_), type_.is_signed());
+}
 
-    
+/*
+ * Store the value of an expression of type type_ at the address held by the register addr
+ * scalars are truncated to the size of type_, structs are copied field by field or byte by byte
+ */
+fn store_codegen(addr: u8, value: &Expr, type_: &Type, ctx: &mut Context) -> String {
+    let value_type = type_of(value, ctx);
+    check_types(type_, value, &value_type, ctx);
+    let size = ctx.structs.size_of(type_);
+    if let ExprKind::StructLiteral { name, fields } = &value.kind {
+        return struct_literal_codegen(addr, name, fields, value.span, ctx);
+    }
+    if type_.is_struct() {
+        let (src, mut code, _) = address_codegen(value, ctx);
+        code += &ctx.target.copy(addr, src, size);
+        ctx.srm.scratch_free(src);
+        return code;
+    }
+    let (regu, mut code, _) = expr_codegen(value, ctx);
+    code += &ctx.target.store(addr, regu, size);
+    ctx.srm.scratch_free(regu);
+    code
 }
 
+/*
+ * exit with an error if a literal of struct name sets a field it doesn't have or sets one twice
+ */
+fn check_struct_literal(name: &str, fields: &[(String, Expr)], ctx: &Context) {
+    let layout = ctx.structs.layout(name);
+    let mut initialized: HashSet<&str> = HashSet::new();
+    for (field_name, value) in fields {
+        if layout.field(field_name).is_none() {
+            eprintln!("ERROR:{}: struct `{}` has no field `{}`", ctx.sources.location(value.span), name, field_name);
+            std::process::exit(1);
+        }
+        if !initialized.insert(field_name) {
+            eprintln!("ERROR:{}: field `{}` is initialized twice in a literal of struct `{}`", ctx.sources.location(value.span), field_name, name);
+            std::process::exit(1);
+        }
+    }
+}
 
-fn main() {
-    let mut args = std::env::args();
-    if args.len() < 2 || args.len() > 3{
-        eprintln!("ERROR: Usage: ./stem-rs `file`");
+/*
+ * the value given to field in a literal of struct name spanning span
+ */
+fn literal_field<'e>(name: &str, fields: &'e [(String, Expr)], field: &str, span: Span, ctx: &Context) -> &'e Expr {
+    match fields.iter().find(|(field_name, _)| field_name == field) {
+        Some((_, value)) => value,
+        None => {
+            eprintln!("ERROR:{}: missing field `{}` in a literal of struct `{}`", ctx.sources.location(span), field, name);
+            std::process::exit(1);
+        }
     }
-    args.next(); // consume program name
-    ) 
-    let file_path: String;
-    file_path = args.next().unwrap_or("".to_string()); 
-    let program_string = fs::read_to_string(file_path.clone()).expect("Can't read file");
-    println!("Program read");
-    let tokens = tokenize(program_string, file_path);
-    println!("Program tokenized");
-    let parsed = parse(tokens);
-    println!("Program parsed");
-    let asm_code = generate_code(parsed.clone());
-    println!("Code generated");
+}
 
-    fs::write("output.asm", asm_code).expect("Can't write the output file");
+/*
+ * Build a struct literal directly at the address held by the register addr
+ */
+fn struct_literal_codegen(addr: u8, name: &str, fields: &[(String, Expr)], span: Span, ctx: &mut Context) -> String {
+    check_struct_literal(name, fields, ctx);
+    let layout = ctx.structs.layout(name).clone();
+    let mut code = "".to_string();
+    for field in &layout.fields {
+        let value = literal_field(name, fields, &field.name, span, ctx);
+        let regu = ctx.srm.scratch_alloc();
+        code += &ctx.target.offset(regu, addr, field.offset as i64);
+        code += &store_codegen(regu, value, &field.type_, ctx);
+        ctx.srm.scratch_free(regu);
+    }
+    code
+}
 
+/*
+ * Take an expression and return his equivalent in assembly as a String
+ * get u8 to keep track of the registers of the childs nodes, and the type of the value in it
+ *
+ */
+fn expr_codegen(expr: &Expr, ctx: &mut Context) -> (u8, String, Type) {
+    match &expr.kind {
+        ExprKind::Integer { value, type_ } => {
+            let regu = ctx.srm.scratch_alloc();
+            return (regu, ctx.target.load_immediate(regu, *value), type_.clone().unwrap_or(Type::I64));
+        }
+        ExprKind::String(text) => {
+            let regu = ctx.srm.scratch_alloc();
+            let label = ctx.string(format!("{text}\0"));
+            return (regu, ctx.target.symbol_address(regu, &label), Type::Pointer(Box::new(Type::U8)));
+        }
+        ExprKind::Binary { op, op_span, lhs, rhs } => {
+            let (regle, mut code, lhs_type) = number_codegen(lhs, ctx);
+            let (regri, code2, rhs_type)    = number_codegen(rhs, ctx);
+            let type_ = binary_result_type(*op, lhs, lhs_type.clone(), rhs_type.clone(), expr.span, ctx);
+            
+            code += &code2; 
 
-  //  for ast in parsed.clone() {
-  //    run(ast);
-  //  }
+            if lhs_type.is_pointer() || rhs_type.is_pointer() {
+                // pointers move by whole elements
+                if !op.is_comparison() && lhs_type.is_pointer() != rhs_type.is_pointer() {
+                    let (pointer, offset) = if lhs_type.is_pointer() { (&lhs_type, regri) } else { (&rhs_type, regle) };
+                    code += &scale_codegen(offset, pointer, ctx);
+                }
+                let pointer = if lhs_type.is_pointer() { &lhs_type } else { &rhs_type };
+                code += &binary_op_codegen(*op, regle, regri, pointer, *op_span, ctx);
+                if lhs_type.is_pointer() && rhs_type.is_pointer() && *op == BinaryOp::Sub {
+                    let size = ctx.structs.size_of(lhs_type.pointee().unwrap());
+                    code += &ctx.target.unscale(regle, size);
+                }
+            } else {
+                code += &binary_op_codegen(*op, regle, regri, &binary_type(lhs, lhs_type, || rhs_type), *op_span, ctx);
+            }
+            ctx.srm.scratch_free(regri);
+            return (regle, code, type_);
+        }
+        ExprKind::Unary { op, operand } => {
+            let (regu, mut code, type_) = number_codegen(operand, ctx);
+            if type_.is_pointer() {
+                eprintln!("ERROR:{}: `{}` can't be applied to `{}`", ctx.sources.location(expr.span), op, type_);
+                std::process::exit(1);
+            }
+            // `-(-128i8)` doesn't fit in an i8, `-128i8` is a literal the lexer already checked
+            let literal = matches!(operand.kind, ExprKind::Integer { .. });
+            if *op == UnaryOp::Neg && ctx.overflow_checks && type_.is_signed() && !literal {
+                let ok_label = ctx.labels.label_create();
+                code += &ctx.target.branch_not_equal(regu, signed_min(ctx.structs.size_of(&type_)), ok_label);
+                code += &panic_unless_codegen(ok_label, "attempt to negate with overflow", expr.span, ctx);
+            }
+            code += &ctx.target.unary(*op, regu);
+            // `~0u8` is 255 and not the 64 bits of -1
+            code += &extend_codegen(regu, &type_, ctx);
+            return (regu, code, type_);
+        }
+        ExprKind::AddressOf(place) => {
+            if let Some(root) = root_variable(place) {
+                if let Some(Place::Global(Global { mutable: false, .. })) = ctx.lookup(root) {
+                    eprintln!("ERROR:{}: can't take the address of constant `{}`", ctx.sources.location(expr.span), root);
+                    std::process::exit(1);
+                }
+            }
+            let (regu, code, type_) = address_codegen(place, ctx);
+            return (regu, code, Type::Pointer(Box::new(type_)));
+        }
+        ExprKind::Variable(_) | ExprKind::Field { .. } | ExprKind::Deref(_) => {
+            if let ExprKind::Variable(name) = &expr.kind {
+                // a scalar const is an immediate
+                if let Some(Place::Global(Global { type_, value: Some(value), .. })) = ctx.lookup(name) {
+                    let regu = ctx.srm.scratch_alloc();
+                    return (regu, ctx.target.load_immediate(regu, value), type_);
+                }
+            }
+            let (regu, mut code, type_) = address_codegen(expr, ctx);
+            code += &load_codegen(regu, &type_, expr.span, ctx);
+            return (regu, code, type_);
+        }
+        ExprKind::StructLiteral { name, .. } => {
+            eprintln!("ERROR:{}: a literal of struct `{}` can only be assigned to a variable or a field", ctx.sources.location(expr.span), name);
+            std::process::exit(1);
+        }
+        ExprKind::Assign { target, value } => {
+            let value_type = type_of(value, ctx);
+            // assigning to an unknown variable declares it in the current scope
+            if let ExprKind::Variable(name) = &target.kind {
+                if ctx.lookup(name).is_none() {
+                    ctx.declare(name.clone(), value_type);
+                }
+            }
+            check_assignable(target, ctx);
+            let (rega, mut code, target_type) = address_codegen(target, ctx);
+
+            // a literal that reads the variable it is assigned to is built aside first
+            let reads_itself = matches!(value.kind, ExprKind::StructLiteral { .. })
+                && root_variable(target).is_some_and(|root| mentions_variable(value, root));
+            if reads_itself {
+                let offset = ctx.allocate(&target_type);
+                let regt = ctx.srm.scratch_alloc();
+                code += &ctx.target.frame_address(regt, offset);
+                code += &store_codegen(regt, value, &target_type, ctx);
+                code += &ctx.target.copy(rega, regt, ctx.structs.size_of(&target_type));
+                ctx.srm.scratch_free(regt);
+            } else {
+                code += &store_codegen(rega, value, &target_type, ctx);
+            }
+
+            if target_type.is_struct() {
+                ctx.srm.scratch_free(rega);
+                return (0, code, target_type);
+            }
+            // the value of an assignment is the value stored
+            code += &load_codegen(rega, &target_type, expr.span, ctx);
+            return (rega, code, target_type);
+        }
+        ExprKind::CompoundAssign { op, op_span, target, value } => {
+            return compound_codegen(*op, *op_span, target, value, false, ctx);
+        }
+        ExprKind::Postfix { op, op_span, target } => {
+            let one = Expr::new(ExprKind::Integer { value: 1, type_: None }, expr.span);
+            return compound_codegen(*op, *op_span, target, &one, true, ctx);
+        }
+        ExprKind::Call { name, args } => {
+            let type_ = type_of(expr, ctx);
+            if type_.is_struct() {
+                return address_codegen(expr, ctx);
+            }
+            let code = call_codegen(name, args, expr.span, ctx);
+            let regu = ctx.srm.scratch_alloc();
+            return (regu, code + &result_codegen(regu, &type_, ctx), type_);
+        }
+    }
+}
+
+/*
+ * apply op to the registers left and right, the result is left in left
+ * type_ is the type of the operands, its sign chooses between the signed and unsigned instructions
+ */
+fn binary_op_codegen(op: BinaryOp, left: u8, right: u8, type_: &Type, span: Span, ctx: &mut Context) -> String {
+    let mut code = "".to_string();
+    let signed = type_.is_signed();
+    match op {
+        BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul => {
+            let message = match op {
+                BinaryOp::Add => "attempt to add with overflow",
+                BinaryOp::Sub => "attempt to subtract with overflow",
+                _ => "attempt to multiply with overflow",
+            };
+            // pointer arithmetic is not checked, a pointer is an address and not a number
+            let ok_label = if ctx.overflow_checks && !type_.is_pointer() { Some(ctx.labels.label_create()) } else { None };
+            code += &ctx.target.arith(op, left, right, ctx.structs.size_of(type_), signed, ok_label);
+            if let Some(ok_label) = ok_label {
+                code += &panic_unless_codegen(ok_label, message, span, ctx);
+            }
+            code += &extend_codegen(left, type_, ctx);
+        }
+        BinaryOp::Div | BinaryOp::Rem => {
+            let (zero, overflow) = if op == BinaryOp::Div {
+                ("attempt to divide by zero", "attempt to divide with overflow")
+            } else {
+                ("attempt to calculate the remainder with a divisor of zero", "attempt to calculate the remainder with overflow")
+            };
+            let ok_label = ctx.labels.label_create();
+            code += &ctx.target.branch_nonzero(right, ok_label);
+            code += &panic_unless_codegen(ok_label, zero, span, ctx);
+            if signed {
+                // the smallest value of type_ divided by -1 doesn't fit either
+                let skip_label = ctx.labels.label_create();
+                code += &ctx.target.branch_not_equal(right, -1, skip_label);
+                let ok_label = ctx.labels.label_create();
+                code += &ctx.target.branch_not_equal(left, signed_min(ctx.structs.size_of(type_)), ok_label);
+                code += &panic_unless_codegen(ok_label, overflow, span, ctx);
+                code += &LabelGenerator::label_name(skip_label);
+                code += "\n";
+            }
+            code += &ctx.target.binary(op, left, right, signed);
+            code += &extend_codegen(left, type_, ctx);
+        }
+        // `1u8 << 9` is 0, the bits shifted out of type_ are lost
+        BinaryOp::Shl | BinaryOp::Shr => {
+            code += &ctx.target.binary(op, left, right, signed);
+            code += &extend_codegen(left, type_, ctx);
+        }
+        _ => code += &ctx.target.binary(op, left, right, signed),
+    }
+    return code;
+}
+
+/*
+ * the smallest signed integer of size bytes
+ */
+fn signed_min(size: u32) -> i64 {
+    return i64::MIN >> (64 - size * 8);
+}
+
+/*
+ * extend the value of type_ in the low bits of the register r to 64 bits
+ */
+fn extend_codegen(r: u8, type_: &Type, ctx: &Context) -> String {
+    return ctx.target.extend(r, ctx.structs.size_of(type_), type_.is_signed());
+}
+
+/*
+ * multiply the offset in the register r by the size of what pointer points to
+ */
+fn scale_codegen(r: u8, pointer: &Type, ctx: &Context) -> String {
+    let size = ctx.structs.size_of(pointer.pointee().unwrap());
+    if size == 1 {
+        return "".to_string();
+    }
+    return ctx.target.scale(r, size);
+}
+
+/*
+ * `target op= value`, or `target++` and `target--` when keep_old is set (value is then the literal 1)
+ * the address of target is computed once, the value is the one stored, or the one before for keep_old
+ */
+fn compound_codegen(op: BinaryOp, op_span: Span, target: &Expr, value: &Expr, keep_old: bool, ctx: &mut Context) -> (u8, String, Type) {
+    check_assignable(target, ctx);
+    let (rega, mut code, type_) = address_codegen(target, ctx);
+    if type_.is_struct() {
+        eprintln!("ERROR:{}: a value of type `{}` can't be used as a number", ctx.sources.location(target.span), type_);
+        std::process::exit(1);
+    }
+    let regv = ctx.srm.scratch_alloc();
+    code += &ctx.target.move_register(regv, rega);
+    code += &load_codegen(regv, &type_, target.span, ctx);
+    let mut rego = regv;
+    if keep_old {
+        rego = ctx.srm.scratch_alloc();
+        code += &ctx.target.move_register(rego, regv);
+    }
+
+    let (regr, code2, value_type) = number_codegen(value, ctx);
+    code += &code2;
+    if type_.is_pointer() {
+        binary_result_type(op, target, type_.clone(), value_type.clone(), value.span, ctx);
+        if value_type.is_pointer() {
+            eprintln!("ERROR:{}: mismatched types: expected an integer, found `{}`", ctx.sources.location(value.span), value_type);
+            std::process::exit(1);
+        }
+        code += &scale_codegen(regr, &type_, ctx);
+    } else if value_type.is_pointer() {
+        eprintln!("ERROR:{}: `{}=` can't be applied to `{}` and `{}`", ctx.sources.location(value.span), op, type_, value_type);
+        std::process::exit(1);
+    }
+    code += &binary_op_codegen(op, regv, regr, &type_, op_span, ctx);
+    ctx.srm.scratch_free(regr);
+
+    let size = ctx.structs.size_of(&type_);
+    code += &ctx.target.store(rega, regv, size);
+    ctx.srm.scratch_free(regv);
+    if keep_old {
+        ctx.srm.scratch_free(rega);
+        return (rego, code, type_);
+    }
+    // the value of an assignment is the value stored, truncated to the type of target
+    code += &load_codegen(rega, &type_, target.span, ctx);
+    return (rega, code, type_);
+}
+
+/*
+ * call the function name, its value is left in the return register of the target
+ * the arguments go in the argument registers, the scratch registers in use are saved around the call
+ * a builtin also gets the `file:line:col: ` of the call and its length, for its panics
+ */
+fn call_codegen(name: &str, args: &[Expr], span: Span, ctx: &mut Context) -> String {
+    let params = ctx.function(name, span).params.clone();
+    let returns = ctx.function(name, span).returns.clone();
+    let linkage = ctx.function(name, span).linkage;
+    if linkage == (Linkage::C { variadic: true }) {
+        if args.len() < params.len() {
+            eprintln!("ERROR:{}: function `{}` takes at least {} arguments but {} were given", ctx.sources.location(span), name, params.len(), args.len());
+            std::process::exit(1);
+        }
+        if args.len() > target::MAX_ARGS {
+            eprintln!("ERROR:{}: function `{}` is given more than {} arguments", ctx.sources.location(span), name, target::MAX_ARGS);
+            std::process::exit(1);
+        }
+    } else if args.len() != params.len() {
+        eprintln!("ERROR:{}: function `{}` takes {} arguments but {} were given", ctx.sources.location(span), name, params.len(), args.len());
+        std::process::exit(1);
+    }
+    let mut code = "".to_string();
+    let mut regs: Vec<u8> = vec![];
+    for (i, arg) in args.iter().enumerate() {
+        if let Some(param @ Type::Struct(_)) = params.get(i) {
+            // the callee gets the address of a copy, it can't see the changes the next arguments make
+            let offset = ctx.allocate(param);
+            let regu = ctx.srm.scratch_alloc();
+            code += &ctx.target.frame_address(regu, offset);
+            code += &store_codegen(regu, arg, param, ctx);
+            regs.push(regu);
+            continue;
+        }
+        let (regu, code2, type_) = number_codegen(arg, ctx);
+        match params.get(i) {
+            Some(param) => check_types(param, arg, &type_, ctx),
+            // the values are already extended to 64 bits, like C promotes the variadic arguments
+            None if type_.is_struct() => {
+                eprintln!("ERROR:{}: a `{}` can't be passed to a C function", ctx.sources.location(arg.span), type_);
+                std::process::exit(1);
+            }
+            None => {}
+        }
+        code += &code2;
+        regs.push(regu);
+    }
+    if let Some(type_ @ Type::Struct(_)) = &returns {
+        let offset = ctx.allocate(type_);
+        let regu = ctx.srm.scratch_alloc();
+        code += &ctx.target.frame_address(regu, offset);
+        regs.insert(0, regu);
+    }
+    let saved: Vec<u8> = (0..ctx.srm.in_use.len() as u8).filter(|r| ctx.srm.in_use[*r as usize] && !regs.contains(r)).collect();
+    for r in &saved {
+        code += &ctx.target.push(*r);
+    }
+    for (i, regu) in regs.into_iter().enumerate() {
+        code += &ctx.target.argument(i, regu);
+        ctx.srm.scratch_free(regu);
+    }
+    match linkage {
+        Linkage::Builtin => {
+            let location = format!("{}: ", ctx.sources.location(span));
+            let len = location.len();
+            let string = ctx.string(location);
+            code += &ctx.target.argument_address(args.len(), &string);
+            code += &ctx.target.argument_immediate(args.len() + 1, len as u64);
+            code += &ctx.target.call(&runtime::symbol(name));
+            ctx.use_runtime(name);
+        }
+        Linkage::Stem => code += &ctx.target.call(&symbol(name)),
+        Linkage::C { variadic } => code += &ctx.target.call_c(name, variadic),
+    }
+    for r in saved.iter().rev() {
+        code += &ctx.target.pop(*r);
+    }
+    return code;
+}
+
+/*
+ * move the value returned by a call to the register r, extended from the size of type_ to 64 bits
+ */
+fn result_codegen(r: u8, type_: &Type, ctx: &Context) -> String {
+    return ctx.target.result(r, ctx.structs.size_of(type_), type_.is_signed());
+}
+
+/*
+ * expr_codegen for the operands of arithmetic, exit with an error if expr is a struct
+ */
+fn number_codegen(expr: &Expr, ctx: &mut Context) -> (u8, String, Type) {
+    let (regu, code, type_) = expr_codegen(expr, ctx);
+    if type_.is_struct() {
+        eprintln!("ERROR:{}: a value of type `{}` can't be used as a number", ctx.sources.location(expr.span), type_);
+        std::process::exit(1);
+    }
+    return (regu, code, type_);
+}
+
+/*
+ * Take a statement and return his equivalent in assembly
+ * every statement starts with a label commented with its location
+ */
+fn stmt_codegen(stmt: &Stmt, ctx: &mut Context) -> String {
+    let mut code = "\n".to_string();
+    code += &LabelGenerator::label_name(ctx.labels.label_create());
+    code += &format!("{}\n", ctx.target.comment(&ctx.sources.location(stmt.span).to_string()));
+    match &stmt.kind {
+        StmtKind::Expr(Expr { kind: ExprKind::Call { name, args }, span }) => {
+            // the value, if any, is dropped
+            code += &call_codegen(name, args, *span, ctx);
+        }
+        StmtKind::Expr(expr) => {
+            let (regu, code2, _) = expr_codegen(expr, ctx);
+            ctx.srm.scratch_free(regu);
+            code += &code2;
+        }
+        StmtKind::Return(value) => {
+            let Some(return_label) = ctx.return_label else {
+                eprintln!("ERROR:{}: `return` outside of a function", ctx.sources.location(stmt.span));
+                std::process::exit(1);
+            };
+            match (value, ctx.return_type.clone()) {
+                // stored where the caller said, the epilogue returns that address
+                (Some(value), Some(type_ @ Type::Struct(_))) => {
+                    let regu = ctx.srm.scratch_alloc();
+                    code += &ctx.target.frame_address(regu, ctx.return_pointer.expect("a function returning a struct has a return pointer"));
+                    code += &ctx.target.load(regu, 8, false);
+                    code += &store_codegen(regu, value, &type_, ctx);
+                    ctx.srm.scratch_free(regu);
+                }
+                (Some(value), Some(type_)) => {
+                    let (regu, code2, value_type) = number_codegen(value, ctx);
+                    check_types(&type_, value, &value_type, ctx);
+                    code += &code2;
+                    code += &ctx.target.return_value(regu);
+                    ctx.srm.scratch_free(regu);
+                }
+                (None, None) => {}
+                (Some(value), None) => {
+                    eprintln!("ERROR:{}: this function returns no value", ctx.sources.location(value.span));
+                    std::process::exit(1);
+                }
+                (None, Some(type_)) => {
+                    eprintln!("ERROR:{}: expected a value of type `{}` after `return`", ctx.sources.location(stmt.span), type_);
+                    std::process::exit(1);
+                }
+            }
+            code += &ctx.target.jump(return_label);
+        }
+        StmtKind::Put(expr) => {
+            let (regu, code2, type_) = number_codegen(expr, ctx);
+            code += &code2;
+            code += &ctx.target.argument(0, regu);
+            code += &ctx.target.argument_immediate(1, type_.is_signed() as u64);
+            code += &ctx.target.call(&runtime::symbol("put"));
+            ctx.use_runtime("put");
+            ctx.srm.scratch_free(regu);
+        }
+        StmtKind::Assert { condition, message } => {
+            let (regu, code2, _) = number_codegen(condition, ctx);
+            code += &code2;
+            let ok_label = ctx.labels.label_create();
+            code += &ctx.target.branch_nonzero(regu, ok_label);
+            ctx.srm.scratch_free(regu);
+            let message = match message {
+                Some(message) => format!("assertion failed: {message}"),
+                None => "assertion failed".to_string(),
+            };
+            code += &panic_unless_codegen(ok_label, &message, stmt.span, ctx);
+        }
+        StmtKind::Let { name, type_, value } => {
+            // the value is computed before the variable exists: `let x = x + 1;` reads the outer x
+            let type_ = type_.clone().unwrap_or_else(|| type_of(value, ctx));
+            let offset = ctx.allocate(&type_);
+            let rega = ctx.srm.scratch_alloc();
+            code += &ctx.target.frame_address(rega, offset);
+            code += &store_codegen(rega, value, &type_, ctx);
+            ctx.srm.scratch_free(rega);
+            ctx.bind(name.clone(), Variable { offset, type_ });
+        }
+        StmtKind::Block(stmts) => {
+            ctx.enter_scope();
+            for stmt in stmts {
+                code += &stmt_codegen(stmt, ctx);
+            }
+            ctx.exit_scope();
+        }
+        StmtKind::If { condition, then, otherwise } => {
+            let else_label = ctx.labels.label_create();
+            code += &condition_codegen(condition, else_label, ctx);
+            code += &stmt_codegen(then, ctx);
+            if let Some(otherwise) = otherwise {
+                let end_label = ctx.labels.label_create();
+                code += &ctx.target.jump(end_label);
+                code += &LabelGenerator::label_name(else_label);
+                code += &stmt_codegen(otherwise, ctx);
+                code += &LabelGenerator::label_name(end_label);
+                code += "\n";
+            } else {
+                code += &LabelGenerator::label_name(else_label);
+                code += "\n";
+            }
+        }
+        StmtKind::While { condition, body } => {
+            let loop_label = ctx.labels.label_create();
+            let end_label = ctx.labels.label_create();
+            code += &LabelGenerator::label_name(loop_label);
+            code += "\n";
+            code += &condition_codegen(condition, end_label, ctx);
+            code += &stmt_codegen(body, ctx);
+            code += &ctx.target.jump(loop_label);
+            code += &LabelGenerator::label_name(end_label);
+            code += "\n";
+        }
+    }
+    return code;
+}
+
+/*
+ * evaluate a condition and jump to false_label when it is 0
+ */
+fn condition_codegen(condition: &Expr, false_label: u32, ctx: &mut Context) -> String {
+    let (regu, mut code, _) = number_codegen(condition, ctx);
+    code += &ctx.target.branch_zero(regu, false_label);
+    ctx.srm.scratch_free(regu);
+    return code;
+}
+
+/*
+ * Take a function and return its code, the arguments are copied from their registers to the stack frame
+ * falling off the end of a function returns 0
+ * a struct argument is the address of a copy made by the caller, the struct is copied again to the frame, and a
+ * function returning a struct gets first the address to store it at, and returns it like C does
+ */
+fn fn_codegen(decl: &FnDecl, ctx: &mut Context) -> String {
+    ctx.srm.in_use = [false; target::SCRATCH_COUNT];
+    ctx.clear_variables();
+    ctx.stack_size = 0;
+    ctx.frame_size = 0;
+    let return_label = ctx.labels.label_create();
+    ctx.return_label = Some(return_label);
+    ctx.return_type = decl.returns.clone();
+
+    let mut body = "".to_string();
+    ctx.enter_scope();
+    let pointer = Type::Pointer(Box::new(Type::U8));
+    let hidden = decl.returns.as_ref().is_some_and(Type::is_struct) as usize;
+    ctx.return_pointer = None;
+    if hidden == 1 {
+        let offset = ctx.allocate(&pointer);
+        body += &ctx.target.store_argument(0, offset, 8);
+        ctx.return_pointer = Some(offset);
+    }
+    // every argument is saved before the copies use the registers
+    let mut copies: Vec<(u32, u32, u32)> = vec![];
+    for (i, (name, type_, _)) in decl.params.iter().enumerate() {
+        let offset = ctx.declare(name.clone(), type_.clone());
+        if type_.is_struct() {
+            let address = ctx.allocate(&pointer);
+            body += &ctx.target.store_argument(i + hidden, address, 8);
+            copies.push((offset, address, ctx.structs.size_of(type_)));
+        } else {
+            body += &ctx.target.store_argument(i + hidden, offset, ctx.structs.size_of(type_));
+        }
+    }
+    for (offset, address, size) in copies {
+        let (dst, src) = (ctx.srm.scratch_alloc(), ctx.srm.scratch_alloc());
+        body += &ctx.target.frame_address(src, address);
+        body += &ctx.target.load(src, 8, false);
+        body += &ctx.target.frame_address(dst, offset);
+        body += &ctx.target.copy(dst, src, size);
+        ctx.srm.scratch_free(dst);
+        ctx.srm.scratch_free(src);
+    }
+    body += &stmt_codegen(&decl.body, ctx);
+    ctx.exit_scope();
+
+    let mut code = format!("\n{}:{}\n", symbol(&decl.name), ctx.target.comment(&ctx.sources.location(decl.span).to_string()));
+    code += &ctx.target.prologue(align_up(ctx.frame_size, 16));
+    code += &body;
+    code += &ctx.target.return_zero();
+    code += &LabelGenerator::label_name(return_label);
+    code += "\n";
+    if let Some(offset) = ctx.return_pointer.take() {
+        let regu = ctx.srm.scratch_alloc();
+        code += &ctx.target.frame_address(regu, offset);
+        code += &ctx.target.load(regu, 8, false);
+        code += &ctx.target.return_value(regu);
+        ctx.srm.scratch_free(regu);
+    }
+    code += &ctx.target.epilogue();
+    if let Some(export) = &decl.export {
+        // the symbol C calls, with its convention
+        code += &ctx.target.export(export, &symbol(&decl.name));
+    }
+    ctx.return_label = None;
+    ctx.return_type = None;
+    return code;
+}
+
+/*
+ * the 64 bits value of a stored value of type_: sign or zero extended from its size, like a load does
+ */
+fn truncate(value: u64, type_: &Type) -> u64 {
+    match type_ {
+        Type::I8 => value as i8 as u64,
+        Type::I16 => value as i16 as u64,
+        Type::I32 => value as i32 as u64,
+        Type::U8 => value as u8 as u64,
+        Type::U16 => value as u16 as u64,
+        Type::U32 => value as u32 as u64,
+        _ => value,
+    }
+}
+
+/*
+ * Compute the value of a constant expression at compile time, with the semantics of the generated code
+ * only literals, operators and the consts declared before are allowed
+ */
+fn const_eval(expr: &Expr, ctx: &Context) -> u64 {
+    match &expr.kind {
+        ExprKind::Integer { value, .. } => *value,
+        ExprKind::Variable(name) => match ctx.lookup(name) {
+            Some(Place::Global(Global { value: Some(value), .. })) => value,
+            _ => {
+                eprintln!("ERROR:{}: `{}` is not a constant", ctx.sources.location(expr.span), name);
+                std::process::exit(1);
+            }
+        },
+        ExprKind::Unary { op, operand } => {
+            let value = const_eval(operand, ctx);
+            let value = match op {
+                UnaryOp::Neg => value.wrapping_neg(),
+                UnaryOp::Not => !value,
+            };
+            // computed at the size of the operand, like the code does
+            truncate(value, &type_of(operand, ctx))
+        }
+        ExprKind::Binary { op, op_span, lhs, rhs } => {
+            let type_ = binary_type(lhs, type_of(lhs, ctx), || type_of(rhs, ctx));
+            let signed = type_.is_signed();
+            let (left, right) = (const_eval(lhs, ctx), const_eval(rhs, ctx));
+            if (*op == BinaryOp::Div || *op == BinaryOp::Rem) && right == 0 {
+                eprintln!("ERROR:{}: attempt to divide by zero", ctx.sources.location(*op_span));
+                std::process::exit(1);
+            }
+            if (*op == BinaryOp::Div || *op == BinaryOp::Rem) && signed && right == u64::MAX && left == signed_min(ctx.structs.size_of(&type_)) as u64 {
+                let what = if *op == BinaryOp::Div { "divide" } else { "calculate the remainder" };
+                eprintln!("ERROR:{}: attempt to {} with overflow", ctx.sources.location(*op_span), what);
+                std::process::exit(1);
+            }
+            let (sleft, sright) = (left as i64, right as i64);
+            let value = match op {
+                BinaryOp::Add => left.wrapping_add(right),
+                BinaryOp::Sub => left.wrapping_sub(right),
+                BinaryOp::Mul => left.wrapping_mul(right),
+                BinaryOp::Div if signed => sleft.wrapping_div(sright) as u64,
+                BinaryOp::Div => left / right,
+                BinaryOp::Rem if signed => sleft.wrapping_rem(sright) as u64,
+                BinaryOp::Rem => left % right,
+                BinaryOp::And => left & right,
+                BinaryOp::Or => left | right,
+                BinaryOp::Xor => left ^ right,
+                // like the shift instructions, only the low 6 bits of the count are used
+                BinaryOp::Shl => left.wrapping_shl(right as u32),
+                BinaryOp::Shr if signed => sleft.wrapping_shr(right as u32) as u64,
+                BinaryOp::Shr => left.wrapping_shr(right as u32),
+                BinaryOp::Eq => (left == right) as u64,
+                BinaryOp::Ne => (left != right) as u64,
+                BinaryOp::Lt if signed => (sleft < sright) as u64,
+                BinaryOp::Lt => (left < right) as u64,
+                BinaryOp::Le if signed => (sleft <= sright) as u64,
+                BinaryOp::Le => (left <= right) as u64,
+                BinaryOp::Gt if signed => (sleft > sright) as u64,
+                BinaryOp::Gt => (left > right) as u64,
+                BinaryOp::Ge if signed => (sleft >= sright) as u64,
+                BinaryOp::Ge => (left >= right) as u64,
+            };
+            // computed at the size of the operands, like the code does, a comparison gives 0 or 1
+            if op.is_comparison() { value } else { truncate(value, &type_) }
+        }
+        _ => {
+            eprintln!("ERROR:{}: this expression can't be computed at compile time", ctx.sources.location(expr.span));
+            std::process::exit(1);
+        }
+    }
+}
+
+/*
+ * the data directives of a value of type type_, a struct value is a literal and its padding is zeroed
+ */
+fn data_codegen(type_: &Type, value: &Expr, ctx: &Context) -> String {
+    let value_type = type_of(value, ctx);
+    check_types(type_, value, &value_type, ctx);
+    let Type::Struct(name) = type_ else {
+        let size = ctx.structs.size_of(type_);
+        return ctx.target.data(size, truncate(const_eval(value, ctx), type_) & (u64::MAX >> (64 - size * 8)));
+    };
+    let ExprKind::StructLiteral { fields, .. } = &value.kind else {
+        eprintln!("ERROR:{}: the value of a global struct must be a struct literal", ctx.sources.location(value.span));
+        std::process::exit(1);
+    };
+    check_struct_literal(name, fields, ctx);
+    let layout = ctx.structs.layout(name).clone();
+    let mut code = "".to_string();
+    let mut offset = 0;
+    for field in &layout.fields {
+        if field.offset > offset {
+            code += &ctx.target.zeros(field.offset - offset);
+        }
+        code += &data_codegen(&field.type_, literal_field(name, fields, &field.name, value.span, ctx), ctx);
+        offset = field.offset + ctx.structs.size_of(&field.type_);
+    }
+    if layout.size > offset {
+        code += &ctx.target.zeros(layout.size - offset);
+    }
+    return code;
+}
+
+/*
+ * check the signatures of the functions and return them by name
+ */
+fn function_table(decls: &[FnDecl], externs: &[ExternDecl], sources: &SourceMap) -> HashMap<String, Function> {
+    let raw = Type::Pointer(Box::new(Type::U8));
+    let mut functions: HashMap<String, Function> = HashMap::new();
+    functions.insert("alloc".to_string(), Function { params: vec![Type::U64], returns: Some(raw.clone()), linkage: Linkage::Builtin });
+    functions.insert("exit".to_string(), Function { params: vec![Type::I64], returns: None, linkage: Linkage::Builtin });
+    functions.insert("free".to_string(), Function { params: vec![raw.clone()], returns: None, linkage: Linkage::Builtin });
+    // 1 when a number was read, 0 at the end of the input, -1 when the line is not a number
+    functions.insert("get".to_string(), Function { params: vec![Type::Pointer(Box::new(Type::I64))], returns: Some(Type::I64), linkage: Linkage::Builtin });
+    // the length of the line, -1 at the end of the input
+    functions.insert("get_line".to_string(), Function { params: vec![raw.clone(), Type::U64], returns: Some(Type::I64), linkage: Linkage::Builtin });
+    // write the bytes of a string on stdout, `put` writes numbers
+    functions.insert("print".to_string(), Function { params: vec![raw, Type::U64], returns: None, linkage: Linkage::Builtin });
+    let mut declared: HashMap<&str, Span> = HashMap::new();
+    let mut exported: HashMap<&str, Span> = HashMap::new();
+    for decl in decls {
+        if functions.get(&decl.name).is_some_and(|function| function.linkage == Linkage::Builtin) {
+            eprintln!("ERROR:{}: `{}` is a builtin function", sources.location(decl.span), decl.name);
+            std::process::exit(1);
+        }
+        if let Some(first) = declared.insert(&decl.name, decl.span) {
+            eprintln!("ERROR:{}: function `{}` is already declared at {}", sources.location(decl.span), decl.name, sources.location(first));
+            std::process::exit(1);
+        }
+        // a struct is returned through a pointer given as the first argument
+        let hidden = decl.returns.as_ref().is_some_and(Type::is_struct) as usize;
+        if decl.params.len() + hidden > target::MAX_ARGS {
+            eprintln!("ERROR:{}: function `{}` has more than {} parameters", sources.location(decl.span), decl.name, target::MAX_ARGS - hidden);
+            std::process::exit(1);
+        }
+        for (j, (name, type_, span)) in decl.params.iter().enumerate() {
+            if decl.params[..j].iter().any(|(other, _, _)| other == name) {
+                eprintln!("ERROR:{}: parameter `{}` is declared twice", sources.location(*span), name);
+                std::process::exit(1);
+            }
+            if type_.is_struct() && decl.export.is_some() {
+                eprintln!("ERROR:{}: parameter `{}` has type `{}`, only integers can be passed to a function exported to C", sources.location(*span), name, type_);
+                std::process::exit(1);
+            }
+        }
+        if let (Some(type_ @ Type::Struct(_)), Some(_)) = (&decl.returns, &decl.export) {
+            eprintln!("ERROR:{}: function `{}` returns a `{}`, only integers can be returned to C", sources.location(decl.span), decl.name, type_);
+            std::process::exit(1);
+        }
+        if decl.name == "main" {
+            check_main(decl, sources);
+        }
+        if let Some(export) = &decl.export {
+            if let Some(first) = exported.insert(export, decl.span) {
+                eprintln!("ERROR:{}: `{}` is already exported at {}", sources.location(decl.span), export, sources.location(first));
+                std::process::exit(1);
+            }
+            if let Some(other) = externs.iter().find(|other| other.name == *export) {
+                eprintln!("ERROR:{}: `{}` is already declared as a C function at {}", sources.location(decl.span), export, sources.location(other.span));
+                std::process::exit(1);
+            }
+        }
+        let params = decl.params.iter().map(|(_, type_, _)| type_.clone()).collect();
+        functions.insert(decl.name.clone(), Function { params, returns: decl.returns.clone(), linkage: Linkage::Stem });
+    }
+    let mut c_functions: HashMap<&str, &ExternDecl> = HashMap::new();
+    for decl in externs {
+        let params: Vec<Type> = decl.params.iter().map(|(_, type_, _)| type_.clone()).collect();
+        // the modules of a program can declare the same C function, with the same signature
+        if let Some(first) = c_functions.get(decl.name.as_str()) {
+            let first_params: Vec<&Type> = first.params.iter().map(|(_, type_, _)| type_).collect();
+            if first_params != params.iter().collect::<Vec<&Type>>() || first.variadic != decl.variadic || first.returns != decl.returns {
+                eprintln!("ERROR:{}: C function `{}` is already declared differently at {}", sources.location(decl.span), decl.name, sources.location(first.span));
+                std::process::exit(1);
+            }
+            continue;
+        }
+        c_functions.insert(&decl.name, decl);
+        if let Some(function) = functions.get(&decl.name) {
+            let what = if function.linkage == Linkage::Builtin { "a builtin function" } else { "already declared as a function of the program" };
+            eprintln!("ERROR:{}: `{}` is {}", sources.location(decl.span), decl.name, what);
+            std::process::exit(1);
+        }
+        if decl.params.len() > target::MAX_ARGS {
+            eprintln!("ERROR:{}: C function `{}` has more than {} parameters", sources.location(decl.span), decl.name, target::MAX_ARGS);
+            std::process::exit(1);
+        }
+        for (name, type_, span) in &decl.params {
+            if type_.is_struct() {
+                eprintln!("ERROR:{}: parameter `{}` has type `{}`, only integers can be passed to a C function", sources.location(*span), name, type_);
+                std::process::exit(1);
+            }
+        }
+        if let Some(type_ @ Type::Struct(_)) = &decl.returns {
+            eprintln!("ERROR:{}: C function `{}` returns a `{}`, only integers can be returned from C", sources.location(decl.span), decl.name, type_);
+            std::process::exit(1);
+        }
+        functions.insert(decl.name.clone(), Function { params, returns: decl.returns.clone(), linkage: Linkage::C { variadic: decl.variadic } });
+    }
+    return functions;
+}
+
+/*
+ * main is called by _start: `fn main()`, `fn main() -> i64` or `fn main(argc: i64, argv: **u8) -> i64`
+ */
+fn check_main(decl: &FnDecl, sources: &SourceMap) {
+    let argv = Type::Pointer(Box::new(Type::Pointer(Box::new(Type::U8))));
+    let params: Vec<&Type> = decl.params.iter().map(|(_, type_, _)| type_).collect();
+    if !params.is_empty() && params != [&Type::I64, &argv] {
+        eprintln!("ERROR:{}: `main` takes no parameters or `(argc: i64, argv: **u8)`", sources.location(decl.span));
+        std::process::exit(1);
+    }
+    if decl.returns.as_ref().is_some_and(|type_| *type_ != Type::I64) {
+        eprintln!("ERROR:{}: `main` returns `i64` or nothing", sources.location(decl.span));
+        std::process::exit(1);
+    }
+}
+
+/*
+ * the type of the global decls[i], exit with an error if its name is already taken
+ * the globals before it are in ctx.globals
+ */
+fn global_type(decls: &[GlobalDecl], i: usize, ctx: &Context) -> Type {
+    let decl = &decls[i];
+    if ctx.globals.contains_key(&decl.name) {
+        let first = decls.iter().find(|d| d.name == decl.name).unwrap();
+        eprintln!("ERROR:{}: `{}` is already declared at {}", ctx.sources.location(decl.span), decl.name, ctx.sources.location(first.span));
+        std::process::exit(1);
+    }
+    if ctx.functions.contains_key(&decl.name) {
+        eprintln!("ERROR:{}: `{}` is already the name of a function", ctx.sources.location(decl.span), decl.name);
+        std::process::exit(1);
+    }
+    match (&decl.type_, &decl.value) {
+        (Some(type_), _) => type_.clone(),
+        (None, Some(value)) => type_of(value, ctx),
+        (None, None) => unreachable!("the parser requires a type or a value"),
+    }
+}
+
+/*
+ * evaluate the globals in declaration order and return the .data, .rodata and .bss sections
+ */
+fn globals_codegen(decls: &[GlobalDecl], ctx: &mut Context) -> String {
+    let (mut data, mut rodata, mut bss) = ("".to_string(), "".to_string(), "".to_string());
+    for (i, decl) in decls.iter().enumerate() {
+        let type_ = global_type(decls, i, ctx);
+        let size = ctx.structs.size_of(&type_);
+        let align = ctx.structs.align_of(&type_);
+        let label = format!("{}:{}\n", symbol(&decl.name), ctx.target.comment(&ctx.sources.location(decl.span).to_string()));
+        let mut value = None;
+        match &decl.value {
+            None => {
+                bss += &format!("{}{label}{}", ctx.target.align(align, Section::Bss), ctx.target.reserve(size));
+            }
+            Some(expr) if !decl.mutable && !type_.is_struct() => {
+                value = Some(truncate(const_eval(expr, ctx), &type_));
+            }
+            Some(expr) if !decl.mutable => {
+                rodata += &format!("{}{label}{}", ctx.target.align(align, Section::Rodata), data_codegen(&type_, expr, ctx));
+            }
+            Some(expr) => {
+                data += &format!("{}{label}{}", ctx.target.align(align, Section::Data), data_codegen(&type_, expr, ctx));
+            }
+        }
+        ctx.globals.insert(decl.name.clone(), Global { type_, mutable: decl.mutable, value });
+    }
+    let target = ctx.target;
+    return format!("\n{}{data}\n{}{rodata}\n{}{bss}", target.section(Section::Data), target.section(Section::Rodata), target.section(Section::Bss));
+}
+
+/*
+ * the _start of `stem-rs test`, after its prologue: call the test whose index is the decimal number in argv[1]
+ * through the tests table, the process exits with 0 when it returns and with 101 when it panics
+ */
+fn test_start_codegen(argv: u32, ctx: &mut Context) -> String {
+    let digit_label = ctx.labels.label_create();
+    let call_label = ctx.labels.label_create();
+    let (pointer, index, digit) = (ctx.srm.scratch_alloc(), ctx.srm.scratch_alloc(), ctx.srm.scratch_alloc());
+    let target = ctx.target;
+    let mut code = target.frame_address(pointer, argv);
+    code += &target.load(pointer, 8, false);
+    code += &target.offset(pointer, pointer, 8);
+    code += &target.load(pointer, 8, false);
+    code += &target.load_immediate(index, 0);
+    code += &LabelGenerator::label_name(digit_label);
+    code += "\n";
+    code += &target.move_register(digit, pointer);
+    code += &target.load(digit, 1, false);
+    code += &target.branch_zero(digit, call_label);
+    code += &target.scale(index, 10);
+    code += &target.arith(BinaryOp::Add, index, digit, 8, false, None);
+    code += &target.offset(index, index, -48);
+    code += &target.offset(pointer, pointer, 1);
+    code += &target.jump(digit_label);
+    code += &LabelGenerator::label_name(call_label);
+    code += "\n";
+    code += &target.symbol_address(pointer, "tests");
+    code += &target.scale(index, 8);
+    code += &target.arith(BinaryOp::Add, pointer, index, 8, false, None);
+    code += &target.load(pointer, 8, false);
+    code += &target.call_register(pointer);
+    code += &target.argument_immediate(0, 0);
+    for r in [pointer, index, digit] {
+        ctx.srm.scratch_free(r);
+    }
+    return code;
+}
+
+/*
+ * what the command line asks of the code generation
+ */
+struct Options {
+    // panic when `+`, `-` or `*` overflow, off with --release
+    overflow_checks: bool,
+    // `stem-rs test`: build the test blocks and a _start that runs one of them, instead of the program
+    tests: bool,
+    // --cc: link with the C library through cc, for the extern functions, the entry point is the `main` of C
+    libc: bool,
+    // `stem-rs lib`: no entry point, C calls the functions declared `pub extern fn`
+    library: bool,
+    // --target=: the machine the code is generated for, x86_64 by default
+    target: &'static dyn Target,
+}
+
+/*
+ * the context to generate the code of program, with the signatures of its functions and the layouts of its structs
+ */
+fn new_context<'a>(program: &Program, sources: &'a SourceMap, options: &Options) -> Context<'a> {
+    return Context {
+        sources,
+        srm: ScratchRegisterManagement { in_use: [false; target::SCRATCH_COUNT] },
+        labels: LabelGenerator { counter: 1 },
+        variables: HashMap::new(),
+        declared: vec![],
+        scopes: vec![],
+        stack_size: 0,
+        frame_size: 0,
+        structs: StructTable::new(&program.structs, sources),
+        globals: HashMap::new(),
+        functions: function_table(&program.functions, &program.externs, sources),
+        return_label: None,
+        return_type: None,
+        return_pointer: None,
+        strings: vec![],
+        runtime: vec![],
+        overflow_checks: options.overflow_checks,
+        target: options.target,
+    };
+}
+
+/*
+ * _start: the top-level statements then main, or the test given on the command line, then exit
+ * linked with the C library it is the `main` of C instead, argc and argv are kept in the frame either way
+ */
+fn start_codegen(program: &Program, ctx: &mut Context, options: &Options) -> String {
+    let entry = if options.libc { "main" } else { "_start" };
+    let argc = ctx.allocate(&Type::I64);
+    let argv = ctx.allocate(&Type::I64);
+    let mut body = "".to_string();
+    if options.tests {
+        body += &test_start_codegen(argv, ctx);
+    } else {
+        for stmt in &program.statements {
+            ctx.srm.in_use = [false; target::SCRATCH_COUNT];
+            body += &stmt_codegen(stmt, ctx);
+        }
+
+        // then main, with argc and argv, and exit with what it returns
+        if let Some(main) = program.functions.iter().find(|function| function.name == "main") {
+            let r = ctx.srm.scratch_alloc();
+            for (i, offset) in [argc, argv].into_iter().enumerate() {
+                body += &ctx.target.frame_address(r, offset);
+                body += &ctx.target.load(r, 8, true);
+                body += &ctx.target.argument(i, r);
+            }
+            body += &ctx.target.call(&symbol("main"));
+            if main.returns.is_some() {
+                body += &ctx.target.result(r, 8, true);
+                body += &ctx.target.argument(0, r);
+            } else {
+                body += &ctx.target.argument_immediate(0, 0);
+            }
+            ctx.srm.scratch_free(r);
+        } else {
+            body += &ctx.target.argument_immediate(0, 0);
+        }
+    }
+    let mut code = ctx.target.section(Section::Text);
+    code += &ctx.target.entry(entry, options.libc, align_up(ctx.frame_size, 16), argc, argv);
+    code += &body;
+    code += &ctx.target.call(&runtime::symbol("exit"));
+    ctx.use_runtime("exit");
+    return code;
+}
+
+/*
+ * exit with an error if two tests have the same name
+ */
+fn check_tests(tests: &[TestDecl], sources: &SourceMap) {
+    let mut declared: HashMap<&str, Span> = HashMap::new();
+    for test in tests {
+        if let Some(first) = declared.insert(&test.name, test.span) {
+            eprintln!("ERROR:{}: test `{}` is already declared at {}", sources.location(test.span), test.name, sources.location(first));
+            std::process::exit(1);
+        }
+    }
+}
+
+/*
+ * the `extern` of the C functions the program declares, each one once
+ */
+fn externs_codegen(program: &Program, target: &dyn Target) -> String {
+    let mut code = "".to_string();
+    let mut declared: HashSet<&str> = HashSet::new();
+    for decl in &program.externs {
+        if declared.insert(&decl.name) {
+            code += &target.extern_symbol(&decl.name);
+        }
+    }
+    return code;
+}
+
+/*
+ * the messages of ctx, in .rodata
+ */
+fn strings_codegen(ctx: &Context) -> String {
+    let mut code = "".to_string();
+    for (i, text) in ctx.strings.iter().enumerate() {
+        code += &format!("string.{i}:\n{}", ctx.target.string(text));
+    }
+    return code;
+}
+
+/*
+ * Take a Program (the structs and the statements) and return a String (all the program as assembly)
+ */
+fn generate_code(program: &Program, sources: &SourceMap, options: &Options) -> String {
+    let mut ctx = new_context(program, sources, options);
+    let sections = globals_codegen(&program.globals, &mut ctx);
+    let mut code = "".to_string();
+    code += ctx.target.runtime().prelude;
+    code += &externs_codegen(program, ctx.target);
+    if !options.library {
+        code += &start_codegen(program, &mut ctx, options);
+    }
+
+    for function in &program.functions {
+        code += &fn_codegen(function, &mut ctx);
+    }
+    let mut tests = "".to_string();
+    if options.tests {
+        // a test is a function without parameters, named after its index
+        check_tests(&program.tests, sources);
+        for (i, test) in program.tests.iter().enumerate() {
+            let decl = FnDecl { name: format!("test.{i}"), params: vec![], returns: None, body: test.body.clone(), export: None, span: test.span };
+            code += &fn_codegen(&decl, &mut ctx);
+            tests += &ctx.target.address(&symbol(&decl.name));
+        }
+    }
+    code += &runtime::runtime_codegen(ctx.target, &ctx.runtime, options.libc);
+    code += &sections;
+    code += "\n";
+    code += &ctx.target.section(Section::Rodata);
+    if options.tests {
+        code += &format!("{}tests:\n{tests}", ctx.target.align(8, Section::Rodata));
+    }
+    code += &strings_codegen(&ctx);
+    return code.to_string();
+}
+
+/*
+ * the object of the module modules[index] alone, program is all the modules merged
+ * its functions and globals are global, the ones of the modules it imports and the runtime are extern
+ * only the file given to the compiler (the last module) has a _start
+ */
+fn generate_object(program: &Program, modules: &[Module], index: usize, sources: &SourceMap, options: &Options) -> String {
+    let mut ctx = new_context(program, sources, options);
+    let module = &modules[index];
+    // the globals of every module are evaluated, for the types and the constants, only the ones of this one are emitted
+    let mut sections = "".to_string();
+    for (i, other) in modules.iter().enumerate() {
+        let code = globals_codegen(&other.program.globals, &mut ctx);
+        if i == index {
+            sections = code;
+        }
+    }
+    // a folded constant has no symbol
+    let symbols = |module: &Module, ctx: &Context| -> Vec<String> {
+        let functions = module.program.functions.iter().map(|decl| symbol(&decl.name));
+        let globals = module.program.globals.iter().filter(|decl| ctx.globals[&decl.name].value.is_none()).map(|decl| symbol(&decl.name));
+        return functions.chain(globals).collect();
+    };
+
+    let mut body = "".to_string();
+    if index == modules.len() - 1 {
+        body += &start_codegen(program, &mut ctx, options);
+    }
+    body += &ctx.target.section(Section::Text);
+    for function in &module.program.functions {
+        body += &fn_codegen(function, &mut ctx);
+    }
+
+    let mut code = ctx.target.runtime().prelude.to_string();
+    for name in symbols(module, &ctx) {
+        code += &ctx.target.global(&name);
+    }
+    for import in &module.program.imports {
+        let imported = modules.iter().find(|other| other.prefix == import.name).expect("the imports are loaded");
+        for name in symbols(imported, &ctx) {
+            code += &ctx.target.extern_symbol(&name);
+        }
+    }
+    for name in &ctx.runtime {
+        code += &ctx.target.extern_symbol(&runtime::symbol(name));
+    }
+    code += &externs_codegen(program, ctx.target);
+    code += &body;
+    code += &sections;
+    code += "\n";
+    code += &ctx.target.section(Section::Rodata);
+    code += &strings_codegen(&ctx);
+    return code;
+}
+
+/*
+ * run a command of the build, exit with an error when it fails
+ */
+fn build_command(program: &str, args: &[&str]) {
+    match Command::new(program).args(args).status() {
+        Ok(status) if status.success() => {}
+        Ok(status) => {
+            eprintln!("ERROR: `{} {}` failed with {}", program, args.join(" "), status);
+            std::process::exit(1);
+        }
+        Err(error) => {
+            eprintln!("ERROR: can't run `{}`: {}", program, error);
+            std::process::exit(1);
+        }
+    }
+}
+
+/*
+ * assemble source in object, with the assembler of target
+ */
+fn assemble(target: &dyn Target, source: &str, object: &str) {
+    let command = target.assemble(source, object);
+    let args: Vec<&str> = command[1..].iter().map(String::as_str).collect();
+    build_command(&command[0], &args);
+}
+
+/*
+ * `stem-rs build`: every module is compiled to its own object, then the objects and the runtime are linked in output
+ * the objects are in a directory of their own for each target and each set of flags that changes the code,
+ * the one of the file given to the compiler is main.o, the ones of the modules it imports are modules/{prefix}.o
+ * an object is kept when it is newer than the compiler, than the file of its module and than the files that module imports
+ */
+fn build_objects(program: &Program, modules: &[Module], sources: &SourceMap, options: &Options) {
+    let dir = build_dir(options);
+    fs::create_dir_all(dir.join("modules")).expect("Can't create the build directory");
+    let compiler = std::env::current_exe().map_or_else(|_| std::time::SystemTime::now(), |path| modified(&path));
+    let stale = |object: &std::path::Path, paths: &[&std::path::Path]| -> bool {
+        let built = fs::metadata(object).and_then(|metadata| metadata.modified()).ok();
+        return built.is_none_or(|built| compiler > built || paths.iter().any(|path| modified(path) > built));
+    };
+    let mut objects: Vec<String> = vec![];
+    for (i, module) in modules.iter().enumerate() {
+        let name = if module.prefix.is_empty() { dir.join("main") } else { dir.join("modules").join(&module.prefix) };
+        let object = name.with_extension("o");
+        if stale(&object, &dependencies(modules, i)) {
+            let source = name.with_extension(options.target.extension());
+            fs::write(&source, generate_object(program, modules, i, sources, options)).expect("Can't write the output file");
+            assemble(options.target, &source.display().to_string(), &object.display().to_string());
+            println!("Compiled {}", module.path.display());
+        }
+        objects.push(object.display().to_string());
+    }
+    // the runtime linked with the C library leaves through its exit, which flushes the streams of C
+    let runtime = dir.join("runtime.o");
+    if stale(&runtime, &[]) {
+        let source = dir.join("runtime").with_extension(options.target.extension());
+        fs::write(&source, runtime::runtime_library(options.target, options.libc)).expect("Can't write the output file");
+        assemble(options.target, &source.display().to_string(), &runtime.display().to_string());
+    }
+    objects.push(runtime.display().to_string());
+    link(&objects, options);
+}
+
+/*
+ * the directory of the objects of `stem-rs build` for the target and the flags of options, like stem-build/x86_64-release-cc
+ */
+fn build_dir(options: &Options) -> std::path::PathBuf {
+    let mut name = format!("{}-{}", options.target.name(), if options.overflow_checks { "debug" } else { "release" });
+    if options.libc {
+        name += "-cc";
+    }
+    return std::path::Path::new("stem-build").join(name);
+}
+
+/*
+ * `stem-rs lib`: all the modules in a single object, archived in lib{name}.a or linked in the shared lib{name}.so
+ * with --shared, name being the file given to the compiler, and {name}.h which declares the exported functions
+ * the code only addresses its data relative to rip and calls C through the PLT, so it is position independent
+ */
+fn build_library(program: &Program, root: &Module, sources: &SourceMap, options: &Options, shared: bool) {
+    if let Some(stmt) = program.statements.first() {
+        eprintln!("ERROR:{}: a library can only declare items, not run statements", sources.location(stmt.span));
+        std::process::exit(1);
+    }
+    let name = root.path.file_stem().and_then(|stem| stem.to_str()).unwrap_or("output");
+    let source = format!("{name}.{}", options.target.extension());
+    fs::write(&source, generate_code(program, sources, options)).expect("Can't write the output file");
+    assemble(options.target, &source, &format!("{name}.o"));
+    let library = if shared { format!("lib{name}.so") } else { format!("lib{name}.a") };
+    if shared {
+        build_command(&options.target.tool("cc"), &["-shared", "-o", &library, &format!("{name}.o")]);
+    } else {
+        let _ = fs::remove_file(&library);
+        build_command(&options.target.tool("ar"), &["rcs", &library, &format!("{name}.o")]);
+    }
+    fs::write(format!("{name}.h"), header_codegen(program, name, &root.path)).expect("Can't write the output file");
+    println!("Built {library} and {name}.h");
+}
+
+/*
+ * the C header of a library: the prototype of each `pub extern fn`, the structs they point to are opaque
+ */
+fn header_codegen(program: &Program, name: &str, path: &std::path::Path) -> String {
+    let guard: String = name.chars().map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' }).collect();
+    let mut code = format!("/* {name}.h: the functions of {} exported with `pub extern fn`, generated by stem-rs */\n", path.display());
+    code += &format!("#ifndef {guard}_H\n#define {guard}_H\n\n#include <stdint.h>\n\n");
+    let exported: Vec<&FnDecl> = program.functions.iter().filter(|decl| decl.export.is_some()).collect();
+    let structs = c_structs(exported.iter().flat_map(|decl| decl.params.iter().map(|(_, type_, _)| type_).chain(&decl.returns)));
+    for c_name in &structs {
+        code += &format!("{c_name};\n");
+    }
+    if !structs.is_empty() {
+        code += "\n";
+    }
+    for decl in &exported {
+        let returns = decl.returns.as_ref().map_or("void".to_string(), c_type);
+        let params: Vec<String> = decl.params.iter().map(|(name, type_, _)| {
+            let type_ = c_type(type_);
+            if type_.ends_with('*') { format!("{type_}{name}") } else { format!("{type_} {name}") }
+        }).collect();
+        let params = if params.is_empty() { "void".to_string() } else { params.join(", ") };
+        code += &format!("{returns} {}({params});\n", decl.export.as_ref().unwrap());
+    }
+    code += &format!("\n#endif /* {guard}_H */\n");
+    return code;
+}
+
+/*
+ * the C type with the same layout as type_, a struct of a module is `struct module_name`
+ */
+fn c_type(type_: &Type) -> String {
+    match type_ {
+        Type::I8 => "int8_t".to_string(),
+        Type::I16 => "int16_t".to_string(),
+        Type::I32 => "int32_t".to_string(),
+        Type::I64 => "int64_t".to_string(),
+        Type::U8 => "uint8_t".to_string(),
+        Type::U16 => "uint16_t".to_string(),
+        Type::U32 => "uint32_t".to_string(),
+        Type::U64 => "uint64_t".to_string(),
+        Type::Struct(name) => format!("struct {}", name.replace("::", "_")),
+        Type::Pointer(pointee) => {
+            let pointee = c_type(pointee);
+            if pointee.ends_with('*') { format!("{pointee}*") } else { format!("{pointee} *") }
+        }
+    }
+}
+
+/*
+ * the C structs that types point to, to declare before the prototypes using them
+ */
+fn c_structs<'t>(types: impl IntoIterator<Item = &'t Type>) -> Vec<String> {
+    let mut structs: Vec<String> = vec![];
+    for type_ in types {
+        let mut pointee = type_;
+        while let Type::Pointer(inner) = pointee {
+            pointee = inner;
+        }
+        let c_name = c_type(pointee);
+        if pointee.is_struct() && !structs.contains(&c_name) {
+            structs.push(c_name);
+        }
+    }
+    return structs;
+}
+
+/*
+ * link objects in output, with ld and _start as the entry point, or with cc and the C library
+ * the code isn't position independent, the table of the tests holds absolute addresses
+ */
+fn link(objects: &[String], options: &Options) {
+    let mut args: Vec<&str> = objects.iter().map(String::as_str).collect();
+    args.extend(["-o", "output"]);
+    if options.libc {
+        args.insert(0, "-no-pie");
+        build_command(&options.target.tool("cc"), &args);
+    } else {
+        build_command(&options.target.tool("ld"), &args);
+    }
+    println!("Linked output");
+}
+
+/*
+ * the files of modules[index] and of the modules it imports, directly or not
+ */
+fn dependencies(modules: &[Module], index: usize) -> Vec<&std::path::Path> {
+    let mut pending = vec![index];
+    let mut seen = vec![false; modules.len()];
+    let mut paths = vec![];
+    while let Some(i) = pending.pop() {
+        if seen[i] {
+            continue;
+        }
+        seen[i] = true;
+        paths.push(modules[i].path.as_path());
+        for import in &modules[i].program.imports {
+            pending.extend(modules.iter().position(|other| other.prefix == import.name));
+        }
+    }
+    return paths;
+}
+
+/*
+ * when a file was last modified, the files that can't be read are always new
+ */
+fn modified(path: &std::path::Path) -> std::time::SystemTime {
+    return fs::metadata(path).and_then(|metadata| metadata.modified()).unwrap_or_else(|_| std::time::SystemTime::now());
+}
+
+/*
+ * the command running the output built with the tests, under the runner of the target when it isn't this machine
+ */
+fn test_runner(options: &Options) -> Vec<String> {
+    assemble(options.target, &format!("output.{}", options.target.extension()), "output.o");
+    link(&["output.o".to_string()], options);
+    match options.target.runner() {
+        Some(runner) => vec![runner.to_string(), "./output".to_string()],
+        None => vec!["./output".to_string()],
+    }
+}
+
+/*
+ * run each test in its own process: runner then the index of the test
+ * a test passes when its process exits with 0, a failed assert panics and exits with 101
+ * a runner that can't be spawned (no qemu or wasmtime) is an error, not a failed test
+ */
+fn run_tests(names: &[&str], runner: &[String]) {
+    println!("\nrunning {} tests", names.len());
+    let mut failed: Vec<&str> = vec![];
+    for (i, name) in names.iter().enumerate() {
+        let passed = match Command::new(&runner[0]).args(&runner[1..]).arg(i.to_string()).status() {
+            Ok(status) => status.success(),
+            Err(error) => {
+                eprintln!("ERROR: can't run `{}`: {}", runner.join(" "), error);
+                std::process::exit(1);
+            }
+        };
+        println!("test {} ... {}", name, if passed { "ok" } else { "FAILED" });
+        if !passed {
+            failed.push(name);
+        }
+    }
+    if !failed.is_empty() {
+        println!("\nfailures:");
+        for name in &failed {
+            println!("    {}", name);
+        }
+    }
+    let result = if failed.is_empty() { "ok" } else { "FAILED" };
+    println!("\ntest result: {}. {} passed; {} failed", result, names.len() - failed.len(), failed.len());
+    if !failed.is_empty() {
+        std::process::exit(1);
+    }
+}
+
+/*
+ * cursor over the borrowed tokens of a file, tokens[end] is the EOF and is returned past the end
+ */
+struct ParsingStruct<'a> {
+    sources: &'a SourceMap,
+    tokens: &'a [Token],
+    pointer_to_tokens: usize,
+    end: usize,
+    // false in the condition of an if or a while, where `x {` starts the block and not a struct literal
+    struct_literals: bool,
+}
+
+impl<'a> ParsingStruct<'a> {
+
+    fn new(sources: &'a SourceMap, tokens: &'a [Token], start: usize, end: usize) -> ParsingStruct<'a> {
+        ParsingStruct {
+            sources,
+            tokens,
+            pointer_to_tokens: start,
+            end,
+            struct_literals: true,
+        }
+    }
+
+    fn next_token(&self) -> &'a Token {
+        &self.tokens[self.pointer_to_tokens]
+    }
+
+    fn at_end(&self) -> bool {
+        self.pointer_to_tokens == self.end
+    }
+
+    /*
+     * current token <- next_token
+     * next_token <- next_next_token
+     */
+    fn scan_token(&mut self) -> &'a Token {
+        let token = self.next_token();
+        if self.pointer_to_tokens < self.end {
+            self.pointer_to_tokens += 1;
+        }
+        token
+    }
+
+    /*
+     * scan the next token if it has the type type_, exit with an error otherwise
+     */
+    fn expect(&mut self, type_: TokenType, expected: &str) -> &'a Token {
+        let token = self.next_token();
+        if token.type_ != type_ || self.at_end() {
+            eprintln!("ERROR:{}: expected {} but found `{}`", self.sources.location(token.span), expected, token.lexeme);
+            std::process::exit(1);
+        }
+        self.scan_token()
+    }
+}
+
+    /* parse the vec of token in a Program
+     *
+     * Scaning scheme
+     * I -> struct ID { ID : T {, ID : T} } | [pub extern] fn ID ( [ID : T {, ID : T}] ) [-> T] { {S} }
+     *    | extern fn ID ( [ID : T {, ID : T}] [, ...] ) [-> T] ;
+     *    | static ID [: T] [= E] ; | const ID [: T] = E ; | test String { {S} }
+     *    | import String ; | mod ID ; | S
+     * T -> N | *T
+     * N -> ID {:: ID}
+     * S -> { {S} } | let ID [: T] = E ; | if E { {S} } [else (if ... | { {S} })]
+     *    | while E { {S} } | return [E] ; | put E ; | assert ( E [, String] ) ; | E ;
+     * E -> F {OP F}      OP from the lowest precedence: = += -= ... (right assoc.), |, ^, &, == !=, < <= > >=, << >>, + -, * / %
+     * F -> P {. ID | ++ | --}
+     * P -> N | N { ID : E {, ID : E} } | N ( [E {, E}] ) | Integer | String | (E) | -F | ~F | &F | *F
+     */
+fn parse(tokens: &[Token], sources: &SourceMap) -> Program {
+    let mut structs: Vec<StructDecl> = vec![];
+    let mut functions: Vec<FnDecl> = vec![];
+    let mut externs: Vec<ExternDecl> = vec![];
+    let mut globals: Vec<GlobalDecl> = vec![];
+    let mut tests: Vec<TestDecl> = vec![];
+    let mut imports: Vec<ImportDecl> = vec![];
+    let mut  program: Vec<Stmt> = vec![];
+    let mut token_str = ParsingStruct::new(sources, tokens, 0, tokens.len() - 1);
+    while !token_str.at_end() {
+        match token_str.next_token().type_ {
+            TokenType::Struct => structs.push(parse_struct(&mut token_str)),
+            TokenType::Fn => functions.push(parse_fn(&mut token_str)),
+            TokenType::Extern => externs.push(parse_extern(&mut token_str)),
+            TokenType::Pub => {
+                let start = token_str.scan_token().span;
+                token_str.expect(TokenType::Extern, "`extern` after `pub`");
+                let mut decl = parse_fn(&mut token_str);
+                decl.export = Some(decl.name.clone());
+                decl.span = start.to(decl.span);
+                functions.push(decl);
+            }
+            TokenType::Static | TokenType::Const => globals.push(parse_global(&mut token_str)),
+            TokenType::Test => tests.push(parse_test(&mut token_str)),
+            TokenType::Import | TokenType::Module => imports.push(parse_import(&mut token_str)),
+            _ => program.push(parse_s(&mut token_str)),
+        }
+    }
+    return Program { structs, functions, externs, globals, tests, imports, statements: program };
+}
+
+/*
+ * `import "path";` or `mod name;`
+ */
+fn parse_import(token_str: &mut ParsingStruct) -> ImportDecl {
+    let keyword = token_str.scan_token();
+    let (path, name, end) = if keyword.type_ == TokenType::Module {
+        let name = token_str.expect(TokenType::Word, "the name of a module");
+        (format!("{}.stm", name.lexeme), name.lexeme.clone(), name.span)
+    } else {
+        let string = token_str.expect(TokenType::String, "the path of a file");
+        let path = parse_string(token_str, string);
+        let file_name = std::path::Path::new(&path).file_stem().and_then(|stem| stem.to_str()).unwrap_or("");
+        let valid = file_name.chars().next().is_some_and(|c| c.is_alphabetic() || c == '_')
+            && file_name.chars().all(|c| c.is_alphanumeric() || c == '_')
+            && lexer::keyword(file_name).is_none();
+        if !valid {
+            eprintln!("ERROR:{}: `{}` can't be imported, `{}` is not a valid module name", token_str.sources.location(string.span), path, file_name);
+            std::process::exit(1);
+        }
+        (path.clone(), file_name.to_string(), string.span)
+    };
+    let span = keyword.span.to(end);
+    expect_semicolon(token_str, span);
+    return ImportDecl { path, name, span };
+}
+
+/*
+ * a name, with the modules it is in: `name` or `module::name`
+ * returned as a single Word token spanning all of it
+ */
+fn parse_path(token_str: &mut ParsingStruct, expected: &str) -> Token {
+    let mut path = token_str.expect(TokenType::Word, expected).clone();
+    while token_str.next_token().type_ == TokenType::PathSep {
+        token_str.scan_token();
+        let name = token_str.expect(TokenType::Word, "a name after `::`");
+        path.lexeme += "::";
+        path.lexeme += &name.lexeme;
+        path.span = path.span.to(name.span);
+    }
+    return path;
+}
+
+/*
+ * `test "name" { ... }`
+ */
+fn parse_test(token_str: &mut ParsingStruct) -> TestDecl {
+    let start = token_str.expect(TokenType::Test, "`test`").span;
+    let name = token_str.expect(TokenType::String, "the name of the test");
+    let body = parse_block(token_str);
+    return TestDecl { name: parse_string(token_str, name), body, span: start.to(name.span) };
+}
+
+/*
+ * declaration of a struct, fields are separated by `,`
+ */
+fn parse_struct(token_str: &mut ParsingStruct) -> StructDecl {
+    let start = token_str.expect(TokenType::Struct, "`struct`").span;
+    let name = token_str.expect(TokenType::Word, "the name of the struct").lexeme.clone();
+    token_str.expect(TokenType::OpenBrace, "`{`");
+    let mut fields: Vec<(String, Type, Span)> = vec![];
+    while token_str.next_token().type_ != TokenType::CloseBrace {
+        let field = token_str.expect(TokenType::Word, "the name of a field");
+        token_str.expect(TokenType::Colon, "`:`");
+        let (type_, span) = parse_type(token_str);
+        fields.push((field.lexeme.clone(), type_, field.span.to(span)));
+        if token_str.next_token().type_ != TokenType::CloseBrace {
+            token_str.expect(TokenType::Comma, "`,` or `}`");
+        }
+    }
+    let end = token_str.expect(TokenType::CloseBrace, "`}`").span;
+    return StructDecl { name, fields, span: start.to(end) };
+}
+
+/*
+ * a type: a name or `*type`, and the span it was written at
+ */
+fn parse_type(token_str: &mut ParsingStruct) -> (Type, Span) {
+    if token_str.next_token().type_ == TokenType::Mult {
+        let start = token_str.scan_token().span;
+        let (pointee, end) = parse_type(token_str);
+        return (Type::Pointer(Box::new(pointee)), start.to(end));
+    }
+    let name = parse_path(token_str, "a type");
+    return (Type::from_name(&name.lexeme), name.span);
+}
+
+/*
+ * `fn name(param: type, ...) -> type { ... }`, the return type is optional
+ */
+fn parse_fn(token_str: &mut ParsingStruct) -> FnDecl {
+    let start = token_str.expect(TokenType::Fn, "`fn`").span;
+    let name = token_str.expect(TokenType::Word, "the name of the function").lexeme.clone();
+    token_str.expect(TokenType::OpenParen, "`(`");
+    let mut params: Vec<(String, Type, Span)> = vec![];
+    while token_str.next_token().type_ != TokenType::CloseParen {
+        let param = token_str.expect(TokenType::Word, "the name of a parameter");
+        token_str.expect(TokenType::Colon, "`:`");
+        let (type_, span) = parse_type(token_str);
+        params.push((param.lexeme.clone(), type_, param.span.to(span)));
+        if token_str.next_token().type_ != TokenType::CloseParen {
+            token_str.expect(TokenType::Comma, "`,` or `)`");
+        }
+    }
+    let end = token_str.expect(TokenType::CloseParen, "`)`").span;
+    let mut returns = None;
+    if token_str.next_token().type_ == TokenType::Arrow {
+        token_str.scan_token();
+        returns = Some(parse_type(token_str).0);
+    }
+    let body = parse_block(token_str);
+    return FnDecl { name, params, returns, body, export: None, span: start.to(end) };
+}
+
+/*
+ * `extern fn name(param: type, ...) -> type;`, `...` after the parameters makes it variadic
+ */
+fn parse_extern(token_str: &mut ParsingStruct) -> ExternDecl {
+    let start = token_str.expect(TokenType::Extern, "`extern`").span;
+    token_str.expect(TokenType::Fn, "`fn`");
+    let name = token_str.expect(TokenType::Word, "the name of the function").lexeme.clone();
+    token_str.expect(TokenType::OpenParen, "`(`");
+    let mut params: Vec<(String, Type, Span)> = vec![];
+    let mut variadic = false;
+    while token_str.next_token().type_ != TokenType::CloseParen {
+        if token_str.next_token().type_ == TokenType::Ellipsis && !params.is_empty() {
+            token_str.scan_token();
+            variadic = true;
+            break;
+        }
+        let param = token_str.expect(TokenType::Word, "the name of a parameter");
+        token_str.expect(TokenType::Colon, "`:`");
+        let (type_, span) = parse_type(token_str);
+        params.push((param.lexeme.clone(), type_, param.span.to(span)));
+        if token_str.next_token().type_ != TokenType::CloseParen {
+            token_str.expect(TokenType::Comma, "`,` or `)`");
+        }
+    }
+    let mut end = token_str.expect(TokenType::CloseParen, "`)`").span;
+    let mut returns = None;
+    if token_str.next_token().type_ == TokenType::Arrow {
+        token_str.scan_token();
+        let (type_, span) = parse_type(token_str);
+        returns = Some(type_);
+        end = span;
+    }
+    expect_semicolon(token_str, start.to(end));
+    return ExternDecl { name, params, variadic, returns, span: start.to(end) };
+}
+
+/*
+ * `static name: type = value;` or `const name: type = value;`
+ * the type can be left to the value, a static without value needs a type
+ */
+fn parse_global(token_str: &mut ParsingStruct) -> GlobalDecl {
+    let keyword = token_str.scan_token();
+    let mutable = keyword.type_ == TokenType::Static;
+    let name = token_str.expect(TokenType::Word, "a name");
+    let mut type_ = None;
+    if token_str.next_token().type_ == TokenType::Colon {
+        token_str.scan_token();
+        type_ = Some(parse_type(token_str).0);
+    }
+    let mut value = None;
+    if token_str.next_token().type_ == TokenType::Assign || !mutable {
+        token_str.expect(TokenType::Assign, "`=`");
+        value = Some(parse_expr(token_str, 0));
+    } else if type_.is_none() {
+        eprintln!("ERROR:{}: static `{}` needs a type or a value", token_str.sources.location(name.span), name.lexeme);
+        std::process::exit(1);
+    }
+    let span = keyword.span.to(name.span);
+    expect_semicolon(token_str, value.as_ref().map_or(span, |value: &Expr| span.to(value.span)));
+    return GlobalDecl { name: name.lexeme.clone(), type_, value, mutable, span };
+}
+
+/*
+ * a statement, with its `;`
+ */
+fn parse_s(token_str: &mut ParsingStruct) -> Stmt {
+    let token = token_str.next_token();
+    if token.type_ == TokenType::OpenBrace {
+        return parse_block(token_str);
+    } else if token.type_ == TokenType::Let {
+        token_str.scan_token();
+        let name = token_str.expect(TokenType::Word, "the name of a variable").lexeme.clone();
+        let mut type_ = None;
+        if token_str.next_token().type_ == TokenType::Colon {
+            token_str.scan_token();
+            type_ = Some(parse_type(token_str).0);
+        }
+        token_str.expect(TokenType::Assign, "`=`");
+        let value = parse_expr(token_str, 0);
+        let span = token.span.to(value.span);
+        expect_semicolon(token_str, span);
+        return Stmt { kind: StmtKind::Let { name, type_, value }, span };
+    } else if token.type_ == TokenType::If {
+        token_str.scan_token();
+        let condition = parse_condition(token_str);
+        let then = parse_block(token_str);
+        let mut otherwise = None;
+        let mut span = token.span.to(then.span);
+        if token_str.next_token().type_ == TokenType::Else {
+            token_str.scan_token();
+            let stmt = if token_str.next_token().type_ == TokenType::If { parse_s(token_str) } else { parse_block(token_str) };
+            span = token.span.to(stmt.span);
+            otherwise = Some(Box::new(stmt));
+        }
+        return Stmt { kind: StmtKind::If { condition, then: Box::new(then), otherwise }, span };
+    } else if token.type_ == TokenType::While {
+        token_str.scan_token();
+        let condition = parse_condition(token_str);
+        let body = parse_block(token_str);
+        let span = token.span.to(body.span);
+        return Stmt { kind: StmtKind::While { condition, body: Box::new(body) }, span };
+    } else if token.type_ == TokenType::Return {
+        token_str.scan_token();
+        let mut value = None;
+        let mut span = token.span;
+        if token_str.next_token().type_ != TokenType::Semicolon {
+            let expr = parse_expr(token_str, 0);
+            span = span.to(expr.span);
+            value = Some(expr);
+        }
+        expect_semicolon(token_str, span);
+        return Stmt { kind: StmtKind::Return(value), span };
+    } else if token.type_ == TokenType::Put {
+        token_str.scan_token();
+        let expr = parse_expr(token_str, 0);
+        let span = token.span.to(expr.span);
+        expect_semicolon(token_str, span);
+        return Stmt { kind: StmtKind::Put(expr), span };
+    } else if token.type_ == TokenType::Assert {
+        token_str.scan_token();
+        token_str.expect(TokenType::OpenParen, "`(`");
+        let condition = parse_expr(token_str, 0);
+        let mut message = None;
+        if token_str.next_token().type_ == TokenType::Comma {
+            token_str.scan_token();
+            let string = token_str.expect(TokenType::String, "a message");
+            message = Some(parse_string(token_str, string));
+        }
+        let span = token.span.to(token_str.expect(TokenType::CloseParen, "`)`").span);
+        expect_semicolon(token_str, span);
+        return Stmt { kind: StmtKind::Assert { condition, message }, span };
+    }
+    let expr = parse_expr(token_str, 0);
+    let span = expr.span;
+    expect_semicolon(token_str, span);
+    return Stmt { kind: StmtKind::Expr(expr), span };
+}
+
+/*
+ * the text of a string token, without its quotes and with its escapes replaced
+ */
+fn parse_string(token_str: &ParsingStruct, token: &Token) -> String {
+    let mut text = "".to_string();
+    let mut chars = token.lexeme[1..token.lexeme.len() - 1].chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            text.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => text.push('\n'),
+            Some('t') => text.push('\t'),
+            Some('0') => text.push('\0'),
+            Some('\\') => text.push('\\'),
+            Some('"') => text.push('"'),
+            other => {
+                let escape = other.map_or("".to_string(), |c| c.to_string());
+                eprintln!("ERROR:{}: unknown escape `\\{}` in string", token_str.sources.location(token.span), escape);
+                std::process::exit(1);
+            }
+        }
+    }
+    return text;
+}
+
+/*
+ * `{ S ... }`
+ */
+fn parse_block(token_str: &mut ParsingStruct) -> Stmt {
+    let start = token_str.expect(TokenType::OpenBrace, "`{`").span;
+    let mut stmts: Vec<Stmt> = vec![];
+    while token_str.next_token().type_ != TokenType::CloseBrace {
+        if token_str.at_end() {
+            eprintln!("ERROR:{}: `{{` is never closed", token_str.sources.location(start));
+            std::process::exit(1);
+        }
+        stmts.push(parse_s(token_str));
+    }
+    let end = token_str.expect(TokenType::CloseBrace, "`}`").span;
+    return Stmt { kind: StmtKind::Block(stmts), span: start.to(end) };
+}
+
+/*
+ * the condition of an if or a while, a struct literal needs parentheses there
+ */
+fn parse_condition(token_str: &mut ParsingStruct) -> Expr {
+    token_str.struct_literals = false;
+    let condition = parse_expr(token_str, 0);
+    token_str.struct_literals = true;
+    return condition;
+}
+
+/*
+ * the `;` that ends the statement spanning span, the error points just after the statement
+ */
+fn expect_semicolon(token_str: &mut ParsingStruct, span: Span) {
+    if token_str.next_token().type_ == TokenType::Semicolon && !token_str.at_end() {
+        token_str.scan_token();
+        return;
+    }
+    let end = Span { start: span.end, ..span };
+    eprintln!("ERROR:{}: expected `;` after this statement but found `{}`", token_str.sources.location(end), token_str.next_token().lexeme);
+    std::process::exit(1);
+}
+
+fn binary(op: BinaryOp, op_span: Span, lhs: Expr, rhs: Expr) -> Expr {
+    let span = lhs.span.to(rhs.span);
+    return Expr::new(ExprKind::Binary { op, op_span, lhs: Box::new(lhs), rhs: Box::new(rhs) }, span);
+}
+
+enum Infix {
+    Assign,
+    CompoundAssign(BinaryOp),
+    Binary(BinaryOp),
+}
+
+#[derive(PartialEq)]
+enum Associativity {
+    Left,
+    Right,
+}
+
+/*
+ * the operators that go between two operands, with their precedence (higher binds tighter)
+ * the levels are the ones of C
+ */
+fn infix_operator(type_: TokenType) -> Option<(Infix, u8, Associativity)> {
+    let operator = match type_ {
+        TokenType::Assign => (Infix::Assign, 1, Associativity::Right),
+        TokenType::PlusAssign => (Infix::CompoundAssign(BinaryOp::Add), 1, Associativity::Right),
+        TokenType::MinusAssign => (Infix::CompoundAssign(BinaryOp::Sub), 1, Associativity::Right),
+        TokenType::MultAssign => (Infix::CompoundAssign(BinaryOp::Mul), 1, Associativity::Right),
+        TokenType::DivAssign => (Infix::CompoundAssign(BinaryOp::Div), 1, Associativity::Right),
+        TokenType::ModAssign => (Infix::CompoundAssign(BinaryOp::Rem), 1, Associativity::Right),
+        TokenType::AndAssign => (Infix::CompoundAssign(BinaryOp::And), 1, Associativity::Right),
+        TokenType::OrAssign => (Infix::CompoundAssign(BinaryOp::Or), 1, Associativity::Right),
+        TokenType::XorAssign => (Infix::CompoundAssign(BinaryOp::Xor), 1, Associativity::Right),
+        TokenType::ShiftLeftAssign => (Infix::CompoundAssign(BinaryOp::Shl), 1, Associativity::Right),
+        TokenType::ShiftRightAssign => (Infix::CompoundAssign(BinaryOp::Shr), 1, Associativity::Right),
+        TokenType::BitOr => (Infix::Binary(BinaryOp::Or), 2, Associativity::Left),
+        TokenType::BitXor => (Infix::Binary(BinaryOp::Xor), 3, Associativity::Left),
+        TokenType::BitAnd => (Infix::Binary(BinaryOp::And), 4, Associativity::Left),
+        TokenType::Equal => (Infix::Binary(BinaryOp::Eq), 5, Associativity::Left),
+        TokenType::NotEqual => (Infix::Binary(BinaryOp::Ne), 5, Associativity::Left),
+        TokenType::Less => (Infix::Binary(BinaryOp::Lt), 6, Associativity::Left),
+        TokenType::LessEqual => (Infix::Binary(BinaryOp::Le), 6, Associativity::Left),
+        TokenType::Greater => (Infix::Binary(BinaryOp::Gt), 6, Associativity::Left),
+        TokenType::GreaterEqual => (Infix::Binary(BinaryOp::Ge), 6, Associativity::Left),
+        TokenType::ShiftLeft => (Infix::Binary(BinaryOp::Shl), 7, Associativity::Left),
+        TokenType::ShiftRight => (Infix::Binary(BinaryOp::Shr), 7, Associativity::Left),
+        TokenType::Plus => (Infix::Binary(BinaryOp::Add), 8, Associativity::Left),
+        TokenType::Minus => (Infix::Binary(BinaryOp::Sub), 8, Associativity::Left),
+        TokenType::Mult => (Infix::Binary(BinaryOp::Mul), 9, Associativity::Left),
+        TokenType::Div => (Infix::Binary(BinaryOp::Div), 9, Associativity::Left),
+        TokenType::Mod => (Infix::Binary(BinaryOp::Rem), 9, Associativity::Left),
+        _ => return None,
+    };
+    return Some(operator);
+}
+
+/*
+ * an expression whose operators all have a precedence of at least min_precedence (precedence climbing)
+ * parse_expr(token_str, 0) reads a whole expression
+ */
+fn parse_expr(token_str: &mut ParsingStruct, min_precedence: u8) -> Expr {
+    let mut a = parse_f(token_str);
+    while let Some((operator, precedence, associativity)) = infix_operator(token_str.next_token().type_) {
+        if precedence < min_precedence {
+            break;
+        }
+        let op_span = token_str.scan_token().span;
+        // a left associative operator stops its right operand at the next operator of the same level
+        let next_precedence = if associativity == Associativity::Left { precedence + 1 } else { precedence };
+        let b = parse_expr(token_str, next_precedence);
+        let span = a.span.to(b.span);
+        a = match operator {
+            Infix::Binary(op) => binary(op, op_span, a, b),
+            Infix::Assign => Expr::new(ExprKind::Assign { target: Box::new(a), value: Box::new(b) }, span),
+            Infix::CompoundAssign(op) => Expr::new(ExprKind::CompoundAssign { op, op_span, target: Box::new(a), value: Box::new(b) }, span),
+        };
+    }
+    return a;
+}
+
+/*
+ * field accesses and `++`/`--`, they bind tighter than any other operand
+ */
+fn parse_f(token_str: &mut ParsingStruct) -> Expr {
+    let mut a = parse_p(token_str);
+    loop {
+        let token = token_str.next_token();
+        if token.type_ == TokenType::Dot {
+            token_str.scan_token();
+            let field = token_str.expect(TokenType::Word, "the name of a field");
+            let span = a.span.to(field.span);
+            a = Expr::new(ExprKind::Field { base: Box::new(a), field: field.lexeme.clone() }, span);
+        } else if token.type_ == TokenType::Increment || token.type_ == TokenType::Decrement {
+            token_str.scan_token();
+            let op = if token.type_ == TokenType::Increment { BinaryOp::Add } else { BinaryOp::Sub };
+            let span = a.span.to(token.span);
+            a = Expr::new(ExprKind::Postfix { op, op_span: token.span, target: Box::new(a) }, span);
+        } else {
+            return a;
+        }
+    }
+}
+
+/*
+ * a primary expression: a literal, a variable, a call, a struct literal, a prefix operator applied to an operand
+ * or an expression in parentheses
+ */
+fn parse_p(token_str: &mut ParsingStruct) -> Expr {
+    let token = token_str.next_token();
+    if token.type_ == TokenType::Integer {
+        token_str.scan_token();
+        return parse_integer(token, false, token_str.sources);
+    } else if token.type_ == TokenType::String {
+        token_str.scan_token();
+        return Expr::new(ExprKind::String(parse_string(token_str, token)), token.span);
+    } else if token.type_ == TokenType::Word {
+        let name = parse_path(token_str, "a name");
+        if token_str.next_token().type_ == TokenType::OpenBrace && token_str.struct_literals {
+            return parse_struct_literal(token_str, &name);
+        }
+        if token_str.next_token().type_ == TokenType::OpenParen {
+            return parse_call(token_str, &name);
+        }
+        return Expr::new(ExprKind::Variable(name.lexeme), name.span);
+    } else if token.type_ == TokenType::Minus {
+        token_str.scan_token();
+        let operand = if token_str.next_token().type_ == TokenType::Integer {
+            parse_integer(token_str.scan_token(), true, token_str.sources)
+        } else {
+            parse_f(token_str)
+        };
+        let span = token.span.to(operand.span);
+        return Expr::new(ExprKind::Unary { op: UnaryOp::Neg, operand: Box::new(operand) }, span);
+    } else if token.type_ == TokenType::BitAnd || token.type_ == TokenType::Mult {
+        token_str.scan_token();
+        let operand = Box::new(parse_f(token_str));
+        let span = token.span.to(operand.span);
+        let kind = if token.type_ == TokenType::BitAnd { ExprKind::AddressOf(operand) } else { ExprKind::Deref(operand) };
+        return Expr::new(kind, span);
+    } else if token.type_ == TokenType::BitNot {
+        token_str.scan_token();
+        let operand = parse_f(token_str);
+        let span = token.span.to(operand.span);
+        return Expr::new(ExprKind::Unary { op: UnaryOp::Not, operand: Box::new(operand) }, span);
+    } else if token.type_ == TokenType::OpenParen {
+        token_str.scan_token();
+        let struct_literals = token_str.struct_literals;
+        token_str.struct_literals = true;
+        let expr = parse_expr(token_str, 0);
+        token_str.struct_literals = struct_literals;
+        if token_str.next_token().type_ == TokenType::CloseParen {
+            let span = token.span.to(token_str.next_token().span);
+            token_str.scan_token();
+            return Expr { span, ..expr };
+        } else {
+            eprintln!("ERROR:{}: `(` is never closed", token_str.sources.location(token.span)); 
+            std::process::exit(1);
+        }
+    } else {
+        eprintln!("ERROR:{}: Unknown token type `{:?}` in parse_p", token_str.sources.location(token.span), token.type_);
+        std::process::exit(1);
+    }
+}
+
+/*
+ * the Integer expr of an Integer token, negated is true for the literal of `-128i8`
+ */
+fn parse_integer(token: &Token, negated: bool, sources: &SourceMap) -> Expr {
+    match lexer::integer_literal(&token.lexeme, negated) {
+        Ok((value, type_)) => Expr::new(ExprKind::Integer { value, type_ }, token.span),
+        Err(message) => {
+            eprintln!("ERROR:{}: {}", sources.location(token.span), message);
+            std::process::exit(1);
+        }
+    }
+}
+
+/*
+ * `name(E, ...)`, the name has already been scanned
+ */
+fn parse_call(token_str: &mut ParsingStruct, name: &Token) -> Expr {
+    token_str.expect(TokenType::OpenParen, "`(`");
+    let struct_literals = token_str.struct_literals;
+    token_str.struct_literals = true;
+    let mut args: Vec<Expr> = vec![];
+    while token_str.next_token().type_ != TokenType::CloseParen {
+        if token_str.at_end() {
+            eprintln!("ERROR:{}: `(` is never closed", token_str.sources.location(name.span));
+            std::process::exit(1);
+        }
+        args.push(parse_expr(token_str, 0));
+        if token_str.next_token().type_ != TokenType::CloseParen {
+            token_str.expect(TokenType::Comma, "`,` or `)`");
+        }
+    }
+    token_str.struct_literals = struct_literals;
+    let end = token_str.expect(TokenType::CloseParen, "`)`").span;
+    return Expr::new(ExprKind::Call { name: name.lexeme.clone(), args }, name.span.to(end));
+}
+
+/*
+ * `Name { field: E, ... }`, the name has already been scanned
+ */
+fn parse_struct_literal(token_str: &mut ParsingStruct, name: &Token) -> Expr {
+    token_str.expect(TokenType::OpenBrace, "`{`");
+    let mut fields: Vec<(String, Expr)> = vec![];
+    while token_str.next_token().type_ != TokenType::CloseBrace {
+        let field = token_str.expect(TokenType::Word, "the name of a field").lexeme.clone();
+        token_str.expect(TokenType::Colon, "`:`");
+        // `=` is not allowed in a field without parentheses
+        fields.push((field, parse_expr(token_str, 2)));
+        if token_str.next_token().type_ != TokenType::CloseBrace {
+            token_str.expect(TokenType::Comma, "`,` or `}`");
+        }
+    }
+    let end = token_str.expect(TokenType::CloseBrace, "`}`").span;
+    let span = name.span.to(end);
+    return Expr::new(ExprKind::StructLiteral { name: name.lexeme.clone(), fields }, span);
+}
+
+
+/*
+ * every pass recurses over the tree, `1 + 1 + ... + 1` is as deep as it is long: the compiler runs on a thread whose
+ * stack is big enough for hundreds of thousands of operands, the pages are only used when it goes that deep
+ */
+const STACK_SIZE: usize = 1 << 30;
+
+fn main() {
+    let compiler = std::thread::Builder::new().stack_size(STACK_SIZE).spawn(compile).expect("Can't start the compiler");
+    if let Err(panic) = compiler.join() {
+        std::panic::resume_unwind(panic);
+    }
+}
+
+fn compile() {
+    let mut args = std::env::args();
+    args.next(); // consume program name
+    // --release leaves the overflow checks out, --cc links with the C library, --target= chooses the machine,
+    // --emit=c writes the program in C instead
+    let (flags, mut files): (Vec<String>, Vec<String>) = args.partition(|arg| arg.starts_with("--"));
+    let release = flags.iter().any(|flag| flag == "--release");
+    let mut libc = flags.iter().any(|flag| flag == "--cc");
+    let shared = flags.iter().any(|flag| flag == "--shared");
+    if let Some(flag) = flags.iter().find(|flag| !["--release", "--cc", "--shared"].contains(&flag.as_str()) && !flag.starts_with("--target=") && !flag.starts_with("--emit=")) {
+        eprintln!("ERROR: unknown option `{}`", flag);
+        std::process::exit(1);
+    }
+    let emit_c = match flags.iter().rev().find_map(|flag| flag.strip_prefix("--emit=")) {
+        None => false,
+        Some(c::OUTPUT) => true,
+        Some(output) => {
+            eprintln!("ERROR: unknown output `{}`, --emit only knows {}", output, c::OUTPUT);
+            std::process::exit(1);
+        }
+    };
+    if emit_c && flags.iter().any(|flag| flag.starts_with("--target=")) {
+        eprintln!("ERROR: --emit=c writes portable C, --target doesn't apply to it");
+        std::process::exit(1);
+    }
+    let target_name = flags.iter().rev().find_map(|flag| flag.strip_prefix("--target=")).unwrap_or(target::TARGETS[0].name());
+    // wasm32 is translated from the tree by wasm.rs, the target its context keeps goes unused
+    let wasm = target_name == wasm::TARGET;
+    let target = match target::target(target_name) {
+        Some(target) => target,
+        None if wasm => target::TARGETS[0],
+        None => {
+            let names: Vec<&str> = target::TARGETS.iter().map(|target| target.name()).chain([wasm::TARGET]).collect();
+            eprintln!("ERROR: unknown target `{}`, the targets are {}", target_name, names.join(", "));
+            std::process::exit(1);
+        }
+    };
+    // `stem-rs test file` builds and runs the test blocks, `stem-rs build file` makes an object of each file,
+    // `stem-rs lib file` makes a library for C, shared with --shared
+    let command = if files.len() == 2 { files.remove(0) } else { "".to_string() };
+    if files.len() != 1 || !["", "test", "build", "lib"].contains(&command.as_str()) {
+        eprintln!("ERROR: Usage: ./stem-rs [test | build | lib] [--release] [--cc] [--shared] [--target=x86_64 | aarch64 | riscv64 | wasm32] [--emit=c] `file`");
+        std::process::exit(1);
+    }
+    if shared && command != "lib" {
+        eprintln!("ERROR: --shared only applies to `stem-rs lib`");
+        std::process::exit(1);
+    }
+    // a library is always used by C
+    libc |= command == "lib";
+    if wasm && (libc || command == "build") {
+        eprintln!("ERROR: wasm32 makes a single module without the C library, `stem-rs build`, `stem-rs lib` and --cc don't apply to it");
+        std::process::exit(1);
+    }
+    if emit_c && (libc || command == "build") {
+        eprintln!("ERROR: --emit=c makes a single C file, `stem-rs build`, `stem-rs lib` and --cc don't apply to it");
+        std::process::exit(1);
+    }
+    let tests = command == "test";
+    let file_path: String = files[0].clone();
+    let mut sources = SourceMap::new();
+    let modules = modules::load_program(&file_path, &mut sources);
+    println!("Program parsed");
+    let parsed = modules::merge(&modules);
+    // the C output is always compiled with the C library
+    if let Some(decl) = parsed.externs.first().filter(|_| !libc && !emit_c) {
+        eprintln!("ERROR:{}: `{}` is a C function, the program has to be linked with the C library: use --cc", sources.location(decl.span), decl.name);
+        std::process::exit(1);
+    }
+    let options = Options { overflow_checks: !release, tests, libc, library: command == "lib", target };
+    if command == "build" {
+        build_objects(&parsed, &modules, &sources, &options);
+        return;
+    }
+    if command == "lib" {
+        build_library(&parsed, modules.last().expect("the file given is loaded"), &sources, &options, shared);
+        return;
+    }
+    let names: Vec<&str> = parsed.tests.iter().map(|test| test.name.as_str()).collect();
+    if emit_c {
+        let code = c::generate_c(&parsed, &sources, &options);
+        println!("Code generated");
+        fs::write("output.c", code).expect("Can't write the output file");
+        if tests {
+            build_command("cc", &["-std=c99", "-o", "output", "output.c"]);
+            run_tests(&names, &["./output".to_string()]);
+        }
+        return;
+    }
+    if wasm {
+        let module = wasm::generate_module(&parsed, &sources, &options);
+        println!("Code generated");
+        fs::write("output.wat", &module).expect("Can't write the output file");
+        fs::write("output.wasm", wasm::assemble(&module)).expect("Can't write the output file");
+        if tests {
+            run_tests(&names, &["wasmtime".to_string(), "output.wasm".to_string()]);
+        }
+        return;
+    }
+    let asm_code = generate_code(&parsed, &sources, &options);
+    println!("Code generated");
+
+    fs::write(format!("output.{}", target.extension()), asm_code).expect("Can't write the output file");
+    if tests {
+        run_tests(&names, &test_runner(&options));
+    }
 }
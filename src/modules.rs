@@ -0,0 +1,349 @@
+/*
+ * The files of a program: the file given to the compiler and the ones it imports, each loaded once
+ * the items of an imported file are renamed `module::item`, so that all the files make a single program
+ */
+use crate::ast::{Expr, ExprKind, ImportDecl, Program, Stmt, StmtKind, Type};
+use crate::lexer::{Lexer, Token, TokenType};
+use crate::source::{SourceMap, Span};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/*
+ * a file of the program with its items renamed, prefix is empty for the file given to the compiler
+ * the name of each of its imports is replaced by the prefix of the imported module
+ */
+pub struct Module {
+    pub prefix: String,
+    pub path: PathBuf,
+    pub program: Program,
+}
+
+struct Loader<'a> {
+    sources: &'a mut SourceMap,
+    // the files loaded, a file comes after the files it imports
+    modules: Vec<Module>,
+    // the prefix of every file loaded or being loaded, by canonical path
+    prefixes: HashMap<PathBuf, String>,
+    // the prefixes given, and the last number appended to each name imported by several files
+    taken: HashSet<String>,
+    numbers: HashMap<String, u32>,
+    // the files being loaded and their path as written, each one imports the next one
+    stack: Vec<(PathBuf, String)>,
+}
+
+/*
+ * load the file at path and everything it imports, the files imported come first
+ */
+pub fn load_program(path: &str, sources: &mut SourceMap) -> Vec<Module> {
+    let mut loader = Loader { sources, modules: vec![], prefixes: HashMap::new(), taken: HashSet::new(), numbers: HashMap::new(), stack: vec![] };
+    loader.load(Path::new(path), "".to_string(), None);
+    return loader.modules;
+}
+
+/*
+ * the items of all the modules as a single program, the statements are the ones of the file given to the compiler
+ */
+pub fn merge(modules: &[Module]) -> Program {
+    let mut program = Program { structs: vec![], functions: vec![], externs: vec![], globals: vec![], tests: vec![], imports: vec![], statements: vec![] };
+    for module in modules {
+        program.structs.extend(module.program.structs.iter().cloned());
+        program.functions.extend(module.program.functions.iter().cloned());
+        program.externs.extend(module.program.externs.iter().cloned());
+        program.globals.extend(module.program.globals.iter().cloned());
+        program.tests.extend(module.program.tests.iter().cloned());
+        program.statements.extend(module.program.statements.iter().cloned());
+    }
+    return program;
+}
+
+impl Loader<'_> {
+    /*
+     * load the file at path as the module prefix and return its prefix,
+     * the one it was first loaded with if it was already, span is the import of the file
+     */
+    fn load(&mut self, path: &Path, prefix: String, span: Option<Span>) -> String {
+        let written = path.display().to_string();
+        let canonical = match fs::canonicalize(path) {
+            Ok(canonical) => canonical,
+            Err(error) => self.error(span, &format!("can't read `{}`: {}", written, error)),
+        };
+        if let Some(i) = self.stack.iter().position(|(other, _)| *other == canonical) {
+            let mut cycle: Vec<&str> = self.stack[i..].iter().map(|(_, written)| written.as_str()).collect();
+            cycle.push(&written);
+            self.error(span, &format!("import cycle: {}", cycle.join(" -> ")));
+        }
+        if let Some(prefix) = self.prefixes.get(&canonical) {
+            return prefix.clone();
+        }
+        // two files imported with the same name by different files get different prefixes
+        let mut unique = prefix.clone();
+        let n = self.numbers.entry(prefix.clone()).or_insert(1);
+        while self.taken.contains(&unique) {
+            *n += 1;
+            unique = format!("{prefix}{n}");
+        }
+        let prefix = unique;
+        self.taken.insert(prefix.clone());
+        self.prefixes.insert(canonical.clone(), prefix.clone());
+
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(error) => self.error(span, &format!("can't read `{}`: {}", written, error)),
+        };
+        let file_id = self.sources.add_file(written.clone(), text);
+        let tokens: Vec<Token> = Lexer::new(self.sources, file_id)
+            .filter(|token| token.type_ != TokenType::DocComment)
+            .collect();
+        let mut program = crate::parse(&tokens, self.sources);
+
+        self.stack.push((canonical, written));
+        let mut aliases: HashMap<String, String> = HashMap::new();
+        for import in &mut program.imports {
+            if aliases.contains_key(&import.name) {
+                self.error(Some(import.span), &format!("a module named `{}` is already imported", import.name));
+            }
+            let file = self.resolve(path, import);
+            let module = self.load(&file, import.name.clone(), Some(import.span));
+            aliases.insert(std::mem::replace(&mut import.name, module.clone()), module);
+        }
+        self.stack.pop();
+
+        if !prefix.is_empty() {
+            if let Some(stmt) = program.statements.first() {
+                self.error(Some(stmt.span), "an imported file can only declare items, not run statements");
+            }
+        }
+        let mut renamer = Renamer { prefix: &prefix, aliases: &aliases, items: HashSet::new(), locals: vec![], shadowing: HashMap::new(), sources: self.sources };
+        renamer.program(&mut program);
+        self.modules.push(Module { prefix: prefix.clone(), path: path.to_path_buf(), program });
+        return prefix;
+    }
+
+    /*
+     * the file of an import: relative to the directory of the importing file,
+     * else in one of the directories of STEM_PATH (separated by `:`)
+     */
+    fn resolve(&self, from: &Path, import: &ImportDecl) -> PathBuf {
+        let relative = from.parent().unwrap_or(Path::new("")).join(&import.path);
+        if relative.is_file() {
+            return relative;
+        }
+        if let Some(stem_path) = std::env::var_os("STEM_PATH") {
+            for dir in std::env::split_paths(&stem_path) {
+                let candidate = dir.join(&import.path);
+                if candidate.is_file() {
+                    return candidate;
+                }
+            }
+        }
+        self.error(Some(import.span), &format!("can't find `{}` next to this file or in STEM_PATH", import.path));
+    }
+
+    fn error(&self, span: Option<Span>, message: &str) -> ! {
+        match span {
+            Some(span) => eprintln!("ERROR:{}: {}", self.sources.location(span), message),
+            None => eprintln!("ERROR: {}", message),
+        }
+        std::process::exit(1);
+    }
+}
+
+/*
+ * rename the items of a file to `prefix::item`, and `module::item` to the prefix of the imported module
+ * a local variable shadows the items, other names (the builtins) are left as they are
+ */
+struct Renamer<'a> {
+    prefix: &'a str,
+    // the modules imported by the file and their prefix
+    aliases: &'a HashMap<String, String>,
+    // the items declared by the file
+    items: HashSet<String>,
+    // the variables in scope where the renamer is, the innermost last, and how many have each name
+    locals: Vec<String>,
+    shadowing: HashMap<String, u32>,
+    sources: &'a SourceMap,
+}
+
+impl Renamer<'_> {
+    fn declare(&mut self, name: &str) {
+        self.locals.push(name.to_string());
+        *self.shadowing.entry(name.to_string()).or_insert(0) += 1;
+    }
+
+    /*
+     * forget the variables declared after the first count ones
+     */
+    fn forget(&mut self, count: usize) {
+        for name in self.locals.drain(count..) {
+            if let Some(n) = self.shadowing.get_mut(&name) {
+                *n -= 1;
+                if *n == 0 {
+                    self.shadowing.remove(&name);
+                }
+            }
+        }
+    }
+
+    fn program(&mut self, program: &mut Program) {
+        self.items.extend(program.structs.iter().map(|decl| decl.name.clone()));
+        self.items.extend(program.functions.iter().map(|decl| decl.name.clone()));
+        self.items.extend(program.globals.iter().map(|decl| decl.name.clone()));
+        for decl in &mut program.structs {
+            decl.name = self.item(&decl.name);
+            for (_, type_, span) in &mut decl.fields {
+                self.type_(type_, *span);
+            }
+        }
+        for decl in &mut program.globals {
+            decl.name = self.item(&decl.name);
+            if let Some(type_) = &mut decl.type_ {
+                self.type_(type_, decl.span);
+            }
+            if let Some(value) = &mut decl.value {
+                self.expr(value);
+            }
+        }
+        for decl in &mut program.functions {
+            decl.name = self.item(&decl.name);
+            for (name, type_, span) in &mut decl.params {
+                self.type_(type_, *span);
+                self.declare(name);
+            }
+            if let Some(type_) = &mut decl.returns {
+                self.type_(type_, decl.span);
+            }
+            self.stmt(&mut decl.body);
+            self.forget(0);
+        }
+        // a C function keeps its name, it is the symbol of the C library
+        for decl in &mut program.externs {
+            for (_, type_, span) in &mut decl.params {
+                self.type_(type_, *span);
+            }
+            if let Some(type_) = &mut decl.returns {
+                self.type_(type_, decl.span);
+            }
+        }
+        for decl in &mut program.tests {
+            decl.name = self.item(&decl.name);
+            self.stmt(&mut decl.body);
+        }
+        for stmt in &mut program.statements {
+            self.stmt(stmt);
+        }
+    }
+
+    /*
+     * the name of an item declared by the file
+     */
+    fn item(&self, name: &str) -> String {
+        if self.prefix.is_empty() {
+            return name.to_string();
+        }
+        return format!("{}::{}", self.prefix, name);
+    }
+
+    /*
+     * the name of an item used by the file
+     */
+    fn path(&self, name: &str, span: Span) -> String {
+        if let Some((module, rest)) = name.split_once("::") {
+            match self.aliases.get(module) {
+                Some(prefix) => return format!("{prefix}::{rest}"),
+                None => {
+                    eprintln!("ERROR:{}: unknown module `{}`, it has to be imported", self.sources.location(span), module);
+                    std::process::exit(1);
+                }
+            }
+        }
+        if self.items.contains(name) {
+            return self.item(name);
+        }
+        return name.to_string();
+    }
+
+    fn type_(&self, type_: &mut Type, span: Span) {
+        match type_ {
+            Type::Struct(name) => *name = self.path(name, span),
+            Type::Pointer(pointee) => self.type_(pointee, span),
+            _ => {}
+        }
+    }
+
+    fn stmt(&mut self, stmt: &mut Stmt) {
+        match &mut stmt.kind {
+            StmtKind::Expr(expr) | StmtKind::Put(expr) => self.expr(expr),
+            StmtKind::Let { name, type_, value } => {
+                // the value is renamed before the variable exists, like it is computed
+                self.expr(value);
+                if let Some(type_) = type_ {
+                    self.type_(type_, stmt.span);
+                }
+                self.declare(name);
+            }
+            StmtKind::Block(stmts) => {
+                let locals = self.locals.len();
+                for stmt in stmts {
+                    self.stmt(stmt);
+                }
+                self.forget(locals);
+            }
+            StmtKind::If { condition, then, otherwise } => {
+                self.expr(condition);
+                self.stmt(then);
+                if let Some(otherwise) = otherwise {
+                    self.stmt(otherwise);
+                }
+            }
+            StmtKind::While { condition, body } => {
+                self.expr(condition);
+                self.stmt(body);
+            }
+            StmtKind::Return(value) => {
+                if let Some(value) = value {
+                    self.expr(value);
+                }
+            }
+            StmtKind::Assert { condition, .. } => self.expr(condition),
+        }
+    }
+
+    fn expr(&mut self, expr: &mut Expr) {
+        let span = expr.span;
+        match &mut expr.kind {
+            ExprKind::Integer { .. } | ExprKind::String(_) => {}
+            ExprKind::Variable(name) => {
+                if !self.shadowing.contains_key(name) {
+                    *name = self.path(name, span);
+                }
+            }
+            ExprKind::StructLiteral { name, fields } => {
+                *name = self.path(name, span);
+                for (_, value) in fields {
+                    self.expr(value);
+                }
+            }
+            // a function can't be in a variable, a call always names an item
+            ExprKind::Call { name, args } => {
+                *name = self.path(name, span);
+                for arg in args {
+                    self.expr(arg);
+                }
+            }
+            ExprKind::Field { base: operand, .. }
+            | ExprKind::Unary { operand, .. }
+            | ExprKind::Postfix { target: operand, .. }
+            | ExprKind::AddressOf(operand)
+            | ExprKind::Deref(operand) => self.expr(operand),
+            ExprKind::Binary { lhs, rhs, .. } => {
+                self.expr(lhs);
+                self.expr(rhs);
+            }
+            ExprKind::Assign { target, value } | ExprKind::CompoundAssign { target, value, .. } => {
+                self.expr(target);
+                self.expr(value);
+            }
+        }
+    }
+}
@@ -0,0 +1,215 @@
+/*
+ * The runtime of the generated programs: the routines the code calls for what it can't do inline
+ * each routine is an assembly file of src/runtime/{target}, only the ones a program uses end up in its output
+ * wasm32 has its routines written as functions of a module, in src/runtime/wasm32, and the C output as C functions
+ * in src/runtime/c
+ */
+use crate::target::{Section, Target};
+
+/*
+ * the label of the routine name, apart from the symbols of the C library the program may be linked with
+ */
+pub fn symbol(name: &str) -> String {
+    return format!("runtime.{name}");
+}
+
+struct Routine {
+    name: &'static str,
+    // the routines it calls or whose data it uses
+    needs: &'static [&'static str],
+}
+
+/*
+ * a routine comes after the ones it needs, so their definitions are known when it is assembled
+ */
+const ROUTINES: [Routine; 10] = [
+    Routine { name: "exit", needs: &[] },
+    Routine { name: "print", needs: &[] },
+    Routine { name: "read", needs: &[] },
+    Routine { name: "get_byte", needs: &["read"] },
+    Routine { name: "get", needs: &["get_byte"] },
+    Routine { name: "get_line", needs: &["get_byte"] },
+    Routine { name: "put", needs: &["print"] },
+    Routine { name: "panic", needs: &["exit"] },
+    Routine { name: "alloc", needs: &["panic"] },
+    Routine { name: "free", needs: &["alloc", "panic"] },
+];
+
+/*
+ * the routines written for a target, in the order of ROUTINES
+ * the prelude holds the definitions every routine can use and is always emitted at the top of the output,
+ * libc the routines that differ when the program is linked with the C library: exit goes through the exit of C
+ * and print flushes the streams of C first, so the output comes in order
+ */
+pub struct Runtime {
+    pub prelude: &'static str,
+    code: [&'static str; ROUTINES.len()],
+    libc: &'static [(&'static str, &'static str)],
+}
+
+pub const X86_64: Runtime = Runtime {
+    prelude: include_str!("runtime/x86_64/prelude.asm"),
+    code: [
+        include_str!("runtime/x86_64/exit.asm"),
+        include_str!("runtime/x86_64/print.asm"),
+        include_str!("runtime/x86_64/read.asm"),
+        include_str!("runtime/x86_64/stdin.asm"),
+        include_str!("runtime/x86_64/get.asm"),
+        include_str!("runtime/x86_64/get_line.asm"),
+        include_str!("runtime/x86_64/put.asm"),
+        include_str!("runtime/x86_64/panic.asm"),
+        include_str!("runtime/x86_64/alloc.asm"),
+        include_str!("runtime/x86_64/free.asm"),
+    ],
+    libc: &[
+        ("exit", include_str!("runtime/x86_64/libc/exit.asm")),
+        ("print", include_str!("runtime/x86_64/libc/print.asm")),
+    ],
+};
+
+pub const AARCH64: Runtime = Runtime {
+    prelude: include_str!("runtime/aarch64/prelude.s"),
+    code: [
+        include_str!("runtime/aarch64/exit.s"),
+        include_str!("runtime/aarch64/print.s"),
+        include_str!("runtime/aarch64/read.s"),
+        include_str!("runtime/aarch64/stdin.s"),
+        include_str!("runtime/aarch64/get.s"),
+        include_str!("runtime/aarch64/get_line.s"),
+        include_str!("runtime/aarch64/put.s"),
+        include_str!("runtime/aarch64/panic.s"),
+        include_str!("runtime/aarch64/alloc.s"),
+        include_str!("runtime/aarch64/free.s"),
+    ],
+    libc: &[
+        ("exit", include_str!("runtime/aarch64/libc/exit.s")),
+        ("print", include_str!("runtime/aarch64/libc/print.s")),
+    ],
+};
+
+pub const RISCV64: Runtime = Runtime {
+    prelude: include_str!("runtime/riscv64/prelude.s"),
+    code: [
+        include_str!("runtime/riscv64/exit.s"),
+        include_str!("runtime/riscv64/print.s"),
+        include_str!("runtime/riscv64/read.s"),
+        include_str!("runtime/riscv64/stdin.s"),
+        include_str!("runtime/riscv64/get.s"),
+        include_str!("runtime/riscv64/get_line.s"),
+        include_str!("runtime/riscv64/put.s"),
+        include_str!("runtime/riscv64/panic.s"),
+        include_str!("runtime/riscv64/alloc.s"),
+        include_str!("runtime/riscv64/free.s"),
+    ],
+    libc: &[
+        ("exit", include_str!("runtime/riscv64/libc/exit.s")),
+        ("print", include_str!("runtime/riscv64/libc/print.s")),
+    ],
+};
+
+/*
+ * the functions of the runtime of wasm32, it has no C library to be linked with
+ * the prelude holds the WASI imports and the functions that _start calls for argv and the tests
+ */
+pub const WASM32: Runtime = Runtime {
+    prelude: include_str!("runtime/wasm32/prelude.wat"),
+    code: [
+        include_str!("runtime/wasm32/exit.wat"),
+        include_str!("runtime/wasm32/print.wat"),
+        include_str!("runtime/wasm32/read.wat"),
+        include_str!("runtime/wasm32/stdin.wat"),
+        include_str!("runtime/wasm32/get.wat"),
+        include_str!("runtime/wasm32/get_line.wat"),
+        include_str!("runtime/wasm32/put.wat"),
+        include_str!("runtime/wasm32/panic.wat"),
+        include_str!("runtime/wasm32/alloc.wat"),
+        include_str!("runtime/wasm32/free.wat"),
+    ],
+    libc: &[],
+};
+
+/*
+ * the functions of the runtime of the programs translated to C, the C library does what the system calls do elsewhere
+ * the prelude holds the includes and the helpers of the code for the loads, stores and signed operations
+ */
+pub const C: Runtime = Runtime {
+    prelude: include_str!("runtime/c/prelude.c"),
+    code: [
+        include_str!("runtime/c/exit.c"),
+        include_str!("runtime/c/print.c"),
+        include_str!("runtime/c/read.c"),
+        include_str!("runtime/c/stdin.c"),
+        include_str!("runtime/c/get.c"),
+        include_str!("runtime/c/get_line.c"),
+        include_str!("runtime/c/put.c"),
+        include_str!("runtime/c/panic.c"),
+        include_str!("runtime/c/alloc.c"),
+        include_str!("runtime/c/free.c"),
+    ],
+    libc: &[],
+};
+
+/*
+ * which routines are named in used or needed by them, indexed like ROUTINES
+ */
+fn linked(used: &[String]) -> [bool; ROUTINES.len()] {
+    let mut linked = [false; ROUTINES.len()];
+    let mut pending: Vec<&str> = used.iter().map(String::as_str).collect();
+    while let Some(name) = pending.pop() {
+        let Some(i) = ROUTINES.iter().position(|routine| routine.name == name) else {
+            panic!("`{name}` is not a routine of the runtime");
+        };
+        if !linked[i] {
+            linked[i] = true;
+            pending.extend(ROUTINES[i].needs);
+        }
+    }
+    return linked;
+}
+
+/*
+ * the routines named in used and everything they need, in the order of ROUTINES
+ */
+pub fn runtime_codegen(target: &dyn Target, used: &[String], libc: bool) -> String {
+    let linked = linked(used);
+    let runtime = target.runtime();
+    let mut code = "".to_string();
+    for ((routine, routine_code), linked) in ROUTINES.iter().zip(runtime.code).zip(linked) {
+        if linked {
+            code += "\n";
+            code += &target.section(Section::Text);
+            match runtime.libc.iter().find(|(name, _)| libc && *name == routine.name) {
+                Some((_, code2)) => code += code2,
+                None => code += routine_code,
+            }
+        }
+    }
+    return code;
+}
+
+/*
+ * every routine of the runtime as an object of its own, for the programs compiled one file at a time
+ */
+pub fn runtime_library(target: &dyn Target, libc: bool) -> String {
+    let mut code = target.runtime().prelude.to_string();
+    for routine in &ROUTINES {
+        code += &target.global(&symbol(routine.name));
+    }
+    let names: Vec<String> = ROUTINES.iter().map(|routine| routine.name.to_string()).collect();
+    code += &runtime_codegen(target, &names, libc);
+    return code;
+}
+
+/*
+ * the functions of the routines named in used and of everything they need, for the module of wasm32 or the C file
+ */
+pub fn functions_codegen(runtime: &Runtime, used: &[String]) -> String {
+    let mut code = "".to_string();
+    for (routine_code, linked) in runtime.code.iter().zip(linked(used)) {
+        if linked {
+            code += "\n";
+            code += routine_code;
+        }
+    }
+    return code;
+}
@@ -0,0 +1,94 @@
+// alloc(size) -> pointer and free(pointer), x1 and x2 hold the location of the call for the panics
+// a block starts with a 16 bytes header: its size and ALLOC_USED or ALLOC_FREE
+// small blocks (up to ALLOC_SMALL bytes) are rounded to a size class 16 << k, taken from the heap
+// grown with brk and kept in a free list per class once freed, larger blocks are mmap'ed and munmap'ed
+runtime.alloc:
+        mov     x3, x0
+        cmp     x3, #ALLOC_SMALL
+        b.hi    .Lalloc_large
+        mov     x4, #0
+        mov     x5, #16
+.Lalloc_class:
+        cmp     x5, x3
+        b.hs    .Lalloc_small
+        lsl     x5, x5, #1
+        add     x4, x4, #1
+        b       .Lalloc_class
+.Lalloc_small:
+        adrp    x6, alloc_free_lists
+        add     x6, x6, :lo12:alloc_free_lists
+        ldr     x0, [x6, x4, lsl #3]
+        cbz     x0, .Lalloc_carve
+        ldr     x3, [x0]
+        str     x3, [x6, x4, lsl #3]
+        sub     x0, x0, #16
+        b       .Lalloc_mark
+// x6 is alloc_heap_top and alloc_heap_end the quad after it, x7 the block with its header
+.Lalloc_carve:
+        add     x7, x5, #16
+        adrp    x6, alloc_heap_top
+        add     x6, x6, :lo12:alloc_heap_top
+        ldr     x0, [x6]
+        cbnz    x0, .Lalloc_room
+        mov     x0, #0
+        mov     x8, #SYS_BRK
+        svc     #0
+        str     x0, [x6]
+        str     x0, [x6, #8]
+.Lalloc_room:
+        add     x3, x0, x7
+        ldr     x4, [x6, #8]
+        cmp     x3, x4
+        b.ls    .Lalloc_carved
+        mov     x0, #ALLOC_GROW
+        add     x0, x4, x0
+        mov     x8, #SYS_BRK
+        svc     #0
+        cmp     x0, x3
+        b.lo    .Lalloc_out_of_memory
+        str     x0, [x6, #8]
+        ldr     x0, [x6]
+.Lalloc_carved:
+        str     x3, [x6]
+        str     x5, [x0]
+        b       .Lalloc_mark
+// the location is kept in x16 and x17 during the mmap
+.Lalloc_large:
+        mov     x16, x1
+        mov     x17, x2
+        add     x1, x3, #16
+        add     x1, x1, #4095
+        and     x1, x1, #-4096
+        mov     x0, #0
+        mov     x2, #3
+        mov     x3, #0x22
+        mov     x4, #-1
+        mov     x5, #0
+        mov     x8, #SYS_MMAP
+        svc     #0
+        mov     x3, x1
+        mov     x1, x16
+        mov     x2, x17
+        mov     x4, #-4096
+        cmp     x0, x4
+        b.hi    .Lalloc_out_of_memory
+        str     x3, [x0]
+.Lalloc_mark:
+        mov     x3, #(ALLOC_USED & 0xffff)
+        movk    x3, #(ALLOC_USED >> 16), lsl #16
+        str     x3, [x0, #8]
+        add     x0, x0, #16
+        ret
+.Lalloc_out_of_memory:
+        adrp    x6, alloc_out_of_memory
+        add     x6, x6, :lo12:alloc_out_of_memory
+        mov     x7, #alloc_out_of_memory_len
+        b       runtime.panic_at
+        .section .rodata
+alloc_out_of_memory: .ascii "out of memory\n"
+        .equ    alloc_out_of_memory_len, . - alloc_out_of_memory
+        .bss
+        .balign 8
+alloc_free_lists: .zero 64
+alloc_heap_top: .zero 8
+alloc_heap_end: .zero 8
@@ -0,0 +1,4 @@
+// exit(code), never returns
+runtime.exit:
+        mov     x8, #SYS_EXIT
+        svc     #0
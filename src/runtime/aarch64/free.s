@@ -0,0 +1,54 @@
+// free(pointer), its blocks go back to the free lists of alloc
+runtime.free:
+        cbz     x0, .Lfree_done
+        sub     x3, x0, #16
+        ldr     x4, [x3, #8]
+        mov     x5, #(ALLOC_FREE & 0xffff)
+        movk    x5, #(ALLOC_FREE >> 16), lsl #16
+        cmp     x4, x5
+        b.eq    .Lfree_double
+        mov     x6, #(ALLOC_USED & 0xffff)
+        movk    x6, #(ALLOC_USED >> 16), lsl #16
+        cmp     x4, x6
+        b.ne    .Lfree_invalid
+        str     x5, [x3, #8]
+        ldr     x4, [x3]
+        cmp     x4, #ALLOC_SMALL
+        b.hi    .Lfree_large
+        mov     x5, #0
+        mov     x6, #16
+.Lfree_class:
+        cmp     x6, x4
+        b.hs    .Lfree_push
+        lsl     x6, x6, #1
+        add     x5, x5, #1
+        b       .Lfree_class
+.Lfree_push:
+        adrp    x6, alloc_free_lists
+        add     x6, x6, :lo12:alloc_free_lists
+        ldr     x4, [x6, x5, lsl #3]
+        str     x4, [x0]
+        str     x0, [x6, x5, lsl #3]
+.Lfree_done:
+        ret
+.Lfree_large:
+        mov     x0, x3
+        mov     x1, x4
+        mov     x8, #SYS_MUNMAP
+        svc     #0
+        ret
+.Lfree_double:
+        adrp    x6, alloc_double_free
+        add     x6, x6, :lo12:alloc_double_free
+        mov     x7, #alloc_double_free_len
+        b       runtime.panic_at
+.Lfree_invalid:
+        adrp    x6, alloc_invalid_free
+        add     x6, x6, :lo12:alloc_invalid_free
+        mov     x7, #alloc_invalid_free_len
+        b       runtime.panic_at
+        .section .rodata
+alloc_double_free: .ascii "double free of a heap block\n"
+        .equ    alloc_double_free_len, . - alloc_double_free
+alloc_invalid_free: .ascii "free of a pointer that alloc didn't return\n"
+        .equ    alloc_invalid_free_len, . - alloc_invalid_free
@@ -0,0 +1,86 @@
+// get(pointer) -> status: read a line of stdin holding an i64 in decimal, with an optional sign and blanks around it
+// the status is 1 when the value was stored at pointer, 0 at the end of the input, -1 when the line is not an i64
+// blank lines are skipped, x12 is where the line is: 0 before the number, 1 after its sign,
+// 2 in its digits, 3 after it and 4 once it is known not to be a number
+// get_byte leaves x9 to x15 alone, the caller of a builtin saves them
+runtime.get:
+        stp     x29, x30, [sp, #-16]!
+        mov     x9, x0
+.Lget_line:
+        mov     x10, #0
+        mov     x11, #0
+        mov     x12, #0
+.Lget_next:
+        bl      runtime.get_byte
+        cmn     x0, #1
+        b.eq    .Lget_eof
+        cmp     x0, #10
+        b.eq    .Lget_end_of_line
+        cmp     x0, #32
+        b.eq    .Lget_blank
+        cmp     x0, #9
+        b.eq    .Lget_blank
+        cmp     x0, #13
+        b.eq    .Lget_blank
+        cmp     x0, #45
+        b.eq    .Lget_minus
+        cmp     x0, #43
+        b.eq    .Lget_plus
+        sub     x0, x0, #48
+        cmp     x0, #9
+        b.hi    .Lget_bad
+        cmp     x12, #2
+        b.hi    .Lget_bad
+        mov     x12, #2
+        mov     x13, #10
+        umulh   x14, x10, x13
+        cbnz    x14, .Lget_bad
+        mul     x10, x10, x13
+        adds    x10, x10, x0
+        b.cs    .Lget_bad
+        b       .Lget_next
+.Lget_minus:
+        mov     x11, #1
+.Lget_plus:
+        cbnz    x12, .Lget_bad
+        mov     x12, #1
+        b       .Lget_next
+.Lget_blank:
+        cmp     x12, #1
+        b.eq    .Lget_bad
+        cmp     x12, #2
+        b.ne    .Lget_next
+        mov     x12, #3
+        b       .Lget_next
+.Lget_bad:
+        mov     x12, #4
+        b       .Lget_next
+.Lget_end_of_line:
+        cbz     x12, .Lget_line
+        b       .Lget_done
+.Lget_eof:
+        mov     x0, #0
+        cbz     x12, .Lget_return
+.Lget_done:
+        cmp     x12, #2
+        b.lo    .Lget_invalid
+        cmp     x12, #3
+        b.hi    .Lget_invalid
+        // the digits are read as an u64, 2^63 only fits with a minus
+        mov     x13, #0x8000000000000000
+        cmp     x10, x13
+        b.hi    .Lget_invalid
+        b.lo    .Lget_sign
+        cbz     x11, .Lget_invalid
+.Lget_sign:
+        cbz     x11, .Lget_store
+        neg     x10, x10
+.Lget_store:
+        str     x10, [x9]
+        mov     x0, #1
+        b       .Lget_return
+.Lget_invalid:
+        mov     x0, #-1
+.Lget_return:
+        ldp     x29, x30, [sp], #16
+        ret
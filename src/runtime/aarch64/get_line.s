@@ -0,0 +1,27 @@
+// get_line(buffer, size) -> len: read a line of stdin in buffer, without its newline
+// len is the length of the whole line, only its first size bytes are stored when it is longer,
+// -1 at the end of the input
+runtime.get_line:
+        stp     x29, x30, [sp, #-16]!
+        mov     x9, x0
+        mov     x10, x1
+        mov     x11, #0
+.Lget_line_next:
+        bl      runtime.get_byte
+        cmn     x0, #1
+        b.eq    .Lget_line_eof
+        cmp     x0, #10
+        b.eq    .Lget_line_done
+        cmp     x11, x10
+        b.hs    .Lget_line_skip
+        strb    w0, [x9, x11]
+.Lget_line_skip:
+        add     x11, x11, #1
+        b       .Lget_line_next
+.Lget_line_eof:
+        cbnz    x11, .Lget_line_done
+        mov     x11, #-1
+.Lget_line_done:
+        mov     x0, x11
+        ldp     x29, x30, [sp], #16
+        ret
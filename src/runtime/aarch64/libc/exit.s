@@ -0,0 +1,3 @@
+// exit(code), never returns: through the exit of C, which flushes its streams
+runtime.exit:
+        bl      exit
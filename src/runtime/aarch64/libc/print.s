@@ -0,0 +1,24 @@
+// print(pointer, len): fflush(NULL) for what C buffered, then write len bytes on stdout,
+// a short write is continued, an error gives up
+runtime.print:
+        stp     x29, x30, [sp, #-32]!
+        mov     x29, sp
+        stp     x0, x1, [sp, #16]
+        mov     x0, #0
+        bl      fflush
+        ldp     x0, x1, [sp, #16]
+        ldp     x29, x30, [sp], #32
+        mov     x2, x1
+        mov     x1, x0
+.Lprint_loop:
+        cbz     x2, .Lprint_done
+        mov     x0, #1
+        mov     x8, #SYS_WRITE
+        svc     #0
+        cmp     x0, #0
+        b.le    .Lprint_done
+        add     x1, x1, x0
+        sub     x2, x2, x0
+        b       .Lprint_loop
+.Lprint_done:
+        ret
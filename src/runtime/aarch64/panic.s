@@ -0,0 +1,17 @@
+// panic(message, len): write the message on stderr and exit with 101
+runtime.panic:
+        mov     x2, x1
+        mov     x1, x0
+        mov     x0, #2
+        mov     x8, #SYS_WRITE
+        svc     #0
+        mov     x0, #101
+        b       runtime.exit
+// panic_at: write the location in x1, x2 (the one a builtin gets) then panic with the message in x6, x7
+runtime.panic_at:
+        mov     x0, #2
+        mov     x8, #SYS_WRITE
+        svc     #0
+        mov     x0, x6
+        mov     x1, x7
+        b       runtime.panic
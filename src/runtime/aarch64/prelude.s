@@ -0,0 +1,10 @@
+        .equ    SYS_READ, 63
+        .equ    SYS_WRITE, 64
+        .equ    SYS_MMAP, 222
+        .equ    SYS_MUNMAP, 215
+        .equ    SYS_BRK, 214
+        .equ    SYS_EXIT, 93
+        .equ    ALLOC_SMALL, 2048
+        .equ    ALLOC_GROW, 65536
+        .equ    ALLOC_USED, 0x75736564
+        .equ    ALLOC_FREE, 0x66726565
@@ -0,0 +1,16 @@
+// print(pointer, len): write len bytes on stdout, a short write is continued, an error gives up
+runtime.print:
+        mov     x2, x1
+        mov     x1, x0
+.Lprint_loop:
+        cbz     x2, .Lprint_done
+        mov     x0, #1
+        mov     x8, #SYS_WRITE
+        svc     #0
+        cmp     x0, #0
+        b.le    .Lprint_done
+        add     x1, x1, x0
+        sub     x2, x2, x0
+        b       .Lprint_loop
+.Lprint_done:
+        ret
@@ -0,0 +1,35 @@
+// put(value, signed): print the value in decimal and a newline, as an i64 when signed is not 0, as an u64 otherwise
+// the digits are written backward from the end of a buffer in the frame, the 32 bytes above x29 and x30
+runtime.put:
+        stp     x29, x30, [sp, #-48]!
+        mov     x29, sp
+        mov     x3, #0
+        cbz     x1, .Lput_digits
+        cmp     x0, #0
+        b.ge    .Lput_digits
+        mov     x3, #1
+        neg     x0, x0
+.Lput_digits:
+        add     x1, x29, #47
+        mov     w2, #10
+        strb    w2, [x1]
+        mov     x4, #10
+.Lput_digit:
+        udiv    x5, x0, x4
+        msub    x6, x5, x4, x0
+        add     w6, w6, #48
+        sub     x1, x1, #1
+        strb    w6, [x1]
+        mov     x0, x5
+        cbnz    x0, .Lput_digit
+        cbz     x3, .Lput_write
+        sub     x1, x1, #1
+        mov     w2, #45
+        strb    w2, [x1]
+.Lput_write:
+        mov     x0, x1
+        add     x1, x29, #48
+        sub     x1, x1, x0
+        bl      runtime.print
+        ldp     x29, x30, [sp], #48
+        ret
@@ -0,0 +1,8 @@
+// read(pointer, len) -> count: read up to len bytes of stdin, 0 at the end of the input, negative on an error
+runtime.read:
+        mov     x2, x1
+        mov     x1, x0
+        mov     x0, #0
+        mov     x8, #SYS_READ
+        svc     #0
+        ret
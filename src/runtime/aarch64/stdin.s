@@ -0,0 +1,41 @@
+        .equ    GET_BUFFER_SIZE, 4096
+// get_byte() -> byte: the next byte of stdin, -1 at the end of the input
+// stdin is read GET_BUFFER_SIZE bytes at a time in get_buffer, get_start is the next byte and get_end the bytes read
+runtime.get_byte:
+        adrp    x3, get_start
+        add     x3, x3, :lo12:get_start
+        ldr     x0, [x3]
+        ldr     x4, [x3, #8]
+        cmp     x0, x4
+        b.lo    .Lget_byte_next
+        stp     x29, x30, [sp, #-16]!
+        adrp    x0, get_buffer
+        add     x0, x0, :lo12:get_buffer
+        mov     x1, #GET_BUFFER_SIZE
+        bl      runtime.read
+        ldp     x29, x30, [sp], #16
+        adrp    x3, get_start
+        add     x3, x3, :lo12:get_start
+        cmp     x0, #0
+        b.le    .Lget_byte_eof
+        str     x0, [x3, #8]
+        mov     x0, #0
+.Lget_byte_next:
+        adrp    x1, get_buffer
+        add     x1, x1, :lo12:get_buffer
+        ldrb    w2, [x1, x0]
+        add     x0, x0, #1
+        str     x0, [x3]
+        mov     x0, x2
+        ret
+.Lget_byte_eof:
+        str     xzr, [x3]
+        str     xzr, [x3, #8]
+        mov     x0, #-1
+        ret
+        .bss
+        .balign 8
+// get_end is the quad after get_start
+get_start: .zero 8
+get_end: .zero 8
+get_buffer: .zero GET_BUFFER_SIZE
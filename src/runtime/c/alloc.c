@@ -0,0 +1,63 @@
+
+/* alloc(size) -> pointer and free(pointer), at is the location of the call for the panics
+ * a block starts with a 16 bytes header: its size and ALLOC_USED or ALLOC_FREE
+ * small blocks (up to ALLOC_SMALL bytes) are rounded to a size class 16 << k, carved one after the other from chunks
+ * of ALLOC_GROW bytes and kept in the free list of their class once freed, larger blocks are rounded to 16 bytes and
+ * kept in the last list where the first one big enough is taken again
+ * the chunks and the large blocks come from malloc and are never given back to it */
+#define ALLOC_SMALL 2048
+#define ALLOC_GROW 65536
+#define ALLOC_USED UINT64_C(0x75736564)
+#define ALLOC_FREE UINT64_C(0x66726565)
+
+static unsigned char *alloc_free_lists[9];
+static unsigned char *alloc_heap_top, *alloc_heap_end;
+
+uint64_t runtime_alloc(uint64_t size, const char *at) {
+    unsigned char *block = NULL;
+    unsigned char **list = alloc_free_lists;
+    uint64_t header[2];
+    uint64_t rounded = 16;
+    if (size > ALLOC_SMALL) {
+        if (size > SIZE_MAX - 32) {
+            runtime_panic_at(at, "out of memory\n");
+        }
+        rounded = (size + 15) & ~UINT64_C(15);
+        for (list = &alloc_free_lists[8]; *list != NULL; list = (unsigned char **)*list) {
+            memcpy(header, *list - 16, 16);
+            if (header[0] >= rounded) {
+                break;
+            }
+        }
+    } else {
+        while (rounded < size) {
+            rounded <<= 1;
+            list++;
+        }
+    }
+    if (*list != NULL) {
+        block = *list;
+        memcpy(list, block, sizeof block);
+    } else {
+        if (size > ALLOC_SMALL) {
+            block = malloc((size_t)rounded + 16);
+        } else {
+            if (alloc_heap_top == NULL || (uint64_t)(alloc_heap_end - alloc_heap_top) < rounded + 16) {
+                alloc_heap_top = malloc(ALLOC_GROW);
+                alloc_heap_end = alloc_heap_top + ALLOC_GROW;
+            }
+            block = alloc_heap_top;
+            if (block != NULL) {
+                alloc_heap_top += rounded + 16;
+            }
+        }
+        if (block == NULL) {
+            runtime_panic_at(at, "out of memory\n");
+        }
+        block += 16;
+        memcpy(block - 16, &rounded, 8);
+    }
+    header[1] = ALLOC_USED;
+    memcpy(block - 8, &header[1], 8);
+    return stem_address(block);
+}
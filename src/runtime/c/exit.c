@@ -0,0 +1,6 @@
+
+/* exit(code), never returns, the status of the process is the low byte of code like on linux */
+void runtime_exit(uint64_t code, const char *at) {
+    (void)at;
+    exit((int)(code & 255));
+}
@@ -0,0 +1,30 @@
+
+/* free(pointer), its blocks go back to the lists of alloc */
+void runtime_free(uint64_t pointer, const char *at) {
+    unsigned char *block = stem_pointer(pointer);
+    unsigned char **list = alloc_free_lists;
+    uint64_t header[2];
+    if (block == NULL) {
+        return;
+    }
+    memcpy(header, block - 16, 16);
+    if (header[1] == ALLOC_FREE) {
+        runtime_panic_at(at, "double free of a heap block\n");
+    }
+    if (header[1] != ALLOC_USED) {
+        runtime_panic_at(at, "free of a pointer that alloc didn't return\n");
+    }
+    header[1] = ALLOC_FREE;
+    memcpy(block - 8, &header[1], 8);
+    if (header[0] > ALLOC_SMALL) {
+        list = &alloc_free_lists[8];
+    } else {
+        while (header[0] > 16) {
+            header[0] >>= 1;
+            list++;
+        }
+    }
+    /* the block is pushed on its list, the link is in its first bytes */
+    memcpy(block, list, sizeof block);
+    *list = block;
+}
@@ -0,0 +1,56 @@
+
+/* get(pointer) -> status: read a line of stdin holding an i64 in decimal, with an optional sign and blanks around it
+ * the status is 1 when the value was stored at pointer, 0 at the end of the input, -1 when the line is not an i64
+ * blank lines are skipped, state is where the line is: 0 before the number, 1 after its sign,
+ * 2 in its digits, 3 after it and 4 once it is known not to be a number */
+uint64_t runtime_get(uint64_t pointer, const char *at) {
+    uint64_t value;
+    int negative, state;
+    int64_t byte;
+    (void)at;
+    do {
+        value = 0;
+        negative = 0;
+        state = 0;
+        while ((byte = runtime_get_byte()) != '\n') {
+            if (byte == -1) {
+                if (state == 0) {
+                    return 0;
+                }
+                break;
+            }
+            if (byte == ' ' || byte == '\t' || byte == '\r') {
+                if (state == 1) {
+                    state = 4;
+                } else if (state == 2) {
+                    state = 3;
+                }
+            } else if (byte == '-' || byte == '+') {
+                if (state != 0) {
+                    state = 4;
+                } else {
+                    negative = byte == '-';
+                    state = 1;
+                }
+            } else if (byte < '0' || byte > '9' || state > 2 || value > UINT64_MAX / 10) {
+                state = 4;
+            } else {
+                /* the digits are read as an u64, value * 10 + digit has to fit */
+                state = 2;
+                value = value * 10 + (uint64_t)(byte - '0');
+                if (value < (uint64_t)(byte - '0')) {
+                    state = 4;
+                }
+            }
+        }
+    } while (state == 0);
+    if (state != 2 && state != 3) {
+        return UINT64_MAX;
+    }
+    /* 2^63 only fits with a minus */
+    if (value > UINT64_C(0x8000000000000000) || (value == UINT64_C(0x8000000000000000) && !negative)) {
+        return UINT64_MAX;
+    }
+    stem_store(pointer, negative ? 0 - value : value, 8);
+    return 1;
+}
@@ -0,0 +1,19 @@
+
+/* get_line(buffer, size) -> len: read a line of stdin in buffer, without its newline
+ * len is the length of the whole line, only its first size bytes are stored when it is longer,
+ * -1 at the end of the input */
+uint64_t runtime_get_line(uint64_t buffer, uint64_t size, const char *at) {
+    uint64_t len = 0;
+    int64_t byte;
+    (void)at;
+    while ((byte = runtime_get_byte()) != '\n') {
+        if (byte == -1) {
+            return len == 0 ? UINT64_MAX : len;
+        }
+        if (len < size) {
+            stem_store(buffer + len, (uint64_t)byte, 1);
+        }
+        len++;
+    }
+    return len;
+}
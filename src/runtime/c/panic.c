@@ -0,0 +1,14 @@
+
+/* panic(message): write the message on stderr, after what the program printed, and exit with 101 */
+void runtime_panic(const char *message) {
+    fflush(stdout);
+    fputs(message, stderr);
+    runtime_exit(101, "");
+}
+
+/* panic_at(at, message): write the location a builtin gets then panic with the message */
+void runtime_panic_at(const char *at, const char *message) {
+    fflush(stdout);
+    fputs(at, stderr);
+    runtime_panic(message);
+}
@@ -0,0 +1,96 @@
+/* the runtime of the programs translated to C99, a value is an uint64_t whatever its type and a pointer is
+ * its address held in one: the C compiler has to target a machine whose pointers have 64 bits */
+#include <inttypes.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+typedef char stem_pointers_have_64_bits[sizeof(void *) == 8 ? 1 : -1];
+
+void *stem_pointer(uint64_t address) {
+    return (void *)(uintptr_t)address;
+}
+
+uint64_t stem_address(const void *pointer) {
+    return (uint64_t)(uintptr_t)pointer;
+}
+
+/* the i64 held in value, without relying on how C converts an uint64_t that doesn't fit */
+int64_t stem_signed(uint64_t value) {
+    return value >> 63 ? -(int64_t)~value - 1 : (int64_t)value;
+}
+
+/* extend the low size bytes of value to 64 bits, with their sign when is_signed is not 0 */
+uint64_t stem_extend(uint64_t value, int size, int is_signed) {
+    uint64_t sign;
+    if (size == 8) {
+        return value;
+    }
+    sign = UINT64_C(1) << (size * 8 - 1);
+    value &= (sign << 1) - 1;
+    return is_signed ? (value ^ sign) - sign : value;
+}
+
+/* the value of size bytes at address, extended like stem_extend */
+uint64_t stem_load(uint64_t address, int size, int is_signed) {
+    uint8_t v8;
+    uint16_t v16;
+    uint32_t v32;
+    uint64_t v64;
+    switch (size) {
+    case 1:
+        memcpy(&v8, stem_pointer(address), 1);
+        return stem_extend(v8, 1, is_signed);
+    case 2:
+        memcpy(&v16, stem_pointer(address), 2);
+        return stem_extend(v16, 2, is_signed);
+    case 4:
+        memcpy(&v32, stem_pointer(address), 4);
+        return stem_extend(v32, 4, is_signed);
+    default:
+        memcpy(&v64, stem_pointer(address), 8);
+        return v64;
+    }
+}
+
+/* store the low size bytes of value at address */
+void stem_store(uint64_t address, uint64_t value, int size) {
+    uint8_t v8 = (uint8_t)value;
+    uint16_t v16 = (uint16_t)value;
+    uint32_t v32 = (uint32_t)value;
+    switch (size) {
+    case 1:
+        memcpy(stem_pointer(address), &v8, 1);
+        break;
+    case 2:
+        memcpy(stem_pointer(address), &v16, 2);
+        break;
+    case 4:
+        memcpy(stem_pointer(address), &v32, 4);
+        break;
+    default:
+        memcpy(stem_pointer(address), &value, 8);
+    }
+}
+
+/* the signed division and remainder of the machine, the smallest i64 divided by -1 wraps instead of trapping */
+uint64_t stem_div(uint64_t a, uint64_t b) {
+    if (b == UINT64_MAX) {
+        return 0 - a;
+    }
+    return (uint64_t)(stem_signed(a) / stem_signed(b));
+}
+
+uint64_t stem_rem(uint64_t a, uint64_t b) {
+    if (b == UINT64_MAX) {
+        return 0;
+    }
+    return (uint64_t)(stem_signed(a) % stem_signed(b));
+}
+
+/* the arithmetic shift right, like the machine only the low 6 bits of count are used */
+uint64_t stem_shr(uint64_t value, uint64_t count) {
+    count &= 63;
+    return value >> 63 ? ~(~value >> count) : value >> count;
+}
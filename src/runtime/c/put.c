@@ -0,0 +1,9 @@
+
+/* put(value, is_signed): print the value in decimal and a newline, as an i64 when is_signed is not 0, as an u64 otherwise */
+void runtime_put(uint64_t value, int is_signed) {
+    if (is_signed) {
+        printf("%" PRId64 "\n", stem_signed(value));
+    } else {
+        printf("%" PRIu64 "\n", value);
+    }
+}
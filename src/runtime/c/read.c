@@ -0,0 +1,14 @@
+
+/* read(pointer, len) -> count: read up to len bytes of stdin, 0 at the end of the input
+ * a read stops after a newline, so that a line typed is read as soon as it is entered */
+int64_t runtime_read(unsigned char *pointer, int64_t len) {
+    int64_t count = 0;
+    int c;
+    while (count < len && (c = getchar()) != EOF) {
+        pointer[count++] = (unsigned char)c;
+        if (c == '\n') {
+            break;
+        }
+    }
+    return count;
+}
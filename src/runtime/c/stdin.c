@@ -0,0 +1,17 @@
+
+/* get_byte() -> byte: the next byte of stdin, -1 at the end of the input
+ * stdin is read 4096 bytes at a time in get_buffer, get_start is the next byte and get_end the bytes read */
+static unsigned char get_buffer[4096];
+static int64_t get_start, get_end;
+
+int64_t runtime_get_byte(void) {
+    if (get_start >= get_end) {
+        get_start = 0;
+        get_end = runtime_read(get_buffer, sizeof get_buffer);
+        if (get_end <= 0) {
+            get_end = 0;
+            return -1;
+        }
+    }
+    return get_buffer[get_start++];
+}
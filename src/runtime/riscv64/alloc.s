@@ -0,0 +1,91 @@
+# alloc(size) -> pointer and free(pointer), a1 and a2 hold the location of the call for the panics
+# a block starts with a 16 bytes header: its size and ALLOC_USED or ALLOC_FREE
+# small blocks (up to ALLOC_SMALL bytes) are rounded to a size class 16 << k, taken from the heap
+# grown with brk and kept in a free list per class once freed, larger blocks are mmap'ed and munmap'ed
+runtime.alloc:
+        mv      a3, a0
+        li      a4, ALLOC_SMALL
+        bgtu    a3, a4, .Lalloc_large
+        li      a4, 0
+        li      a5, 16
+.Lalloc_class:
+        bgeu    a5, a3, .Lalloc_small
+        slli    a5, a5, 1
+        addi    a4, a4, 1
+        j       .Lalloc_class
+# a6 is the free list of the class
+.Lalloc_small:
+        lla     a6, alloc_free_lists
+        slli    a4, a4, 3
+        add     a6, a6, a4
+        ld      a0, 0(a6)
+        beqz    a0, .Lalloc_carve
+        ld      a3, 0(a0)
+        sd      a3, 0(a6)
+        addi    a0, a0, -16
+        j       .Lalloc_mark
+# t1 is alloc_heap_top and alloc_heap_end the quad after it, t0 the block with its header
+.Lalloc_carve:
+        addi    t0, a5, 16
+        lla     t1, alloc_heap_top
+        ld      a0, 0(t1)
+        bnez    a0, .Lalloc_room
+        li      a0, 0
+        li      a7, SYS_BRK
+        ecall
+        sd      a0, 0(t1)
+        sd      a0, 8(t1)
+.Lalloc_room:
+        add     a3, a0, t0
+        ld      a4, 8(t1)
+        bleu    a3, a4, .Lalloc_carved
+        li      a0, ALLOC_GROW
+        add     a0, a4, a0
+        li      a7, SYS_BRK
+        ecall
+        bltu    a0, a3, .Lalloc_out_of_memory
+        sd      a0, 8(t1)
+        ld      a0, 0(t1)
+.Lalloc_carved:
+        sd      a3, 0(t1)
+        sd      a5, 0(a0)
+        j       .Lalloc_mark
+# the location is kept in t0 and t1 during the mmap
+.Lalloc_large:
+        mv      t0, a1
+        mv      t1, a2
+        li      a4, 4096 + 15
+        add     a1, a3, a4
+        li      a4, -4096
+        and     a1, a1, a4
+        li      a0, 0
+        li      a2, 3
+        li      a3, 0x22
+        li      a4, -1
+        li      a5, 0
+        li      a7, SYS_MMAP
+        ecall
+        mv      a3, a1
+        mv      a1, t0
+        mv      a2, t1
+        li      a4, -4096
+        bgtu    a0, a4, .Lalloc_out_of_memory
+        sd      a3, 0(a0)
+.Lalloc_mark:
+        li      a3, ALLOC_USED
+        sd      a3, 8(a0)
+        addi    a0, a0, 16
+        ret
+.Lalloc_out_of_memory:
+        lla     a3, alloc_out_of_memory
+        lla     a4, alloc_out_of_memory_end
+        sub     a4, a4, a3
+        j       runtime.panic_at
+        .section .rodata
+alloc_out_of_memory: .ascii "out of memory\n"
+alloc_out_of_memory_end:
+        .bss
+        .balign 8
+alloc_free_lists: .zero 64
+alloc_heap_top: .zero 8
+alloc_heap_end: .zero 8
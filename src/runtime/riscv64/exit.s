@@ -0,0 +1,4 @@
+# exit(code), never returns
+runtime.exit:
+        li      a7, SYS_EXIT
+        ecall
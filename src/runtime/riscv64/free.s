@@ -0,0 +1,50 @@
+# free(pointer), its blocks go back to the free lists of alloc
+runtime.free:
+        beqz    a0, .Lfree_done
+        addi    a3, a0, -16
+        ld      a4, 8(a3)
+        li      a5, ALLOC_FREE
+        beq     a4, a5, .Lfree_double
+        li      a6, ALLOC_USED
+        bne     a4, a6, .Lfree_invalid
+        sd      a5, 8(a3)
+        ld      a4, 0(a3)
+        li      a5, ALLOC_SMALL
+        bgtu    a4, a5, .Lfree_large
+        li      a5, 0
+        li      a6, 16
+.Lfree_class:
+        bgeu    a6, a4, .Lfree_push
+        slli    a6, a6, 1
+        addi    a5, a5, 1
+        j       .Lfree_class
+.Lfree_push:
+        lla     a6, alloc_free_lists
+        slli    a5, a5, 3
+        add     a6, a6, a5
+        ld      a4, 0(a6)
+        sd      a4, 0(a0)
+        sd      a0, 0(a6)
+.Lfree_done:
+        ret
+.Lfree_large:
+        mv      a0, a3
+        mv      a1, a4
+        li      a7, SYS_MUNMAP
+        ecall
+        ret
+.Lfree_double:
+        lla     a3, alloc_double_free
+        lla     a4, alloc_double_free_end
+        sub     a4, a4, a3
+        j       runtime.panic_at
+.Lfree_invalid:
+        lla     a3, alloc_invalid_free
+        lla     a4, alloc_invalid_free_end
+        sub     a4, a4, a3
+        j       runtime.panic_at
+        .section .rodata
+alloc_double_free: .ascii "double free of a heap block\n"
+alloc_double_free_end:
+alloc_invalid_free: .ascii "free of a pointer that alloc didn't return\n"
+alloc_invalid_free_end:
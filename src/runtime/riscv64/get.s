@@ -0,0 +1,88 @@
+# get(pointer) -> status: read a line of stdin holding an i64 in decimal, with an optional sign and blanks around it
+# the status is 1 when the value was stored at pointer, 0 at the end of the input, -1 when the line is not an i64
+# blank lines are skipped, t3 is where the line is: 0 before the number, 1 after its sign,
+# 2 in its digits, 3 after it and 4 once it is known not to be a number
+# the caller of a builtin saves the t registers
+runtime.get:
+        addi    sp, sp, -16
+        sd      ra, 8(sp)
+        mv      t0, a0
+.Lget_line:
+        li      t1, 0
+        li      t2, 0
+        li      t3, 0
+.Lget_next:
+        call    runtime.get_byte
+        li      t4, -1
+        beq     a0, t4, .Lget_eof
+        li      t4, 10
+        beq     a0, t4, .Lget_end_of_line
+        li      t4, 32
+        beq     a0, t4, .Lget_blank
+        li      t4, 9
+        beq     a0, t4, .Lget_blank
+        li      t4, 13
+        beq     a0, t4, .Lget_blank
+        li      t4, 45
+        beq     a0, t4, .Lget_minus
+        li      t4, 43
+        beq     a0, t4, .Lget_plus
+        addi    a0, a0, -48
+        li      t4, 9
+        bgtu    a0, t4, .Lget_bad
+        li      t4, 2
+        bgtu    t3, t4, .Lget_bad
+        li      t3, 2
+        li      t4, 10
+        mulhu   t5, t1, t4
+        bnez    t5, .Lget_bad
+        mul     t1, t1, t4
+        add     t1, t1, a0
+        bltu    t1, a0, .Lget_bad
+        j       .Lget_next
+.Lget_minus:
+        li      t2, 1
+.Lget_plus:
+        bnez    t3, .Lget_bad
+        li      t3, 1
+        j       .Lget_next
+.Lget_blank:
+        li      t4, 1
+        beq     t3, t4, .Lget_bad
+        li      t4, 2
+        bne     t3, t4, .Lget_next
+        li      t3, 3
+        j       .Lget_next
+.Lget_bad:
+        li      t3, 4
+        j       .Lget_next
+.Lget_end_of_line:
+        beqz    t3, .Lget_line
+        j       .Lget_done
+.Lget_eof:
+        li      a0, 0
+        beqz    t3, .Lget_return
+.Lget_done:
+        li      t4, 2
+        bltu    t3, t4, .Lget_invalid
+        li      t4, 3
+        bgtu    t3, t4, .Lget_invalid
+        # the digits are read as an u64, 2^63 only fits with a minus
+        li      t4, 1
+        slli    t4, t4, 63
+        bgtu    t1, t4, .Lget_invalid
+        bltu    t1, t4, .Lget_sign
+        beqz    t2, .Lget_invalid
+.Lget_sign:
+        beqz    t2, .Lget_store
+        neg     t1, t1
+.Lget_store:
+        sd      t1, 0(t0)
+        li      a0, 1
+        j       .Lget_return
+.Lget_invalid:
+        li      a0, -1
+.Lget_return:
+        ld      ra, 8(sp)
+        addi    sp, sp, 16
+        ret
@@ -0,0 +1,29 @@
+# get_line(buffer, size) -> len: read a line of stdin in buffer, without its newline
+# len is the length of the whole line, only its first size bytes are stored when it is longer,
+# -1 at the end of the input
+runtime.get_line:
+        addi    sp, sp, -16
+        sd      ra, 8(sp)
+        mv      t0, a0
+        mv      t1, a1
+        li      t2, 0
+.Lget_line_next:
+        call    runtime.get_byte
+        li      t3, -1
+        beq     a0, t3, .Lget_line_eof
+        li      t3, 10
+        beq     a0, t3, .Lget_line_done
+        bgeu    t2, t1, .Lget_line_skip
+        add     t3, t0, t2
+        sb      a0, 0(t3)
+.Lget_line_skip:
+        addi    t2, t2, 1
+        j       .Lget_line_next
+.Lget_line_eof:
+        bnez    t2, .Lget_line_done
+        li      t2, -1
+.Lget_line_done:
+        mv      a0, t2
+        ld      ra, 8(sp)
+        addi    sp, sp, 16
+        ret
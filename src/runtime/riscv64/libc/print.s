@@ -0,0 +1,26 @@
+# print(pointer, len): fflush(NULL) for what C buffered, then write len bytes on stdout,
+# a short write is continued, an error gives up
+runtime.print:
+        addi    sp, sp, -32
+        sd      ra, 24(sp)
+        sd      a0, 8(sp)
+        sd      a1, 0(sp)
+        li      a0, 0
+        call    fflush
+        ld      a0, 8(sp)
+        ld      a1, 0(sp)
+        ld      ra, 24(sp)
+        addi    sp, sp, 32
+        mv      a2, a1
+        mv      a1, a0
+.Lprint_loop:
+        beqz    a2, .Lprint_done
+        li      a0, 1
+        li      a7, SYS_WRITE
+        ecall
+        blez    a0, .Lprint_done
+        add     a1, a1, a0
+        sub     a2, a2, a0
+        j       .Lprint_loop
+.Lprint_done:
+        ret
@@ -0,0 +1,17 @@
+# panic(message, len): write the message on stderr and exit with 101
+runtime.panic:
+        mv      a2, a1
+        mv      a1, a0
+        li      a0, 2
+        li      a7, SYS_WRITE
+        ecall
+        li      a0, 101
+        j       runtime.exit
+# panic_at: write the location in a1, a2 (the one a builtin gets) then panic with the message in a3, a4
+runtime.panic_at:
+        li      a0, 2
+        li      a7, SYS_WRITE
+        ecall
+        mv      a0, a3
+        mv      a1, a4
+        j       runtime.panic
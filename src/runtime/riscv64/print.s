@@ -0,0 +1,15 @@
+# print(pointer, len): write len bytes on stdout, a short write is continued, an error gives up
+runtime.print:
+        mv      a2, a1
+        mv      a1, a0
+.Lprint_loop:
+        beqz    a2, .Lprint_done
+        li      a0, 1
+        li      a7, SYS_WRITE
+        ecall
+        blez    a0, .Lprint_done
+        add     a1, a1, a0
+        sub     a2, a2, a0
+        j       .Lprint_loop
+.Lprint_done:
+        ret
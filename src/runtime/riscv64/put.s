@@ -0,0 +1,37 @@
+# put(value, signed): print the value in decimal and a newline, as an i64 when signed is not 0, as an u64 otherwise
+# the digits are written backward from the end of a buffer in the frame, the 32 bytes above ra and s0
+runtime.put:
+        addi    sp, sp, -48
+        sd      ra, 8(sp)
+        sd      s0, 0(sp)
+        mv      s0, sp
+        li      a3, 0
+        beqz    a1, .Lput_digits
+        bgez    a0, .Lput_digits
+        li      a3, 1
+        neg     a0, a0
+.Lput_digits:
+        addi    a1, s0, 47
+        li      a2, 10
+        sb      a2, 0(a1)
+        li      a4, 10
+.Lput_digit:
+        remu    a5, a0, a4
+        divu    a0, a0, a4
+        addi    a5, a5, 48
+        addi    a1, a1, -1
+        sb      a5, 0(a1)
+        bnez    a0, .Lput_digit
+        beqz    a3, .Lput_write
+        addi    a1, a1, -1
+        li      a2, 45
+        sb      a2, 0(a1)
+.Lput_write:
+        mv      a0, a1
+        addi    a1, s0, 48
+        sub     a1, a1, a0
+        call    runtime.print
+        ld      ra, 8(sp)
+        ld      s0, 0(sp)
+        addi    sp, sp, 48
+        ret
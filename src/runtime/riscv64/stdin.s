@@ -0,0 +1,39 @@
+        .equ    GET_BUFFER_SIZE, 4096
+# get_byte() -> byte: the next byte of stdin, -1 at the end of the input
+# stdin is read GET_BUFFER_SIZE bytes at a time in get_buffer, get_start is the next byte and get_end the bytes read
+# only the a registers are used, get and get_line keep their state in the t registers
+runtime.get_byte:
+        lla     a3, get_start
+        ld      a0, 0(a3)
+        ld      a4, 8(a3)
+        bltu    a0, a4, .Lget_byte_next
+        addi    sp, sp, -16
+        sd      ra, 8(sp)
+        lla     a0, get_buffer
+        li      a1, GET_BUFFER_SIZE
+        call    runtime.read
+        ld      ra, 8(sp)
+        addi    sp, sp, 16
+        lla     a3, get_start
+        blez    a0, .Lget_byte_eof
+        sd      a0, 8(a3)
+        li      a0, 0
+.Lget_byte_next:
+        lla     a1, get_buffer
+        add     a1, a1, a0
+        lbu     a2, 0(a1)
+        addi    a0, a0, 1
+        sd      a0, 0(a3)
+        mv      a0, a2
+        ret
+.Lget_byte_eof:
+        sd      zero, 0(a3)
+        sd      zero, 8(a3)
+        li      a0, -1
+        ret
+        .bss
+        .balign 8
+# get_end is the quad after get_start
+get_start: .zero 8
+get_end: .zero 8
+get_buffer: .zero GET_BUFFER_SIZE
@@ -0,0 +1,169 @@
+  ;; alloc(size) -> pointer and free(pointer), at and at_len are the location of the call for the panics
+  ;; a block starts with a 16 bytes header: its size and ALLOC_USED (0x75736564) or ALLOC_FREE (0x66726565)
+  ;; small blocks (up to 2048 bytes) are rounded to a size class 16 << k and kept in the free list at 64 + 4 * k
+  ;; once freed, larger blocks are rounded to 16 bytes and kept in the list at 96 where the first one big enough is
+  ;; taken again, every block is carved from the heap: the memory above the one of the module, grown as needed
+  (global $alloc.heap_top (mut i64) (i64.const 0))
+  (global $alloc.heap_end (mut i64) (i64.const 0))
+  (data (i32.const 256) "out of memory\n")
+  (func $runtime.alloc (param $size i64) (param $at i32) (param $at_len i32) (result i64)
+    (local $rounded i32) (local $list i32) (local $block i32) (local $pages i32)
+    block $mark
+      local.get $size
+      i64.const 2048
+      i64.gt_u
+      if
+        ;; more than the 4 GiB of the memory
+        local.get $size
+        i64.const 0xffff0000
+        i64.gt_u
+        if
+          local.get $at
+          local.get $at_len
+          i32.const 256
+          i32.const 14
+          call $runtime.panic_at
+        end
+        local.get $size
+        i32.wrap_i64
+        i32.const 15
+        i32.add
+        i32.const -16
+        i32.and
+        local.set $rounded
+        i32.const 96
+        local.set $list
+        block $carve
+          loop $next
+            local.get $list
+            i32.load
+            local.tee $block
+            i32.eqz
+            br_if $carve
+            local.get $block
+            i32.const 16
+            i32.sub
+            i64.load
+            local.get $rounded
+            i64.extend_i32_u
+            i64.ge_u
+            if
+              local.get $list
+              local.get $block
+              i32.load
+              i32.store
+              local.get $block
+              i32.const 16
+              i32.sub
+              local.set $block
+              br $mark
+            end
+            local.get $block
+            local.set $list
+            br $next
+          end
+        end
+      else
+        i32.const 16
+        local.set $rounded
+        i32.const 64
+        local.set $list
+        loop $class
+          local.get $rounded
+          i64.extend_i32_u
+          local.get $size
+          i64.lt_u
+          if
+            local.get $rounded
+            i32.const 1
+            i32.shl
+            local.set $rounded
+            local.get $list
+            i32.const 4
+            i32.add
+            local.set $list
+            br $class
+          end
+        end
+        local.get $list
+        i32.load
+        local.tee $block
+        if
+          local.get $list
+          local.get $block
+          i32.load
+          i32.store
+          local.get $block
+          i32.const 16
+          i32.sub
+          local.set $block
+          br $mark
+        end
+      end
+      ;; carve the block and its header
+      global.get $alloc.heap_top
+      i64.eqz
+      if
+        memory.size
+        i64.extend_i32_u
+        i64.const 16
+        i64.shl
+        global.set $alloc.heap_top
+        global.get $alloc.heap_top
+        global.set $alloc.heap_end
+      end
+      global.get $alloc.heap_top
+      i32.wrap_i64
+      local.set $block
+      global.get $alloc.heap_top
+      local.get $rounded
+      i64.extend_i32_u
+      i64.add
+      i64.const 16
+      i64.add
+      global.set $alloc.heap_top
+      global.get $alloc.heap_top
+      global.get $alloc.heap_end
+      i64.gt_u
+      if
+        ;; the pages that are missing
+        global.get $alloc.heap_top
+        global.get $alloc.heap_end
+        i64.sub
+        i64.const 65535
+        i64.add
+        i64.const 16
+        i64.shr_u
+        i32.wrap_i64
+        local.tee $pages
+        memory.grow
+        i32.const -1
+        i32.eq
+        if
+          local.get $at
+          local.get $at_len
+          i32.const 256
+          i32.const 14
+          call $runtime.panic_at
+        end
+        global.get $alloc.heap_end
+        local.get $pages
+        i64.extend_i32_u
+        i64.const 16
+        i64.shl
+        i64.add
+        global.set $alloc.heap_end
+      end
+      local.get $block
+      local.get $rounded
+      i64.extend_i32_u
+      i64.store
+    end
+    local.get $block
+    i64.const 0x75736564
+    i64.store offset=8
+    local.get $block
+    i32.const 16
+    i32.add
+    i64.extend_i32_u
+  )
@@ -0,0 +1,9 @@
+  ;; exit(code), never returns, the status of the process is the low byte of code like on linux
+  (func $runtime.exit (param $code i64) (param $at i32) (param $at_len i32)
+    local.get $code
+    i32.wrap_i64
+    i32.const 255
+    i32.and
+    call $wasi.proc_exit
+    unreachable
+  )
@@ -0,0 +1,79 @@
+  ;; free(pointer), its blocks go back to the lists of alloc
+  (data (i32.const 288) "double free of a heap block\n")
+  (data (i32.const 320) "free of a pointer that alloc didn't return\n")
+  (func $runtime.free (param $pointer i64) (param $at i32) (param $at_len i32)
+    (local $block i32) (local $size i32) (local $list i32)
+    local.get $pointer
+    i64.eqz
+    if
+      return
+    end
+    local.get $pointer
+    i32.wrap_i64
+    i32.const 16
+    i32.sub
+    local.tee $block
+    i64.load offset=8
+    i64.const 0x66726565
+    i64.eq
+    if
+      local.get $at
+      local.get $at_len
+      i32.const 288
+      i32.const 28
+      call $runtime.panic_at
+    end
+    local.get $block
+    i64.load offset=8
+    i64.const 0x75736564
+    i64.ne
+    if
+      local.get $at
+      local.get $at_len
+      i32.const 320
+      i32.const 43
+      call $runtime.panic_at
+    end
+    local.get $block
+    i64.const 0x66726565
+    i64.store offset=8
+    local.get $block
+    i64.load
+    i32.wrap_i64
+    local.set $size
+    i32.const 96
+    local.set $list
+    local.get $size
+    i32.const 2048
+    i32.le_u
+    if
+      i32.const 64
+      local.set $list
+      loop $class
+        local.get $size
+        i32.const 16
+        i32.gt_u
+        if
+          local.get $size
+          i32.const 1
+          i32.shr_u
+          local.set $size
+          local.get $list
+          i32.const 4
+          i32.add
+          local.set $list
+          br $class
+        end
+      end
+    end
+    ;; the block is pushed on its list, the link is in its first bytes
+    local.get $block
+    local.get $list
+    i32.load
+    i32.store offset=16
+    local.get $list
+    local.get $block
+    i32.const 16
+    i32.add
+    i32.store
+  )
@@ -0,0 +1,164 @@
+  ;; get(pointer) -> status: read a line of stdin holding an i64 in decimal, with an optional sign and blanks around it
+  ;; the status is 1 when the value was stored at pointer, 0 at the end of the input, -1 when the line is not an i64
+  ;; blank lines are skipped, $state is where the line is: 0 before the number, 1 after its sign,
+  ;; 2 in its digits, 3 after it and 4 once it is known not to be a number
+  (func $runtime.get (param $pointer i64) (param $at i32) (param $at_len i32) (result i64)
+    (local $value i64) (local $negative i32) (local $state i32) (local $byte i64)
+    loop $line
+      i64.const 0
+      local.set $value
+      i32.const 0
+      local.set $negative
+      i32.const 0
+      local.set $state
+      block $end_of_line
+        loop $next
+          call $runtime.get_byte
+          local.tee $byte
+          i64.const -1
+          i64.eq
+          if
+            local.get $state
+            i32.eqz
+            if
+              i64.const 0
+              return
+            end
+            br $end_of_line
+          end
+          local.get $byte
+          i64.const 10
+          i64.eq
+          if
+            local.get $state
+            i32.eqz
+            br_if $line
+            br $end_of_line
+          end
+          local.get $byte
+          i64.const 32
+          i64.eq
+          local.get $byte
+          i64.const 9
+          i64.eq
+          i32.or
+          local.get $byte
+          i64.const 13
+          i64.eq
+          i32.or
+          if
+            local.get $state
+            i32.const 1
+            i32.eq
+            if
+              i32.const 4
+              local.set $state
+            end
+            local.get $state
+            i32.const 2
+            i32.eq
+            if
+              i32.const 3
+              local.set $state
+            end
+            br $next
+          end
+          local.get $byte
+          i64.const 45
+          i64.eq
+          local.get $byte
+          i64.const 43
+          i64.eq
+          i32.or
+          if
+            local.get $state
+            if
+              i32.const 4
+              local.set $state
+              br $next
+            end
+            local.get $byte
+            i64.const 45
+            i64.eq
+            local.set $negative
+            i32.const 1
+            local.set $state
+            br $next
+          end
+          local.get $byte
+          i64.const 48
+          i64.sub
+          local.tee $byte
+          i64.const 9
+          i64.gt_u
+          local.get $state
+          i32.const 2
+          i32.gt_u
+          i32.or
+          ;; the digits are read as an u64, value * 10 + digit has to fit
+          local.get $value
+          i64.const 0x1999999999999999
+          i64.gt_u
+          i32.or
+          if
+            i32.const 4
+            local.set $state
+            br $next
+          end
+          i32.const 2
+          local.set $state
+          local.get $value
+          i64.const 10
+          i64.mul
+          local.get $byte
+          i64.add
+          local.tee $value
+          local.get $byte
+          i64.lt_u
+          if
+            i32.const 4
+            local.set $state
+          end
+          br $next
+        end
+      end
+      local.get $state
+      i32.const 2
+      i32.lt_u
+      local.get $state
+      i32.const 3
+      i32.gt_u
+      i32.or
+      if
+        i64.const -1
+        return
+      end
+      ;; 2^63 only fits with a minus
+      local.get $value
+      i64.const 0x8000000000000000
+      i64.gt_u
+      local.get $value
+      i64.const 0x8000000000000000
+      i64.eq
+      local.get $negative
+      i32.eqz
+      i32.and
+      i32.or
+      if
+        i64.const -1
+        return
+      end
+      local.get $pointer
+      i32.wrap_i64
+      i64.const 0
+      local.get $value
+      i64.sub
+      local.get $value
+      local.get $negative
+      select
+      i64.store
+      i64.const 1
+      return
+    end
+    unreachable
+  )
@@ -0,0 +1,44 @@
+  ;; get_line(buffer, size) -> len: read a line of stdin in buffer, without its newline
+  ;; len is the length of the whole line, only its first size bytes are stored when it is longer,
+  ;; -1 at the end of the input
+  (func $runtime.get_line (param $buffer i64) (param $size i64) (param $at i32) (param $at_len i32) (result i64)
+    (local $len i64) (local $byte i64)
+    block $done
+      loop $next
+        call $runtime.get_byte
+        local.tee $byte
+        i64.const -1
+        i64.eq
+        if
+          local.get $len
+          i64.eqz
+          if
+            i64.const -1
+            return
+          end
+          br $done
+        end
+        local.get $byte
+        i64.const 10
+        i64.eq
+        br_if $done
+        local.get $len
+        local.get $size
+        i64.lt_u
+        if
+          local.get $buffer
+          local.get $len
+          i64.add
+          i32.wrap_i64
+          local.get $byte
+          i64.store8
+        end
+        local.get $len
+        i64.const 1
+        i64.add
+        local.set $len
+        br $next
+      end
+    end
+    local.get $len
+  )
@@ -0,0 +1,38 @@
+  ;; panic(message, len): write the message on stderr and exit with 101
+  (func $runtime.panic (param $message i32) (param $len i32)
+    i32.const 16
+    local.get $message
+    i32.store
+    i32.const 20
+    local.get $len
+    i32.store
+    i32.const 2
+    i32.const 16
+    i32.const 1
+    i32.const 24
+    call $wasi.fd_write
+    drop
+    i64.const 101
+    i32.const 0
+    i32.const 0
+    call $runtime.exit
+  )
+
+  ;; panic_at(at, at_len, message, len): write the location a builtin gets then panic with the message
+  (func $runtime.panic_at (param $at i32) (param $at_len i32) (param $message i32) (param $len i32)
+    i32.const 16
+    local.get $at
+    i32.store
+    i32.const 20
+    local.get $at_len
+    i32.store
+    i32.const 2
+    i32.const 16
+    i32.const 1
+    i32.const 24
+    call $wasi.fd_write
+    drop
+    local.get $message
+    local.get $len
+    call $runtime.panic
+  )
@@ -0,0 +1,123 @@
+  ;; the runtime of wasm32 makes its system calls through the functions WASI imports from wasi_snapshot_preview1
+  ;; the memory below 8192 is the runtime's: 0 to 16 stays zero so that a null pointer points to nothing,
+  ;; 16 is the iovec of a read or a write and 24 the count of bytes it moved, 32 to 64 the digits of put,
+  ;; 64 the free lists of alloc, 256 the messages of alloc and free, 1024 to 5120 the buffer of get_byte
+  (import "wasi_snapshot_preview1" "fd_write" (func $wasi.fd_write (param i32 i32 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "fd_read" (func $wasi.fd_read (param i32 i32 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "args_sizes_get" (func $wasi.args_sizes_get (param i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "args_get" (func $wasi.args_get (param i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "proc_exit" (func $wasi.proc_exit (param i32)))
+
+  ;; argv() -> pointer: the arguments of the process as main takes them, an array of pointers of 8 bytes ended
+  ;; by a null one, built with their strings below the stack pointer, their count is left in $runtime.argc
+  (global $runtime.argc (mut i64) (i64.const 0))
+  (func $runtime.argv (result i64)
+    (local $count i32) (local $strings i32) (local $pointers i32) (local $argv i32) (local $i i32)
+    i32.const 16
+    i32.const 20
+    call $wasi.args_sizes_get
+    drop
+    i32.const 16
+    i32.load
+    local.set $count
+    ;; the strings, the pointers of 4 bytes WASI writes under them, then the array
+    global.get $stack_pointer
+    i32.const 20
+    i32.load
+    i32.sub
+    i32.const -16
+    i32.and
+    local.tee $strings
+    local.get $count
+    i32.const 4
+    i32.mul
+    i32.sub
+    i32.const -16
+    i32.and
+    local.tee $pointers
+    local.get $count
+    i32.const 1
+    i32.add
+    i32.const 8
+    i32.mul
+    i32.sub
+    i32.const -16
+    i32.and
+    local.tee $argv
+    global.set $stack_pointer
+    local.get $pointers
+    local.get $strings
+    call $wasi.args_get
+    drop
+    block $done
+      loop $next
+        local.get $i
+        local.get $count
+        i32.ge_u
+        br_if $done
+        local.get $argv
+        local.get $i
+        i32.const 8
+        i32.mul
+        i32.add
+        local.get $pointers
+        local.get $i
+        i32.const 4
+        i32.mul
+        i32.add
+        i32.load
+        i64.extend_i32_u
+        i64.store
+        local.get $i
+        i32.const 1
+        i32.add
+        local.set $i
+        br $next
+      end
+    end
+    local.get $argv
+    local.get $count
+    i32.const 8
+    i32.mul
+    i32.add
+    i64.const 0
+    i64.store
+    local.get $count
+    i64.extend_i32_u
+    global.set $runtime.argc
+    local.get $argv
+    i64.extend_i32_u
+  )
+
+  ;; test() -> index: the index of the test `stem-rs test` runs, the decimal number in argv[1]
+  (func $runtime.test (result i64)
+    (local $pointer i32) (local $index i64) (local $digit i64)
+    call $runtime.argv
+    i32.wrap_i64
+    i64.load offset=8
+    i32.wrap_i64
+    local.set $pointer
+    block $done
+      loop $next
+        local.get $pointer
+        i64.load8_u
+        local.tee $digit
+        i64.eqz
+        br_if $done
+        local.get $index
+        i64.const 10
+        i64.mul
+        local.get $digit
+        i64.add
+        i64.const 48
+        i64.sub
+        local.set $index
+        local.get $pointer
+        i32.const 1
+        i32.add
+        local.set $pointer
+        br $next
+      end
+    end
+    local.get $index
+  )
@@ -0,0 +1,46 @@
+  ;; print(pointer, len): write len bytes on stdout, the builtin of the programs
+  (func $runtime.print (param $pointer i64) (param $len i64) (param $at i32) (param $at_len i32)
+    local.get $pointer
+    i32.wrap_i64
+    local.get $len
+    i32.wrap_i64
+    call $runtime.write
+  )
+  ;; write(pointer, len): write len bytes on stdout, a short write is continued, an error gives up
+  (func $runtime.write (param $pointer i32) (param $len i32)
+    block $done
+      loop $next
+        local.get $len
+        i32.eqz
+        br_if $done
+        i32.const 16
+        local.get $pointer
+        i32.store
+        i32.const 20
+        local.get $len
+        i32.store
+        i32.const 1
+        i32.const 16
+        i32.const 1
+        i32.const 24
+        call $wasi.fd_write
+        br_if $done
+        ;; writing nothing would never end
+        i32.const 24
+        i32.load
+        i32.eqz
+        br_if $done
+        local.get $pointer
+        i32.const 24
+        i32.load
+        i32.add
+        local.set $pointer
+        local.get $len
+        i32.const 24
+        i32.load
+        i32.sub
+        local.set $len
+        br $next
+      end
+    end
+  )
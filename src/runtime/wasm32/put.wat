@@ -0,0 +1,54 @@
+  ;; put(value, signed): print the value in decimal and a newline, as an i64 when signed is not 0, as an u64 otherwise
+  ;; the digits are written backward from the end of the 32 bytes at 32
+  (func $runtime.put (param $value i64) (param $signed i32)
+    (local $pointer i32) (local $negative i32)
+    local.get $signed
+    local.get $value
+    i64.const 0
+    i64.lt_s
+    i32.and
+    local.tee $negative
+    if
+      i64.const 0
+      local.get $value
+      i64.sub
+      local.set $value
+    end
+    i32.const 63
+    local.tee $pointer
+    i32.const 10
+    i32.store8
+    loop $digit
+      local.get $pointer
+      i32.const 1
+      i32.sub
+      local.tee $pointer
+      local.get $value
+      i64.const 10
+      i64.rem_u
+      i64.const 48
+      i64.add
+      i64.store8
+      local.get $value
+      i64.const 10
+      i64.div_u
+      local.tee $value
+      i64.eqz
+      i32.eqz
+      br_if $digit
+    end
+    local.get $negative
+    if
+      local.get $pointer
+      i32.const 1
+      i32.sub
+      local.tee $pointer
+      i32.const 45
+      i32.store8
+    end
+    local.get $pointer
+    i32.const 64
+    local.get $pointer
+    i32.sub
+    call $runtime.write
+  )
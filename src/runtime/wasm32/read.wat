@@ -0,0 +1,20 @@
+  ;; read(pointer, len) -> count: read up to len bytes of stdin, 0 at the end of the input, -1 on an error
+  (func $runtime.read (param $pointer i32) (param $len i32) (result i32)
+    i32.const 16
+    local.get $pointer
+    i32.store
+    i32.const 20
+    local.get $len
+    i32.store
+    i32.const 0
+    i32.const 16
+    i32.const 1
+    i32.const 24
+    call $wasi.fd_read
+    if
+      i32.const -1
+      return
+    end
+    i32.const 24
+    i32.load
+  )
@@ -0,0 +1,38 @@
+  ;; get_byte() -> byte: the next byte of stdin, -1 at the end of the input
+  ;; stdin is read 4096 bytes at a time in the buffer at 1024, $get.start is the next byte and $get.end the bytes read
+  (global $get.start (mut i32) (i32.const 0))
+  (global $get.end (mut i32) (i32.const 0))
+  (func $runtime.get_byte (result i64)
+    (local $count i32)
+    global.get $get.start
+    global.get $get.end
+    i32.ge_u
+    if
+      i32.const 1024
+      i32.const 4096
+      call $runtime.read
+      local.tee $count
+      i32.const 0
+      i32.le_s
+      if
+        i32.const 0
+        global.set $get.start
+        i32.const 0
+        global.set $get.end
+        i64.const -1
+        return
+      end
+      i32.const 0
+      global.set $get.start
+      local.get $count
+      global.set $get.end
+    end
+    global.get $get.start
+    i32.const 1024
+    i32.add
+    i64.load8_u
+    global.get $get.start
+    i32.const 1
+    i32.add
+    global.set $get.start
+  )
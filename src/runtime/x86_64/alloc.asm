@@ -0,0 +1,100 @@
+%define ALLOC_SMALL 2048
+%define ALLOC_GROW 65536
+%define ALLOC_USED 0x75736564
+%define ALLOC_FREE 0x66726565
+; alloc(size) -> pointer and free(pointer), rsi and rdx hold the location of the call for the panics
+; a block starts with a 16 bytes header: its size and ALLOC_USED or ALLOC_FREE
+; small blocks (up to ALLOC_SMALL bytes) are rounded to a size class 16 << k, taken from the heap
+; grown with brk and kept in a free list per class once freed, larger blocks are mmap'ed and munmap'ed
+runtime.alloc:
+        mov     r8, rdi
+        cmp     r8, ALLOC_SMALL
+        ja      .alloc_large
+        mov     rcx, 0
+        mov     rax, 16
+.alloc_class:
+        cmp     rax, r8
+        jae     .alloc_small
+        shl     rax, 1
+        inc     rcx
+        jmp     .alloc_class
+.alloc_small:
+        lea     r9, [rel alloc_free_lists]
+        mov     rdi, QWORD [r9+rcx*8]
+        test    rdi, rdi
+        jz      .alloc_carve
+        mov     r8, QWORD [rdi]
+        mov     QWORD [r9+rcx*8], r8
+        lea     rax, [rdi-16]
+        jmp     .alloc_mark
+.alloc_carve:
+        mov     r8, rax
+        lea     r9, [rax+16]
+        mov     rax, QWORD [rel alloc_heap_top]
+        test    rax, rax
+        jnz     .alloc_room
+        push    rsi
+        push    rdx
+        mov     rax, SYS_BRK
+        mov     rdi, 0
+        syscall
+        pop     rdx
+        pop     rsi
+        mov     QWORD [rel alloc_heap_top], rax
+        mov     QWORD [rel alloc_heap_end], rax
+.alloc_room:
+        lea     rdi, [rax+r9]
+        cmp     rdi, QWORD [rel alloc_heap_end]
+        jbe     .alloc_carved
+        push    rsi
+        push    rdx
+        push    rdi
+        mov     rdi, QWORD [rel alloc_heap_end]
+        add     rdi, ALLOC_GROW
+        mov     rax, SYS_BRK
+        syscall
+        pop     rdi
+        pop     rdx
+        pop     rsi
+        cmp     rax, rdi
+        jb      .alloc_out_of_memory
+        mov     QWORD [rel alloc_heap_end], rax
+        mov     rax, QWORD [rel alloc_heap_top]
+.alloc_carved:
+        mov     QWORD [rel alloc_heap_top], rdi
+        mov     QWORD [rax], r8
+        jmp     .alloc_mark
+.alloc_large:
+        push    rsi
+        push    rdx
+        lea     rsi, [r8+16+4095]
+        and     rsi, -4096
+        mov     rax, SYS_MMAP
+        mov     rdi, 0
+        mov     rdx, 3
+        mov     r10, 0x22
+        mov     r8, -1
+        mov     r9, 0
+        syscall
+        mov     rcx, rsi
+        pop     rdx
+        pop     rsi
+        cmp     rax, -4096
+        ja      .alloc_out_of_memory
+        mov     QWORD [rax], rcx
+.alloc_mark:
+        mov     QWORD [rax+8], ALLOC_USED
+        add     rax, 16
+        ret
+.alloc_out_of_memory:
+        lea     r8, [rel alloc_out_of_memory]
+        mov     r9, alloc_out_of_memory_len
+        jmp     runtime.panic_at
+segment .rodata
+alloc_out_of_memory: db `out of memory\n`
+alloc_out_of_memory_len equ $ - alloc_out_of_memory
+segment .bss
+        alignb  8
+alloc_free_lists: resq 8
+alloc_heap_top: resq 1
+alloc_heap_end: resq 1
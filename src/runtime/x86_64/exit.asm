@@ -0,0 +1,4 @@
+; exit(code), never returns
+runtime.exit:
+        mov     rax, SYS_EXIT
+        syscall
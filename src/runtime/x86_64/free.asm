@@ -0,0 +1,48 @@
+; free(pointer), its blocks go back to the free lists of alloc
+runtime.free:
+        test    rdi, rdi
+        jz      .free_done
+        lea     rax, [rdi-16]
+        mov     rcx, QWORD [rax+8]
+        cmp     rcx, ALLOC_FREE
+        je      .free_double
+        cmp     rcx, ALLOC_USED
+        jne     .free_invalid
+        mov     QWORD [rax+8], ALLOC_FREE
+        mov     r8, QWORD [rax]
+        cmp     r8, ALLOC_SMALL
+        ja      .free_large
+        mov     rcx, 0
+        mov     r9, 16
+.free_class:
+        cmp     r9, r8
+        jae     .free_push
+        shl     r9, 1
+        inc     rcx
+        jmp     .free_class
+.free_push:
+        lea     r9, [rel alloc_free_lists]
+        mov     r8, QWORD [r9+rcx*8]
+        mov     QWORD [rdi], r8
+        mov     QWORD [r9+rcx*8], rdi
+.free_done:
+        ret
+.free_large:
+        mov     rdi, rax
+        mov     rsi, r8
+        mov     rax, SYS_MUNMAP
+        syscall
+        ret
+.free_double:
+        lea     r8, [rel alloc_double_free]
+        mov     r9, alloc_double_free_len
+        jmp     runtime.panic_at
+.free_invalid:
+        lea     r8, [rel alloc_invalid_free]
+        mov     r9, alloc_invalid_free_len
+        jmp     runtime.panic_at
+segment .rodata
+alloc_double_free: db `double free of a heap block\n`
+alloc_double_free_len equ $ - alloc_double_free
+alloc_invalid_free: db `free of a pointer that alloc didn't return\n`
+alloc_invalid_free_len equ $ - alloc_invalid_free
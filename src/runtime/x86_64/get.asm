@@ -0,0 +1,98 @@
+; get(pointer) -> status: read a line of stdin holding an i64 in decimal, with an optional sign and blanks around it
+; the status is 1 when the value was stored at pointer, 0 at the end of the input, -1 when the line is not an i64
+; blank lines are skipped, r14 is where the line is: 0 before the number, 1 after its sign,
+; 2 in its digits, 3 after it and 4 once it is known not to be a number
+runtime.get:
+        push    rbx
+        push    r12
+        push    r13
+        push    r14
+        mov     rbx, rdi
+.get_line:
+        mov     r12, 0
+        mov     r13, 0
+        mov     r14, 0
+.get_next:
+        call    runtime.get_byte
+        cmp     rax, -1
+        je      .get_eof
+        cmp     rax, 10
+        je      .get_end_of_line
+        cmp     rax, 32
+        je      .get_blank
+        cmp     rax, 9
+        je      .get_blank
+        cmp     rax, 13
+        je      .get_blank
+        cmp     rax, 45
+        je      .get_minus
+        cmp     rax, 43
+        je      .get_plus
+        sub     rax, 48
+        cmp     rax, 9
+        ja      .get_bad
+        cmp     r14, 2
+        ja      .get_bad
+        mov     r14, 2
+        mov     rcx, rax
+        mov     rax, r12
+        mov     rdx, 10
+        mul     rdx
+        jc      .get_bad
+        add     rax, rcx
+        jc      .get_bad
+        mov     r12, rax
+        jmp     .get_next
+.get_minus:
+        mov     r13, 1
+.get_plus:
+        test    r14, r14
+        jnz     .get_bad
+        mov     r14, 1
+        jmp     .get_next
+.get_blank:
+        cmp     r14, 1
+        je      .get_bad
+        cmp     r14, 2
+        jne     .get_next
+        mov     r14, 3
+        jmp     .get_next
+.get_bad:
+        mov     r14, 4
+        jmp     .get_next
+.get_end_of_line:
+        test    r14, r14
+        jz      .get_line
+        jmp     .get_done
+.get_eof:
+        mov     rax, 0
+        test    r14, r14
+        jz      .get_return
+.get_done:
+        cmp     r14, 2
+        jb      .get_invalid
+        cmp     r14, 3
+        ja      .get_invalid
+        ; the digits are read as an u64, 2^63 only fits with a minus
+        mov     rax, 0x8000000000000000
+        cmp     r12, rax
+        ja      .get_invalid
+        jb      .get_sign
+        test    r13, r13
+        jz      .get_invalid
+.get_sign:
+        test    r13, r13
+        jz      .get_store
+        neg     r12
+.get_store:
+        mov     QWORD [rbx], r12
+        mov     rax, 1
+        jmp     .get_return
+.get_invalid:
+        mov     rax, -1
+.get_return:
+        pop     r14
+        pop     r13
+        pop     r12
+        pop     rbx
+        ret
@@ -0,0 +1,32 @@
+; get_line(buffer, size) -> len: read a line of stdin in buffer, without its newline
+; len is the length of the whole line, only its first size bytes are stored when it is longer,
+; -1 at the end of the input
+runtime.get_line:
+        push    rbx
+        push    r12
+        push    r13
+        mov     rbx, rdi
+        mov     r12, rsi
+        mov     r13, 0
+.get_line_next:
+        call    runtime.get_byte
+        cmp     rax, -1
+        je      .get_line_eof
+        cmp     rax, 10
+        je      .get_line_done
+        cmp     r13, r12
+        jae     .get_line_skip
+        mov     BYTE [rbx+r13], al
+.get_line_skip:
+        inc     r13
+        jmp     .get_line_next
+.get_line_eof:
+        test    r13, r13
+        jnz     .get_line_done
+        mov     r13, -1
+.get_line_done:
+        mov     rax, r13
+        pop     r13
+        pop     r12
+        pop     rbx
+        ret
@@ -0,0 +1,29 @@
+; print(pointer, len): fflush(NULL) for what C buffered, then write len bytes on stdout,
+; a short write is continued, an error gives up
+extern fflush
+runtime.print:
+        push    rbp
+        mov     rbp, rsp
+        push    rdi
+        push    rsi
+        and     rsp, -16
+        mov     rdi, 0
+        call    fflush wrt ..plt
+        mov     rsi, QWORD [rbp-16]
+        mov     rdi, QWORD [rbp-8]
+        leave
+        mov     rdx, rsi
+        mov     rsi, rdi
+.print_loop:
+        test    rdx, rdx
+        jz      .print_done
+        mov     rdi, 1
+        mov     rax, SYS_WRITE
+        syscall
+        test    rax, rax
+        jle     .print_done
+        add     rsi, rax
+        sub     rdx, rax
+        jmp     .print_loop
+.print_done:
+        ret
@@ -0,0 +1,17 @@
+; panic(message, len): write the message on stderr and exit with 101
+runtime.panic:
+        mov     rdx, rsi
+        mov     rsi, rdi
+        mov     rdi, 2
+        mov     rax, SYS_WRITE
+        syscall
+        mov     rdi, 101
+        jmp     runtime.exit
+; panic_at: write the location in rsi, rdx (the one a builtin gets) then panic with the message in r8, r9
+runtime.panic_at:
+        mov     rdi, 2
+        mov     rax, SYS_WRITE
+        syscall
+        mov     rdi, r8
+        mov     rsi, r9
+        jmp     runtime.panic
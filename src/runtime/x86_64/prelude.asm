@@ -0,0 +1,7 @@
+BITS 64
+%define SYS_READ 0
+%define SYS_WRITE 1
+%define SYS_MMAP 9
+%define SYS_MUNMAP 11
+%define SYS_BRK 12
+%define SYS_EXIT 60
@@ -0,0 +1,17 @@
+; print(pointer, len): write len bytes on stdout, a short write is continued, an error gives up
+runtime.print:
+        mov     rdx, rsi
+        mov     rsi, rdi
+.print_loop:
+        test    rdx, rdx
+        jz      .print_done
+        mov     rdi, 1
+        mov     rax, SYS_WRITE
+        syscall
+        test    rax, rax
+        jle     .print_done
+        add     rsi, rax
+        sub     rdx, rax
+        jmp     .print_loop
+.print_done:
+        ret
@@ -0,0 +1,36 @@
+; put(value, signed): print the value in decimal and a newline, as an i64 when signed is not 0, as an u64 otherwise
+; the digits are written backward from the end of a buffer in the frame
+runtime.put:
+        push    rbp
+        mov     rbp, rsp
+        sub     rsp, 32
+        mov     rax, rdi
+        mov     r8, 0
+        test    rsi, rsi
+        jz      .put_digits
+        test    rax, rax
+        jns     .put_digits
+        mov     r8, 1
+        neg     rax
+.put_digits:
+        lea     rdi, [rbp-1]
+        mov     BYTE [rdi], 10
+        mov     rcx, 10
+.put_digit:
+        mov     rdx, 0
+        div     rcx
+        add     dl, 48
+        dec     rdi
+        mov     BYTE [rdi], dl
+        test    rax, rax
+        jnz     .put_digit
+        test    r8, r8
+        jz      .put_write
+        dec     rdi
+        mov     BYTE [rdi], 45
+.put_write:
+        mov     rsi, rbp
+        sub     rsi, rdi
+        call    runtime.print
+        leave
+        ret
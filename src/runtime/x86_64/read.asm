@@ -0,0 +1,8 @@
+; read(pointer, len) -> count: read up to len bytes of stdin, 0 at the end of the input, negative on an error
+runtime.read:
+        mov     rdx, rsi
+        mov     rsi, rdi
+        mov     rdi, 0
+        mov     rax, SYS_READ
+        syscall
+        ret
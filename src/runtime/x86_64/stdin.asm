@@ -0,0 +1,31 @@
+%define GET_BUFFER_SIZE 4096
+; get_byte() -> byte: the next byte of stdin, -1 at the end of the input
+; stdin is read GET_BUFFER_SIZE bytes at a time in get_buffer, get_start is the next byte and get_end the bytes read
+runtime.get_byte:
+        mov     rax, QWORD [rel get_start]
+        cmp     rax, QWORD [rel get_end]
+        jb      .get_byte_next
+        lea     rdi, [rel get_buffer]
+        mov     rsi, GET_BUFFER_SIZE
+        call    runtime.read
+        test    rax, rax
+        jle     .get_byte_eof
+        mov     QWORD [rel get_end], rax
+        mov     rax, 0
+.get_byte_next:
+        lea     rdi, [rel get_buffer]
+        movzx   rcx, BYTE [rdi+rax]
+        inc     rax
+        mov     QWORD [rel get_start], rax
+        mov     rax, rcx
+        ret
+.get_byte_eof:
+        mov     QWORD [rel get_start], 0
+        mov     QWORD [rel get_end], 0
+        mov     rax, -1
+        ret
+segment .bss
+        alignb  8
+get_start: resq 1
+get_end: resq 1
+get_buffer: resb GET_BUFFER_SIZE
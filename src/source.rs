@@ -0,0 +1,175 @@
+/*
+ * The source files read by the compiler
+ * spans are byte offsets into them, the SourceMap turns them back into lines and columns
+ */
+
+use std::cell::Cell;
+
+/*
+ * a tab moves the column to the next multiple of TAB_WIDTH, like gcc does
+ */
+const TAB_WIDTH: u32 = 8;
+
+/*
+ * bytes start..end of the file file_id
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Span {
+    pub file_id: u32,
+    pub start: u32,
+    pub end: u32,
+}
+
+impl Span {
+    /*
+     * the span covering self and everything up to the end of other
+     */
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            file_id: self.file_id,
+            start: self.start,
+            end: other.end,
+        }
+    }
+}
+
+pub struct SourceFile {
+    pub path: String,
+    pub text: String,
+    // byte offset of the first char of every line
+    line_starts: Vec<u32>,
+    // the byte offset and the 0-based column of the last location, the code is generated from left to right so the
+    // column of the next one on the same line is counted from there instead of from the start of the line
+    last: Cell<(u32, u32)>,
+}
+
+/*
+ * 1-based line and column of the start of a span, printed as `file:line:col`
+ */
+pub struct Location<'a> {
+    pub file: &'a str,
+    pub line: u32,
+    pub col: u32,
+}
+
+impl core::fmt::Display for Location<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.col)
+    }
+}
+
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    pub fn new() -> SourceMap {
+        SourceMap { files: vec![] }
+    }
+
+    /*
+     * take ownership of the text of a file and return its file_id
+     */
+    pub fn add_file(&mut self, path: String, text: String) -> u32 {
+        let mut line_starts = vec![0];
+        for (i, byte) in text.bytes().enumerate() {
+            if byte == b'\n' {
+                line_starts.push(i as u32 + 1);
+            }
+        }
+        self.files.push(SourceFile { path, text, line_starts, last: Cell::new((0, 0)) });
+        (self.files.len() - 1) as u32
+    }
+
+    pub fn file(&self, file_id: u32) -> &SourceFile {
+        &self.files[file_id as usize]
+    }
+
+    /*
+     * the line is found by a binary search on the line starts,
+     * the column counts chars (not bytes) from the start of the line, or from the last location when it is earlier
+     * on the same line, expanding tabs
+     */
+    pub fn location(&self, span: Span) -> Location<'_> {
+        let file = self.file(span.file_id);
+        let line = file.line_starts.partition_point(|&start| start <= span.start) - 1;
+        let line_start = file.line_starts[line];
+        let (mut from, mut col) = file.last.get();
+        if from < line_start || from > span.start {
+            (from, col) = (line_start, 0);
+        }
+        for c in file.text[from as usize..span.start as usize].chars() {
+            if c == '\t' {
+                col = (col / TAB_WIDTH + 1) * TAB_WIDTH;
+            } else {
+                col += 1;
+            }
+        }
+        file.last.set((span.start, col));
+        Location {
+            file: &file.path,
+            line: line as u32 + 1,
+            col: col + 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(text: &str, start: u32) -> (u32, u32) {
+        let mut sources = SourceMap::new();
+        let file_id = sources.add_file("t.stm".to_string(), text.to_string());
+        let location = sources.location(Span { file_id, start, end: start });
+        return (location.line, location.col);
+    }
+
+    #[test]
+    fn first_line() {
+        assert_eq!(at("let x = 1;\n", 0), (1, 1));
+        assert_eq!(at("let x = 1;\n", 4), (1, 5));
+    }
+
+    #[test]
+    fn tabs_move_to_the_next_stop() {
+        assert_eq!(at("\tx", 1), (1, 9));
+        assert_eq!(at("ab\tx", 3), (1, 9));
+        assert_eq!(at("abcdefgh\tx", 9), (1, 17));
+        assert_eq!(at("\t\tx", 2), (1, 17));
+        assert_eq!(at("a\n\t b", 4), (2, 10));
+    }
+
+    #[test]
+    fn columns_count_chars_not_bytes() {
+        // é is 2 bytes, 漢 is 3 bytes
+        assert_eq!(at("\"é\" x", 5), (1, 5));
+        assert_eq!(at("// 漢漢\nx\ty", 10), (2, 1));
+        assert_eq!(at("漢\ty", 4), (1, 9));
+    }
+
+    #[test]
+    fn columns_go_back_and_forth() {
+        let mut sources = SourceMap::new();
+        let file_id = sources.add_file("t.stm".to_string(), "a\tb\tc\nd\te".to_string());
+        let col = |start| sources.location(Span { file_id, start, end: start }).col;
+        assert_eq!([col(4), col(2), col(4), col(0), col(8), col(4)], [17, 9, 17, 1, 9, 17]);
+    }
+
+    #[test]
+    fn last_line() {
+        let text = "a\nb\nlast";
+        assert_eq!(at(text, 4), (3, 1));
+        assert_eq!(at(text, 7), (3, 4));
+        // the end of the file, where EOF is
+        assert_eq!(at(text, 8), (3, 5));
+        assert_eq!(at("a\n", 2), (2, 1));
+    }
+
+    #[test]
+    fn newline_belongs_to_its_line() {
+        assert_eq!(at("ab\ncd", 2), (1, 3));
+        assert_eq!(at("ab\ncd", 3), (2, 1));
+        assert_eq!(at("\n\n\n", 2), (3, 1));
+    }
+}
@@ -0,0 +1,171 @@
+/*
+ * The machines the code is generated for
+ * the code generation computes in the scratch registers 0 to SCRATCH_COUNT - 1 and names the labels,
+ * a Target selects the instructions of each operation, with its own register names, the temporaries
+ * it keeps aside for itself and its calling convention, and writes them in the syntax of its assembler
+ */
+mod aarch64;
+mod riscv64;
+mod x86_64;
+
+use crate::ast::{BinaryOp, UnaryOp};
+use crate::runtime::Runtime;
+
+pub use aarch64::AArch64;
+pub use riscv64::RiscV64;
+pub use x86_64::X86_64;
+
+// the registers the code generation allocates, on every target
+pub const SCRATCH_COUNT: usize = 7;
+
+// the arguments of a call are passed in registers, a function takes at most that many
+pub const MAX_ARGS: usize = 6;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Section {
+    Text,
+    Data,
+    Rodata,
+    Bss,
+}
+
+pub trait Target {
+    // the name given to --target=
+    fn name(&self) -> &'static str;
+    fn runtime(&self) -> &'static Runtime;
+
+    /*
+     * the toolchain: the extension of the assembly files, the command assembling source in object,
+     * a tool of the target (`ld`, `cc`, `ar`) and what runs its programs on this machine, if not the machine itself
+     */
+    fn extension(&self) -> &'static str;
+    fn assemble(&self, source: &str, object: &str) -> Vec<String>;
+    fn tool(&self, name: &str) -> String;
+    fn runner(&self) -> Option<&'static str>;
+
+    /*
+     * directives: a comment at the end of a line, the start of a section, the linkage of a symbol,
+     * then the data of .data, .rodata and .bss
+     */
+    fn comment(&self, text: &str) -> String;
+    fn section(&self, section: Section) -> String;
+    fn global(&self, symbol: &str) -> String;
+    fn extern_symbol(&self, symbol: &str) -> String;
+    fn align(&self, align: u32, section: Section) -> String;
+    // an integer of size bytes
+    fn data(&self, size: u32, value: u64) -> String;
+    fn zeros(&self, size: u32) -> String;
+    // size bytes of .bss
+    fn reserve(&self, size: u32) -> String;
+    fn string(&self, text: &str) -> String;
+    // the address of symbol, as data
+    fn address(&self, symbol: &str) -> String;
+
+    /*
+     * values in the scratch registers
+     */
+    fn load_immediate(&self, r: u8, value: u64) -> String;
+    fn move_register(&self, dst: u8, src: u8) -> String;
+    // the address of the slot at offset bytes below the frame pointer
+    fn frame_address(&self, r: u8, offset: u32) -> String;
+    fn symbol_address(&self, r: u8, symbol: &str) -> String;
+    // dst = src + offset
+    fn offset(&self, dst: u8, src: u8, offset: i64) -> String;
+    // replace the address in r by the size bytes it points to, sign or zero extended to 64 bits
+    fn load(&self, r: u8, size: u32, signed: bool) -> String;
+    // store the low size bytes of value at the address in addr
+    fn store(&self, addr: u8, value: u8, size: u32) -> String;
+    // copy size bytes from the address in src to the address in dst
+    fn copy(&self, dst: u8, src: u8, size: u32) -> String;
+
+    /*
+     * arithmetic on 64 bits values, the result is left in left
+     */
+    fn unary(&self, op: UnaryOp, r: u8) -> String;
+    // `+`, `-` or `*` on size bytes, then a branch to ok_label when the result fits in size bytes,
+    // with the sign or not, the bits above size are left for extend
+    fn arith(&self, op: BinaryOp, left: u8, right: u8, size: u32, signed: bool, ok_label: Option<u32>) -> String;
+    // the other operators: `/ % & | ^ << >>` and the comparisons, which give 0 or 1, right is never 0 for `/` and `%`
+    fn binary(&self, op: BinaryOp, left: u8, right: u8, signed: bool) -> String;
+    // extend the value in the low size bytes of r to 64 bits
+    fn extend(&self, r: u8, size: u32, signed: bool) -> String;
+    // r = r * size, and r = r / size when r is a multiple of it
+    fn scale(&self, r: u8, size: u32) -> String;
+    fn unscale(&self, r: u8, size: u32) -> String;
+
+    /*
+     * control flow
+     */
+    fn jump(&self, label: u32) -> String;
+    fn branch_zero(&self, r: u8, label: u32) -> String;
+    fn branch_nonzero(&self, r: u8, label: u32) -> String;
+    fn branch_not_equal(&self, r: u8, value: i64, label: u32) -> String;
+
+    /*
+     * calls: the scratch registers live across a call are pushed before it and popped after it,
+     * the arguments are set from the first, a value is returned in a register of the target
+     */
+    fn push(&self, r: u8) -> String;
+    fn pop(&self, r: u8) -> String;
+    fn argument(&self, i: usize, r: u8) -> String;
+    fn argument_immediate(&self, i: usize, value: u64) -> String;
+    fn argument_address(&self, i: usize, symbol: &str) -> String;
+    fn call(&self, symbol: &str) -> String;
+    // a function of C, with the stack as its convention wants it
+    fn call_c(&self, name: &str, variadic: bool) -> String;
+    fn call_register(&self, r: u8) -> String;
+    // move the value returned by a call to r, extended from size bytes
+    fn result(&self, r: u8, size: u32, signed: bool) -> String;
+    fn return_value(&self, r: u8) -> String;
+    fn return_zero(&self) -> String;
+
+    /*
+     * functions: the prologue reserves frame_size bytes below the frame pointer, the parameters
+     * are copied to their slot, the epilogue returns, export is the symbol C calls for a `pub extern fn`
+     */
+    fn prologue(&self, frame_size: u32) -> String;
+    fn store_argument(&self, i: usize, offset: u32, size: u32) -> String;
+    fn epilogue(&self) -> String;
+    fn export(&self, export: &str, symbol: &str) -> String;
+    // the entry point name, with argc and argv stored in the slots at argc and argv
+    fn entry(&self, name: &str, libc: bool, frame_size: u32, argc: u32, argv: u32) -> String;
+}
+
+/*
+ * a string for .ascii, with the escapes of GNU as
+ */
+fn gnu_string(text: &str) -> String {
+    let mut data = "\"".to_string();
+    for c in text.chars() {
+        match c {
+            '\n' => data += "\\n",
+            '\t' => data += "\\t",
+            '\\' => data += "\\\\",
+            '"' => data += "\\\"",
+            '\0' => data += "\\000",
+            c => data.push(c),
+        }
+    }
+    data += "\"";
+    return data;
+}
+
+/*
+ * the prefix of the cross tools of the Linux target arch, none when this machine is one itself
+ */
+fn cross_prefix(arch: &str) -> String {
+    if std::env::consts::ARCH == arch {
+        return "".to_string();
+    }
+    return format!("{arch}-linux-gnu-");
+}
+
+// the first one is the default
+pub const TARGETS: [&dyn Target; 3] = [&X86_64, &AArch64, &RiscV64];
+
+/*
+ * the target named by --target=
+ */
+pub fn target(name: &str) -> Option<&'static dyn Target> {
+    return TARGETS.into_iter().find(|target| target.name() == name);
+}
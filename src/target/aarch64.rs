@@ -0,0 +1,463 @@
+/*
+ * AArch64 in the syntax of GNU as, for Linux and the AAPCS64 calling convention
+ * x16 and x17 (the intra-procedure-call registers) are the temporaries of the instructions,
+ * x29 is the frame pointer and x30 the link register, sp always stays aligned to 16 bytes
+ */
+use super::{cross_prefix, gnu_string, Section, Target};
+use crate::ast::{BinaryOp, UnaryOp};
+use crate::runtime::{self, Runtime};
+
+// x9 to x15 are the caller-saved registers that don't pass arguments
+const REG_NAMES: [&str; 7] = ["x9", "x10", "x11", "x12", "x13", "x14", "x15"];
+const REG_NAMES_32: [&str; 7] = ["w9", "w10", "w11", "w12", "w13", "w14", "w15"];
+
+// the registers of the first 6 arguments of a call, the value is returned in x0
+const ARG_REGS: [&str; 6] = ["x0", "x1", "x2", "x3", "x4", "x5"];
+const ARG_REGS_32: [&str; 6] = ["w0", "w1", "w2", "w3", "w4", "w5"];
+
+pub struct AArch64;
+
+fn reg(r: u8) -> &'static str {
+    REG_NAMES[r as usize]
+}
+
+fn reg_32(r: u8) -> &'static str {
+    REG_NAMES_32[r as usize]
+}
+
+fn label_ref(label: u32) -> String {
+    format!(".L{label}")
+}
+
+/*
+ * mov takes a 16 bits immediate, or the complement of one, larger values are built 16 bits at a time
+ */
+fn immediate(reg: &str, value: u64) -> String {
+    if value <= 0xffff {
+        return format!("        mov     {reg}, #{value}\n");
+    }
+    if !value <= 0xffff {
+        return format!("        mov     {reg}, #{}\n", value as i64);
+    }
+    let mut code = "".to_string();
+    for shift in [0, 16, 32, 48] {
+        let part = (value >> shift) & 0xffff;
+        if part != 0 {
+            let instruction = if code.is_empty() { "movz" } else { "movk" };
+            code += &format!("        {instruction}    {reg}, #{part}, lsl #{shift}\n");
+        }
+    }
+    return code;
+}
+
+/*
+ * dst = src + value, add and sub take a 12 bits immediate
+ */
+fn add_immediate(dst: &str, src: &str, value: i64) -> String {
+    match value {
+        0..=4095 => format!("        add     {dst}, {src}, #{value}\n"),
+        -4095..=-1 => format!("        sub     {dst}, {src}, #{}\n", -value),
+        _ => immediate("x17", value as u64) + &format!("        add     {dst}, {src}, x17\n"),
+    }
+}
+
+/*
+ * the load or store instruction of `size` bytes, and the name of the register at that size
+ */
+fn memory_instruction(load: bool, size: u32, signed: bool) -> &'static str {
+    match (load, size, signed) {
+        (true, 1, true) => "ldrsb",
+        (true, 1, false) => "ldrb",
+        (true, 2, true) => "ldrsh",
+        (true, 2, false) => "ldrh",
+        (true, 4, true) => "ldrsw",
+        (true, _, _) => "ldr",
+        (false, 1, _) => "strb",
+        (false, 2, _) => "strh",
+        (false, _, _) => "str",
+    }
+}
+
+impl Target for AArch64 {
+    fn name(&self) -> &'static str {
+        "aarch64"
+    }
+
+    fn runtime(&self) -> &'static Runtime {
+        &runtime::AARCH64
+    }
+
+    fn extension(&self) -> &'static str {
+        "s"
+    }
+
+    fn assemble(&self, source: &str, object: &str) -> Vec<String> {
+        return vec![self.tool("as"), "-o".to_string(), object.to_string(), source.to_string()];
+    }
+
+    fn tool(&self, name: &str) -> String {
+        let name = if name == "cc" { "gcc" } else { name };
+        format!("{}{name}", cross_prefix("aarch64"))
+    }
+
+    /*
+     * qemu in user mode, it finds the C library of the cross toolchain through QEMU_LD_PREFIX
+     */
+    fn runner(&self) -> Option<&'static str> {
+        if cross_prefix("aarch64").is_empty() { None } else { Some("qemu-aarch64") }
+    }
+
+    fn comment(&self, text: &str) -> String {
+        format!(" // {text}")
+    }
+
+    fn section(&self, section: Section) -> String {
+        match section {
+            Section::Text => "        .text\n".to_string(),
+            Section::Data => "        .data\n".to_string(),
+            Section::Rodata => "        .section .rodata\n".to_string(),
+            Section::Bss => "        .bss\n".to_string(),
+        }
+    }
+
+    fn global(&self, symbol: &str) -> String {
+        format!("        .globl  {symbol}\n")
+    }
+
+    // an undefined symbol is external for GNU as
+    fn extern_symbol(&self, _symbol: &str) -> String {
+        "".to_string()
+    }
+
+    fn align(&self, align: u32, _section: Section) -> String {
+        format!("        .balign {align}\n")
+    }
+
+    fn data(&self, size: u32, value: u64) -> String {
+        let directive = match size {
+            1 => ".byte ",
+            2 => ".2byte",
+            4 => ".4byte",
+            _ => ".8byte",
+        };
+        format!("        {directive}  0x{value:x}\n")
+    }
+
+    fn zeros(&self, size: u32) -> String {
+        format!("        .zero   {size}\n")
+    }
+
+    fn reserve(&self, size: u32) -> String {
+        format!("        .zero   {size}\n")
+    }
+
+    fn string(&self, text: &str) -> String {
+        format!("        .ascii  {}\n", gnu_string(text))
+    }
+
+    fn address(&self, symbol: &str) -> String {
+        format!("        .8byte  {symbol}\n")
+    }
+
+    fn load_immediate(&self, r: u8, value: u64) -> String {
+        immediate(reg(r), value)
+    }
+
+    fn move_register(&self, dst: u8, src: u8) -> String {
+        format!("        mov     {}, {}\n", reg(dst), reg(src))
+    }
+
+    fn frame_address(&self, r: u8, offset: u32) -> String {
+        add_immediate(reg(r), "x29", -(offset as i64))
+    }
+
+    /*
+     * the page of the symbol, then its offset in the page
+     */
+    fn symbol_address(&self, r: u8, symbol: &str) -> String {
+        format!("        adrp    {0}, {symbol}\n        add     {0}, {0}, :lo12:{symbol}\n", reg(r))
+    }
+
+    fn offset(&self, dst: u8, src: u8, offset: i64) -> String {
+        add_immediate(reg(dst), reg(src), offset)
+    }
+
+    fn load(&self, r: u8, size: u32, signed: bool) -> String {
+        // a load in a w register clears the high bits
+        let dst = if signed || size == 8 { reg(r) } else { reg_32(r) };
+        format!("        {:<7} {dst}, [{}]\n", memory_instruction(true, size, signed), reg(r))
+    }
+
+    fn store(&self, addr: u8, value: u8, size: u32) -> String {
+        let src = if size == 8 { reg(value) } else { reg_32(value) };
+        format!("        {:<7} {src}, [{}]\n", memory_instruction(false, size, false), reg(addr))
+    }
+
+    /*
+     * through x16, with x17 for the addresses out of reach of an immediate offset
+     */
+    fn copy(&self, dst: u8, src: u8, size: u32) -> String {
+        let (dst, src) = (reg(dst), reg(src));
+        let mut code = "".to_string();
+        let mut offset = 0;
+        while offset < size {
+            let chunk = match size - offset {
+                8.. => 8,
+                4..=7 => 4,
+                2..=3 => 2,
+                _ => 1,
+            };
+            let value = if chunk == 8 { "x16" } else { "w16" };
+            let (load, store) = (memory_instruction(true, chunk, false), memory_instruction(false, chunk, false));
+            if offset < 4096 {
+                code += &format!("        {load:<7} {value}, [{src}, #{offset}]\n");
+                code += &format!("        {store:<7} {value}, [{dst}, #{offset}]\n");
+            } else {
+                code += &add_immediate("x16", src, offset as i64);
+                code += &format!("        {load:<7} {value}, [x16]\n");
+                code += &add_immediate("x17", dst, offset as i64);
+                code += &format!("        {store:<7} {value}, [x17]\n");
+            }
+            offset += chunk;
+        }
+        code
+    }
+
+    fn unary(&self, op: UnaryOp, r: u8) -> String {
+        match op {
+            UnaryOp::Neg => format!("        neg     {0}, {0}\n", reg(r)),
+            UnaryOp::Not => format!("        mvn     {0}, {0}\n", reg(r)),
+        }
+    }
+
+    /*
+     * on 64 bits the flags tell if it overflowed, smulh and umulh give the high half of a product,
+     * a smaller result fits when extending its low bits gives it back
+     */
+    fn arith(&self, op: BinaryOp, left: u8, right: u8, size: u32, signed: bool, ok_label: Option<u32>) -> String {
+        let (reg_left, reg_right) = (reg(left), reg(right));
+        let mut code = "".to_string();
+        let Some(ok_label) = ok_label else {
+            let instruction = match op {
+                BinaryOp::Add => "add",
+                BinaryOp::Sub => "sub",
+                _ => "mul",
+            };
+            return format!("        {instruction:<7} {reg_left}, {reg_left}, {reg_right}\n");
+        };
+        let ok = label_ref(ok_label);
+        if size == 8 {
+            match op {
+                BinaryOp::Add | BinaryOp::Sub => {
+                    let instruction = if op == BinaryOp::Add { "adds" } else { "subs" };
+                    code += &format!("        {instruction:<7} {reg_left}, {reg_left}, {reg_right}\n");
+                    // the carry of a subtraction is set when it didn't borrow
+                    let condition = match (op, signed) {
+                        (_, true) => "vc",
+                        (BinaryOp::Add, false) => "cc",
+                        _ => "cs",
+                    };
+                    code += &format!("        b.{condition}    {ok}\n");
+                }
+                _ if signed => {
+                    code += &format!("        smulh   x16, {reg_left}, {reg_right}\n");
+                    code += &format!("        mul     {reg_left}, {reg_left}, {reg_right}\n");
+                    code += &format!("        cmp     x16, {reg_left}, asr #63\n");
+                    code += &format!("        b.eq    {ok}\n");
+                }
+                _ => {
+                    code += &format!("        umulh   x16, {reg_left}, {reg_right}\n");
+                    code += &format!("        mul     {reg_left}, {reg_left}, {reg_right}\n");
+                    code += &format!("        cbz     x16, {ok}\n");
+                }
+            }
+            return code;
+        }
+        // extended from size bytes (a literal may not be), the exact result of the operands fits in 64 bits
+        let instruction = match op {
+            BinaryOp::Add => "add",
+            BinaryOp::Sub => "sub",
+            _ => "mul",
+        };
+        code += &self.extend(left, size, signed);
+        code += &self.extend(right, size, signed);
+        code += &format!("        {instruction:<7} {reg_left}, {reg_left}, {reg_right}\n");
+        let extend = match (size, signed) {
+            (1, true) => "sxtb",
+            (1, false) => "uxtb",
+            (2, true) => "sxth",
+            (2, false) => "uxth",
+            (_, true) => "sxtw",
+            (_, false) => "uxtw",
+        };
+        code += &format!("        cmp     {reg_left}, {}, {extend}\n", reg_32(left));
+        code += &format!("        b.eq    {ok}\n");
+        return code;
+    }
+
+    fn binary(&self, op: BinaryOp, left: u8, right: u8, signed: bool) -> String {
+        let (reg_left, reg_right) = (reg(left), reg(right));
+        let division = if signed { "sdiv" } else { "udiv" };
+        match op {
+            BinaryOp::Div => format!("        {division}    {reg_left}, {reg_left}, {reg_right}\n"),
+            // the remainder is left - quotient * right
+            BinaryOp::Rem => format!("        {division}    x16, {reg_left}, {reg_right}\n        msub    {reg_left}, x16, {reg_right}, {reg_left}\n"),
+            BinaryOp::And => format!("        and     {reg_left}, {reg_left}, {reg_right}\n"),
+            BinaryOp::Or => format!("        orr     {reg_left}, {reg_left}, {reg_right}\n"),
+            BinaryOp::Xor => format!("        eor     {reg_left}, {reg_left}, {reg_right}\n"),
+            // like on x86, only the low 6 bits of the count are used
+            BinaryOp::Shl => format!("        lsl     {reg_left}, {reg_left}, {reg_right}\n"),
+            BinaryOp::Shr if signed => format!("        asr     {reg_left}, {reg_left}, {reg_right}\n"),
+            BinaryOp::Shr => format!("        lsr     {reg_left}, {reg_left}, {reg_right}\n"),
+            BinaryOp::Eq | BinaryOp::Ne | BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => {
+                let condition = match (op, signed) {
+                    (BinaryOp::Eq, _) => "eq",
+                    (BinaryOp::Ne, _) => "ne",
+                    (BinaryOp::Lt, true) => "lt",
+                    (BinaryOp::Le, true) => "le",
+                    (BinaryOp::Gt, true) => "gt",
+                    (BinaryOp::Ge, true) => "ge",
+                    (BinaryOp::Lt, false) => "lo",
+                    (BinaryOp::Le, false) => "ls",
+                    (BinaryOp::Gt, false) => "hi",
+                    (BinaryOp::Ge, false) => "hs",
+                    _ => unreachable!("not a comparison"),
+                };
+                format!("        cmp     {reg_left}, {reg_right}\n        cset    {reg_left}, {condition}\n")
+            }
+            BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul => unreachable!("`{op}` goes through arith"),
+        }
+    }
+
+    fn extend(&self, r: u8, size: u32, signed: bool) -> String {
+        // writing a w register clears the high bits
+        match (size, signed) {
+            (1, true) => format!("        sxtb    {}, {}\n", reg(r), reg_32(r)),
+            (1, false) => format!("        uxtb    {0}, {0}\n", reg_32(r)),
+            (2, true) => format!("        sxth    {}, {}\n", reg(r), reg_32(r)),
+            (2, false) => format!("        uxth    {0}, {0}\n", reg_32(r)),
+            (4, true) => format!("        sxtw    {}, {}\n", reg(r), reg_32(r)),
+            (4, false) => format!("        mov     {0}, {0}\n", reg_32(r)),
+            _ => "".to_string(),
+        }
+    }
+
+    fn scale(&self, r: u8, size: u32) -> String {
+        immediate("x16", size as u64) + &format!("        mul     {0}, {0}, x16\n", reg(r))
+    }
+
+    fn unscale(&self, r: u8, size: u32) -> String {
+        immediate("x16", size as u64) + &format!("        sdiv    {0}, {0}, x16\n", reg(r))
+    }
+
+    fn jump(&self, label: u32) -> String {
+        format!("        b       {}\n", label_ref(label))
+    }
+
+    fn branch_zero(&self, r: u8, label: u32) -> String {
+        format!("        cbz     {}, {}\n", reg(r), label_ref(label))
+    }
+
+    fn branch_nonzero(&self, r: u8, label: u32) -> String {
+        format!("        cbnz    {}, {}\n", reg(r), label_ref(label))
+    }
+
+    fn branch_not_equal(&self, r: u8, value: i64, label: u32) -> String {
+        let mut code = immediate("x16", value as u64);
+        code += &format!("        cmp     {}, x16\n", reg(r));
+        code += &format!("        b.ne    {}\n", label_ref(label));
+        return code;
+    }
+
+    // a whole 16 bytes slot each, sp stays aligned
+    fn push(&self, r: u8) -> String {
+        format!("        str     {}, [sp, #-16]!\n", reg(r))
+    }
+
+    fn pop(&self, r: u8) -> String {
+        format!("        ldr     {}, [sp], #16\n", reg(r))
+    }
+
+    fn argument(&self, i: usize, r: u8) -> String {
+        format!("        mov     {}, {}\n", ARG_REGS[i], reg(r))
+    }
+
+    fn argument_immediate(&self, i: usize, value: u64) -> String {
+        immediate(ARG_REGS[i], value)
+    }
+
+    fn argument_address(&self, i: usize, symbol: &str) -> String {
+        format!("        adrp    {0}, {symbol}\n        add     {0}, {0}, :lo12:{symbol}\n", ARG_REGS[i])
+    }
+
+    fn call(&self, symbol: &str) -> String {
+        format!("        bl      {symbol}\n")
+    }
+
+    // sp is always aligned and the variadic arguments go in registers like the others
+    fn call_c(&self, name: &str, _variadic: bool) -> String {
+        format!("        bl      {name}\n")
+    }
+
+    fn call_register(&self, r: u8) -> String {
+        format!("        blr     {}\n", reg(r))
+    }
+
+    fn result(&self, r: u8, size: u32, signed: bool) -> String {
+        format!("        mov     {}, x0\n", reg(r)) + &self.extend(r, size, signed)
+    }
+
+    fn return_value(&self, r: u8) -> String {
+        format!("        mov     x0, {}\n", reg(r))
+    }
+
+    fn return_zero(&self) -> String {
+        "        mov     x0, #0\n".to_string()
+    }
+
+    fn prologue(&self, frame_size: u32) -> String {
+        let mut code = "        stp     x29, x30, [sp, #-16]!\n".to_string();
+        code += "        mov     x29, sp\n";
+        code += &add_immediate("sp", "sp", -(frame_size as i64));
+        return code;
+    }
+
+    fn store_argument(&self, i: usize, offset: u32, size: u32) -> String {
+        let src = if size == 8 { ARG_REGS[i] } else { ARG_REGS_32[i] };
+        let mut code = add_immediate("x16", "x29", -(offset as i64));
+        code += &format!("        {:<7} {src}, [x16]\n", memory_instruction(false, size, false));
+        return code;
+    }
+
+    fn epilogue(&self) -> String {
+        let mut code = "        mov     sp, x29\n".to_string();
+        code += "        ldp     x29, x30, [sp], #16\n";
+        code += "        ret\n";
+        return code;
+    }
+
+    /*
+     * the scratch registers and the temporaries are caller-saved in C too, the function is called as it is
+     */
+    fn export(&self, export: &str, symbol: &str) -> String {
+        format!("\n        .globl  {export}\n{export}:\n        b       {symbol}\n")
+    }
+
+    /*
+     * _start finds argc at the top of the stack the kernel gave it and argv just above,
+     * the `main` of C gets them in x0 and x1
+     */
+    fn entry(&self, name: &str, libc: bool, frame_size: u32, argc: u32, argv: u32) -> String {
+        let mut code = format!("        .globl  {name}\n{name}:\n");
+        if !libc {
+            code += "        ldr     x0, [sp]\n";
+            code += "        add     x1, sp, #8\n";
+        }
+        code += &self.prologue(frame_size);
+        code += &add_immediate("x16", "x29", -(argc as i64));
+        code += "        str     x0, [x16]\n";
+        code += &add_immediate("x16", "x29", -(argv as i64));
+        code += "        str     x1, [x16]\n";
+        return code;
+    }
+}
@@ -0,0 +1,443 @@
+/*
+ * RV64GC in the syntax of GNU as, for Linux and the standard psABI calling convention
+ * a6 and a7 (arguments the calls never use) are the temporaries of the instructions,
+ * s0 is the frame pointer and ra the return address, sp always stays aligned to 16 bytes
+ * there are no flags, the overflow checks compare the result with what it should be
+ */
+use super::{cross_prefix, gnu_string, Section, Target};
+use crate::ast::{BinaryOp, UnaryOp};
+use crate::runtime::{self, Runtime};
+
+// the temporaries of the psABI
+const REG_NAMES: [&str; 7] = ["t0", "t1", "t2", "t3", "t4", "t5", "t6"];
+
+// the registers of the first 6 arguments of a call, the value is returned in a0
+const ARG_REGS: [&str; 6] = ["a0", "a1", "a2", "a3", "a4", "a5"];
+
+pub struct RiscV64;
+
+fn reg(r: u8) -> &'static str {
+    REG_NAMES[r as usize]
+}
+
+fn label_ref(label: u32) -> String {
+    format!(".L{label}")
+}
+
+/*
+ * li builds any 64 bits value
+ */
+fn immediate(reg: &str, value: u64) -> String {
+    format!("        li      {reg}, {}\n", value as i64)
+}
+
+/*
+ * dst = src + value, addi takes a 12 bits immediate
+ */
+fn add_immediate(dst: &str, src: &str, value: i64) -> String {
+    match value {
+        -2048..=2047 => format!("        addi    {dst}, {src}, {value}\n"),
+        _ => immediate("a7", value as u64) + &format!("        add     {dst}, {src}, a7\n"),
+    }
+}
+
+/*
+ * the load or store instruction of `size` bytes
+ */
+fn memory_instruction(load: bool, size: u32, signed: bool) -> &'static str {
+    match (load, size, signed) {
+        (true, 1, true) => "lb",
+        (true, 1, false) => "lbu",
+        (true, 2, true) => "lh",
+        (true, 2, false) => "lhu",
+        (true, 4, true) => "lw",
+        (true, 4, false) => "lwu",
+        (true, _, _) => "ld",
+        (false, 1, _) => "sb",
+        (false, 2, _) => "sh",
+        (false, 4, _) => "sw",
+        (false, _, _) => "sd",
+    }
+}
+
+/*
+ * dst = the low size bytes of src extended to 64 bits, the bits above are shifted out
+ */
+fn extend_to(dst: &str, src: &str, size: u32, signed: bool) -> String {
+    match (size, signed) {
+        (1, false) => format!("        andi    {dst}, {src}, 255\n"),
+        (4, true) => format!("        sext.w  {dst}, {src}\n"),
+        (1 | 2 | 4, _) => {
+            let shift = 64 - size * 8;
+            let right = if signed { "srai" } else { "srli" };
+            format!("        slli    {dst}, {src}, {shift}\n        {right:<7} {dst}, {dst}, {shift}\n")
+        }
+        _ if dst != src => format!("        mv      {dst}, {src}\n"),
+        _ => "".to_string(),
+    }
+}
+
+impl Target for RiscV64 {
+    fn name(&self) -> &'static str {
+        "riscv64"
+    }
+
+    fn runtime(&self) -> &'static Runtime {
+        &runtime::RISCV64
+    }
+
+    fn extension(&self) -> &'static str {
+        "s"
+    }
+
+    fn assemble(&self, source: &str, object: &str) -> Vec<String> {
+        return vec![self.tool("as"), "-march=rv64gc".to_string(), "-o".to_string(), object.to_string(), source.to_string()];
+    }
+
+    fn tool(&self, name: &str) -> String {
+        let name = if name == "cc" { "gcc" } else { name };
+        format!("{}{name}", cross_prefix("riscv64"))
+    }
+
+    /*
+     * qemu in user mode, it finds the C library of the cross toolchain through QEMU_LD_PREFIX
+     */
+    fn runner(&self) -> Option<&'static str> {
+        if cross_prefix("riscv64").is_empty() { None } else { Some("qemu-riscv64") }
+    }
+
+    fn comment(&self, text: &str) -> String {
+        format!(" # {text}")
+    }
+
+    fn section(&self, section: Section) -> String {
+        match section {
+            Section::Text => "        .text\n".to_string(),
+            Section::Data => "        .data\n".to_string(),
+            Section::Rodata => "        .section .rodata\n".to_string(),
+            Section::Bss => "        .bss\n".to_string(),
+        }
+    }
+
+    fn global(&self, symbol: &str) -> String {
+        format!("        .globl  {symbol}\n")
+    }
+
+    // an undefined symbol is external for GNU as
+    fn extern_symbol(&self, _symbol: &str) -> String {
+        "".to_string()
+    }
+
+    fn align(&self, align: u32, _section: Section) -> String {
+        format!("        .balign {align}\n")
+    }
+
+    fn data(&self, size: u32, value: u64) -> String {
+        let directive = match size {
+            1 => ".byte ",
+            2 => ".2byte",
+            4 => ".4byte",
+            _ => ".8byte",
+        };
+        format!("        {directive}  0x{value:x}\n")
+    }
+
+    fn zeros(&self, size: u32) -> String {
+        format!("        .zero   {size}\n")
+    }
+
+    fn reserve(&self, size: u32) -> String {
+        format!("        .zero   {size}\n")
+    }
+
+    fn string(&self, text: &str) -> String {
+        format!("        .ascii  {}\n", gnu_string(text))
+    }
+
+    fn address(&self, symbol: &str) -> String {
+        format!("        .8byte  {symbol}\n")
+    }
+
+    fn load_immediate(&self, r: u8, value: u64) -> String {
+        immediate(reg(r), value)
+    }
+
+    fn move_register(&self, dst: u8, src: u8) -> String {
+        format!("        mv      {}, {}\n", reg(dst), reg(src))
+    }
+
+    fn frame_address(&self, r: u8, offset: u32) -> String {
+        add_immediate(reg(r), "s0", -(offset as i64))
+    }
+
+    // relative to pc, the symbols of the program are never out of reach
+    fn symbol_address(&self, r: u8, symbol: &str) -> String {
+        format!("        lla     {}, {symbol}\n", reg(r))
+    }
+
+    fn offset(&self, dst: u8, src: u8, offset: i64) -> String {
+        add_immediate(reg(dst), reg(src), offset)
+    }
+
+    fn load(&self, r: u8, size: u32, signed: bool) -> String {
+        format!("        {:<7} {1}, 0({1})\n", memory_instruction(true, size, signed), reg(r))
+    }
+
+    fn store(&self, addr: u8, value: u8, size: u32) -> String {
+        format!("        {:<7} {}, 0({})\n", memory_instruction(false, size, false), reg(value), reg(addr))
+    }
+
+    /*
+     * through a6, with a7 for the addresses out of reach of an immediate offset
+     */
+    fn copy(&self, dst: u8, src: u8, size: u32) -> String {
+        let (dst, src) = (reg(dst), reg(src));
+        let mut code = "".to_string();
+        let mut offset = 0;
+        while offset < size {
+            let chunk = match size - offset {
+                8.. => 8,
+                4..=7 => 4,
+                2..=3 => 2,
+                _ => 1,
+            };
+            let (load, store) = (memory_instruction(true, chunk, false), memory_instruction(false, chunk, false));
+            if offset < 2048 {
+                code += &format!("        {load:<7} a6, {offset}({src})\n");
+                code += &format!("        {store:<7} a6, {offset}({dst})\n");
+            } else {
+                code += &add_immediate("a6", src, offset as i64);
+                code += &format!("        {load:<7} a6, 0(a6)\n");
+                code += &add_immediate("a7", dst, offset as i64);
+                code += &format!("        {store:<7} a6, 0(a7)\n");
+            }
+            offset += chunk;
+        }
+        code
+    }
+
+    fn unary(&self, op: UnaryOp, r: u8) -> String {
+        match op {
+            UnaryOp::Neg => format!("        neg     {0}, {0}\n", reg(r)),
+            UnaryOp::Not => format!("        not     {0}, {0}\n", reg(r)),
+        }
+    }
+
+    /*
+     * a signed sum is smaller than left exactly when right is negative, unless it overflowed,
+     * mulh and mulhu give the high half of a product, a smaller result fits when extending its low bits gives it back
+     */
+    fn arith(&self, op: BinaryOp, left: u8, right: u8, size: u32, signed: bool, ok_label: Option<u32>) -> String {
+        let (reg_left, reg_right) = (reg(left), reg(right));
+        let instruction = match op {
+            BinaryOp::Add => "add",
+            BinaryOp::Sub => "sub",
+            _ => "mul",
+        };
+        let mut code = "".to_string();
+        let Some(ok_label) = ok_label else {
+            return format!("        {instruction:<7} {reg_left}, {reg_left}, {reg_right}\n");
+        };
+        let ok = label_ref(ok_label);
+        if size == 8 {
+            match (op, signed) {
+                (BinaryOp::Add | BinaryOp::Sub, true) => {
+                    code += &format!("        {instruction:<7} a6, {reg_left}, {reg_right}\n");
+                    code += &format!("        slt     a7, a6, {reg_left}\n");
+                    code += &format!("        mv      {reg_left}, a6\n");
+                    // a difference is smaller than left exactly when right is positive
+                    let sign = if op == BinaryOp::Add { "sltz" } else { "sgtz" };
+                    code += &format!("        {sign:<7} a6, {reg_right}\n");
+                    code += &format!("        beq     a6, a7, {ok}\n");
+                }
+                (BinaryOp::Add, false) => {
+                    code += &format!("        add     {reg_left}, {reg_left}, {reg_right}\n");
+                    code += &format!("        bgeu    {reg_left}, {reg_right}, {ok}\n");
+                }
+                (BinaryOp::Sub, false) => {
+                    code += &format!("        sltu    a6, {reg_left}, {reg_right}\n");
+                    code += &format!("        sub     {reg_left}, {reg_left}, {reg_right}\n");
+                    code += &format!("        beqz    a6, {ok}\n");
+                }
+                (_, true) => {
+                    code += &format!("        mulh    a6, {reg_left}, {reg_right}\n");
+                    code += &format!("        mul     {reg_left}, {reg_left}, {reg_right}\n");
+                    code += &format!("        srai    a7, {reg_left}, 63\n");
+                    code += &format!("        beq     a6, a7, {ok}\n");
+                }
+                (_, false) => {
+                    code += &format!("        mulhu   a6, {reg_left}, {reg_right}\n");
+                    code += &format!("        mul     {reg_left}, {reg_left}, {reg_right}\n");
+                    code += &format!("        beqz    a6, {ok}\n");
+                }
+            }
+            return code;
+        }
+        // extended from size bytes (a literal may not be), the exact result of the operands fits in 64 bits
+        code += &self.extend(left, size, signed);
+        code += &self.extend(right, size, signed);
+        code += &format!("        {instruction:<7} {reg_left}, {reg_left}, {reg_right}\n");
+        code += &extend_to("a6", reg_left, size, signed);
+        code += &format!("        beq     {reg_left}, a6, {ok}\n");
+        return code;
+    }
+
+    fn binary(&self, op: BinaryOp, left: u8, right: u8, signed: bool) -> String {
+        let (reg_left, reg_right) = (reg(left), reg(right));
+        let suffix = if signed { "" } else { "u" };
+        match op {
+            BinaryOp::Div => format!("        div{suffix:<4} {reg_left}, {reg_left}, {reg_right}\n"),
+            BinaryOp::Rem => format!("        rem{suffix:<4} {reg_left}, {reg_left}, {reg_right}\n"),
+            BinaryOp::And => format!("        and     {reg_left}, {reg_left}, {reg_right}\n"),
+            BinaryOp::Or => format!("        or      {reg_left}, {reg_left}, {reg_right}\n"),
+            BinaryOp::Xor => format!("        xor     {reg_left}, {reg_left}, {reg_right}\n"),
+            // like on x86, only the low 6 bits of the count are used
+            BinaryOp::Shl => format!("        sll     {reg_left}, {reg_left}, {reg_right}\n"),
+            BinaryOp::Shr if signed => format!("        sra     {reg_left}, {reg_left}, {reg_right}\n"),
+            BinaryOp::Shr => format!("        srl     {reg_left}, {reg_left}, {reg_right}\n"),
+            BinaryOp::Eq => format!("        xor     {0}, {0}, {reg_right}\n        seqz    {0}, {0}\n", reg_left),
+            BinaryOp::Ne => format!("        xor     {0}, {0}, {reg_right}\n        snez    {0}, {0}\n", reg_left),
+            // there is only `<`, the others swap its operands or negate it
+            BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => {
+                let (first, second) = match op {
+                    BinaryOp::Lt | BinaryOp::Ge => (reg_left, reg_right),
+                    _ => (reg_right, reg_left),
+                };
+                let mut code = format!("        slt{suffix:<4} {reg_left}, {first}, {second}\n");
+                if op == BinaryOp::Le || op == BinaryOp::Ge {
+                    code += &format!("        xori    {0}, {0}, 1\n", reg_left);
+                }
+                code
+            }
+            BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul => unreachable!("`{op}` goes through arith"),
+        }
+    }
+
+    fn extend(&self, r: u8, size: u32, signed: bool) -> String {
+        extend_to(reg(r), reg(r), size, signed)
+    }
+
+    fn scale(&self, r: u8, size: u32) -> String {
+        immediate("a6", size as u64) + &format!("        mul     {0}, {0}, a6\n", reg(r))
+    }
+
+    fn unscale(&self, r: u8, size: u32) -> String {
+        immediate("a6", size as u64) + &format!("        div     {0}, {0}, a6\n", reg(r))
+    }
+
+    fn jump(&self, label: u32) -> String {
+        format!("        j       {}\n", label_ref(label))
+    }
+
+    fn branch_zero(&self, r: u8, label: u32) -> String {
+        format!("        beqz    {}, {}\n", reg(r), label_ref(label))
+    }
+
+    fn branch_nonzero(&self, r: u8, label: u32) -> String {
+        format!("        bnez    {}, {}\n", reg(r), label_ref(label))
+    }
+
+    fn branch_not_equal(&self, r: u8, value: i64, label: u32) -> String {
+        let mut code = immediate("a6", value as u64);
+        code += &format!("        bne     {}, a6, {}\n", reg(r), label_ref(label));
+        return code;
+    }
+
+    // a whole 16 bytes slot each, sp stays aligned
+    fn push(&self, r: u8) -> String {
+        format!("        addi    sp, sp, -16\n        sd      {}, 0(sp)\n", reg(r))
+    }
+
+    fn pop(&self, r: u8) -> String {
+        format!("        ld      {}, 0(sp)\n        addi    sp, sp, 16\n", reg(r))
+    }
+
+    fn argument(&self, i: usize, r: u8) -> String {
+        format!("        mv      {}, {}\n", ARG_REGS[i], reg(r))
+    }
+
+    fn argument_immediate(&self, i: usize, value: u64) -> String {
+        immediate(ARG_REGS[i], value)
+    }
+
+    fn argument_address(&self, i: usize, symbol: &str) -> String {
+        format!("        lla     {}, {symbol}\n", ARG_REGS[i])
+    }
+
+    fn call(&self, symbol: &str) -> String {
+        format!("        call    {symbol}\n")
+    }
+
+    // sp is always aligned and the variadic integers go in registers like the others
+    fn call_c(&self, name: &str, _variadic: bool) -> String {
+        format!("        call    {name}\n")
+    }
+
+    fn call_register(&self, r: u8) -> String {
+        format!("        jalr    {}\n", reg(r))
+    }
+
+    fn result(&self, r: u8, size: u32, signed: bool) -> String {
+        extend_to(reg(r), "a0", size, signed)
+    }
+
+    fn return_value(&self, r: u8) -> String {
+        format!("        mv      a0, {}\n", reg(r))
+    }
+
+    fn return_zero(&self) -> String {
+        "        li      a0, 0\n".to_string()
+    }
+
+    fn prologue(&self, frame_size: u32) -> String {
+        let mut code = "        addi    sp, sp, -16\n".to_string();
+        code += "        sd      ra, 8(sp)\n";
+        code += "        sd      s0, 0(sp)\n";
+        code += "        mv      s0, sp\n";
+        code += &add_immediate("sp", "sp", -(frame_size as i64));
+        return code;
+    }
+
+    fn store_argument(&self, i: usize, offset: u32, size: u32) -> String {
+        let mut code = add_immediate("a7", "s0", -(offset as i64));
+        code += &format!("        {:<7} {}, 0(a7)\n", memory_instruction(false, size, false), ARG_REGS[i]);
+        return code;
+    }
+
+    fn epilogue(&self) -> String {
+        let mut code = "        mv      sp, s0\n".to_string();
+        code += "        ld      ra, 8(sp)\n";
+        code += "        ld      s0, 0(sp)\n";
+        code += "        addi    sp, sp, 16\n";
+        code += "        ret\n";
+        return code;
+    }
+
+    /*
+     * the scratch registers and the temporaries are caller-saved in C too, the function is called as it is
+     */
+    fn export(&self, export: &str, symbol: &str) -> String {
+        format!("\n        .globl  {export}\n{export}:\n        tail    {symbol}\n")
+    }
+
+    /*
+     * _start finds argc at the top of the stack the kernel gave it and argv just above, and sets gp
+     * like the start files of C do, the linker may relax the addresses of symbols near it to gp + offset
+     * the `main` of C gets them in a0 and a1
+     */
+    fn entry(&self, name: &str, libc: bool, frame_size: u32, argc: u32, argv: u32) -> String {
+        let mut code = format!("        .globl  {name}\n{name}:\n");
+        if !libc {
+            code += "        .option push\n";
+            code += "        .option norelax\n";
+            code += "        lla     gp, __global_pointer$\n";
+            code += "        .option pop\n";
+            code += "        ld      a0, 0(sp)\n";
+            code += "        addi    a1, sp, 8\n";
+        }
+        code += &self.prologue(frame_size);
+        code += &self.store_argument(0, argc, 8);
+        code += &self.store_argument(1, argv, 8);
+        return code;
+    }
+}
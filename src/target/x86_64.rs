@@ -0,0 +1,487 @@
+/*
+ * x86-64 in the syntax of NASM, for Linux and the System V calling convention
+ * rax, rcx and rdx are the temporaries of the instructions that need fixed registers (mul, div, shifts, setcc)
+ */
+use super::{Section, Target};
+use crate::ast::{BinaryOp, UnaryOp};
+use crate::runtime::{self, Runtime};
+
+const REG_NAMES: [&str; 7] = ["rbx", "r10", "r11","r12", "r13", "r14", "r15"];
+const REG_NAMES_32: [&str; 7] = ["ebx", "r10d", "r11d", "r12d", "r13d", "r14d", "r15d"];
+const REG_NAMES_16: [&str; 7] = ["bx", "r10w", "r11w", "r12w", "r13w", "r14w", "r15w"];
+const REG_NAMES_8: [&str; 7] = ["bl", "r10b", "r11b", "r12b", "r13b", "r14b", "r15b"];
+
+// the registers of the first 6 arguments of a call, in the System V order
+const ARG_REGS: [&str; 6] = ["rdi", "rsi", "rdx", "rcx", "r8", "r9"];
+const ARG_REGS_32: [&str; 6] = ["edi", "esi", "edx", "ecx", "r8d", "r9d"];
+const ARG_REGS_16: [&str; 6] = ["di", "si", "dx", "cx", "r8w", "r9w"];
+const ARG_REGS_8: [&str; 6] = ["dil", "sil", "dl", "cl", "r8b", "r9b"];
+
+// the scratch registers a C function keeps for its caller
+const CALLEE_SAVED: [&str; 5] = ["rbx", "r12", "r13", "r14", "r15"];
+
+pub struct X86_64;
+
+fn reg(r: u8) -> &'static str {
+    REG_NAMES[r as usize]
+}
+
+/*
+ * the name of the lowest `size` bytes of the register
+ */
+fn sized(r: u8, size: u32) -> &'static str {
+    match size {
+        1 => REG_NAMES_8[r as usize],
+        2 => REG_NAMES_16[r as usize],
+        4 => REG_NAMES_32[r as usize],
+        _ => REG_NAMES[r as usize],
+    }
+}
+
+/*
+ * NASM keyword for a memory operand of `size` bytes
+ */
+fn size_keyword(size: u32) -> &'static str {
+    match size {
+        1 => "BYTE",
+        2 => "WORD",
+        4 => "DWORD",
+        _ => "QWORD",
+    }
+}
+
+/*
+ * NASM directive for `size` bytes of data
+ */
+fn data_keyword(size: u32) -> &'static str {
+    match size {
+        1 => "db",
+        2 => "dw",
+        4 => "dd",
+        _ => "dq",
+    }
+}
+
+/*
+ * a NASM string for db, backquoted so that escapes work
+ */
+fn string_data(text: &str) -> String {
+    let mut data = "`".to_string();
+    for c in text.chars() {
+        match c {
+            '\n' => data += "\\n",
+            '\t' => data += "\\t",
+            '\\' => data += "\\\\",
+            '\0' => data += "\\0",
+            '`' => data += "\\`",
+            c => data.push(c),
+        }
+    }
+    data += "`";
+    return data;
+}
+
+fn label_ref(label: u32) -> String {
+    format!(".L{label}")
+}
+
+impl Target for X86_64 {
+    fn name(&self) -> &'static str {
+        "x86_64"
+    }
+
+    fn runtime(&self) -> &'static Runtime {
+        &runtime::X86_64
+    }
+
+    fn extension(&self) -> &'static str {
+        "asm"
+    }
+
+    fn assemble(&self, source: &str, object: &str) -> Vec<String> {
+        return ["nasm", "-f", "elf64", "-o", object, source].map(String::from).to_vec();
+    }
+
+    fn tool(&self, name: &str) -> String {
+        name.to_string()
+    }
+
+    fn runner(&self) -> Option<&'static str> {
+        None
+    }
+
+    fn comment(&self, text: &str) -> String {
+        format!(" ; {text}")
+    }
+
+    fn section(&self, section: Section) -> String {
+        match section {
+            Section::Text => "segment .text\n".to_string(),
+            Section::Data => "segment .data\n".to_string(),
+            Section::Rodata => "segment .rodata\n".to_string(),
+            Section::Bss => "segment .bss\n".to_string(),
+        }
+    }
+
+    fn global(&self, symbol: &str) -> String {
+        format!("global {symbol}\n")
+    }
+
+    fn extern_symbol(&self, symbol: &str) -> String {
+        format!("extern {symbol}\n")
+    }
+
+    fn align(&self, align: u32, section: Section) -> String {
+        match section {
+            Section::Bss => format!("        alignb {align}\n"),
+            _ => format!("        align  {align}, db 0\n"),
+        }
+    }
+
+    fn data(&self, size: u32, value: u64) -> String {
+        format!("        {}     0x{value:x}\n", data_keyword(size))
+    }
+
+    fn zeros(&self, size: u32) -> String {
+        format!("        times {size} db 0\n")
+    }
+
+    fn reserve(&self, size: u32) -> String {
+        format!("        resb   {size}\n")
+    }
+
+    fn string(&self, text: &str) -> String {
+        format!("        db     {}\n", string_data(text))
+    }
+
+    fn address(&self, symbol: &str) -> String {
+        format!("        dq     {symbol}\n")
+    }
+
+    fn load_immediate(&self, r: u8, value: u64) -> String {
+        format!("        mov    {}, {value}\n", reg(r))
+    }
+
+    fn move_register(&self, dst: u8, src: u8) -> String {
+        format!("        mov    {}, {}\n", reg(dst), reg(src))
+    }
+
+    fn frame_address(&self, r: u8, offset: u32) -> String {
+        format!("        lea    {}, [rbp-{offset}]\n", reg(r))
+    }
+
+    fn symbol_address(&self, r: u8, symbol: &str) -> String {
+        format!("        lea    {}, [rel {symbol}]\n", reg(r))
+    }
+
+    fn offset(&self, dst: u8, src: u8, offset: i64) -> String {
+        if dst == src {
+            return format!("        add    {}, {offset}\n", reg(dst));
+        }
+        return format!("        lea    {}, [{}{offset:+}]\n", reg(dst), reg(src));
+    }
+
+    fn load(&self, r: u8, size: u32, signed: bool) -> String {
+        let reg = reg(r);
+        match (size, signed) {
+            (4, true) => format!("        movsxd {reg}, DWORD [{reg}]\n"),
+            (4, false) => format!("        mov    {}, DWORD [{reg}]\n", sized(r, 4)),
+            (1 | 2, true) => format!("        movsx  {reg}, {} [{reg}]\n", size_keyword(size)),
+            (1 | 2, false) => format!("        movzx  {reg}, {} [{reg}]\n", size_keyword(size)),
+            _ => format!("        mov    {reg}, QWORD [{reg}]\n"),
+        }
+    }
+
+    fn store(&self, addr: u8, value: u8, size: u32) -> String {
+        format!("        mov    {} [{}], {}\n", size_keyword(size), reg(addr), sized(value, size))
+    }
+
+    /*
+     * through rax
+     */
+    fn copy(&self, dst: u8, src: u8, size: u32) -> String {
+        let (dst, src) = (reg(dst), reg(src));
+        let mut code = "".to_string();
+        let mut offset = 0;
+        while offset < size {
+            let (chunk, rax) = match size - offset {
+                8.. => (8, "rax"),
+                4..=7 => (4, "eax"),
+                2..=3 => (2, "ax"),
+                _ => (1, "al"),
+            };
+            let keyword = size_keyword(chunk);
+            code += &format!("        mov    {rax}, {keyword} [{src}+{offset}]\n");
+            code += &format!("        mov    {keyword} [{dst}+{offset}], {rax}\n");
+            offset += chunk;
+        }
+        code
+    }
+
+    fn unary(&self, op: UnaryOp, r: u8) -> String {
+        match op {
+            UnaryOp::Neg => format!("        neg    {}\n", reg(r)),
+            UnaryOp::Not => format!("        not    {}\n", reg(r)),
+        }
+    }
+
+    /*
+     * computed at the size of the type, so that the flags tell if it overflowed
+     */
+    fn arith(&self, op: BinaryOp, left: u8, right: u8, size: u32, signed: bool, ok_label: Option<u32>) -> String {
+        let mut code = "".to_string();
+        let (sized_left, sized_right) = (sized(left, size), sized(right, size));
+        let jump = match op {
+            BinaryOp::Add => {
+                code += &format!("        add    {sized_left}, {sized_right}\n");
+                if signed { "jno" } else { "jnc" }
+            }
+            BinaryOp::Sub => {
+                code += &format!("        sub    {sized_left}, {sized_right}\n");
+                if signed { "jno" } else { "jnc" }
+            }
+            _ => {
+                let instruction = if signed { "imul" } else { "mul" };
+                code += &format!("        mov    rax, {}\n", reg(left));
+                code += &format!("        {instruction:<6} {sized_right}\n");
+                code += &format!("        mov    {}, rax\n", reg(left));
+                "jno"
+            }
+        };
+        if let Some(ok_label) = ok_label {
+            code += &format!("        {jump:<6} {}\n", label_ref(ok_label));
+        }
+        return code;
+    }
+
+    fn binary(&self, op: BinaryOp, left: u8, right: u8, signed: bool) -> String {
+        let mut code = "".to_string();
+        let (reg_left, reg_right) = (reg(left), reg(right));
+        match op {
+            BinaryOp::Div | BinaryOp::Rem => {
+                // the quotient is left in rax and the remainder in rdx
+                code += &format!("        mov    rax, {reg_left}\n");
+                if signed {
+                    code += "        cqo\n";
+                    code += &format!("        idiv   {reg_right}\n");
+                } else {
+                    code += "        mov    rdx, 0\n";
+                    code += &format!("        div    {reg_right}\n");
+                }
+                let result = if op == BinaryOp::Div { "rax" } else { "rdx" };
+                code += &format!("        mov    {reg_left}, {result}\n");
+            }
+            BinaryOp::And => {
+                code += &format!("        and    {reg_left}, {reg_right}\n");
+            }
+            BinaryOp::Or => {
+                code += &format!("        or     {reg_left}, {reg_right}\n");
+            }
+            BinaryOp::Xor => {
+                code += &format!("        xor    {reg_left}, {reg_right}\n");
+            }
+            BinaryOp::Shl | BinaryOp::Shr => {
+                // the shift count has to be in cl
+                let instruction = match op {
+                    BinaryOp::Shl => "shl",
+                    _ if signed => "sar",
+                    _ => "shr",
+                };
+                code += &format!("        mov    rcx, {reg_right}\n");
+                code += &format!("        {instruction}    {reg_left}, cl\n");
+            }
+            BinaryOp::Eq | BinaryOp::Ne | BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => {
+                let condition = match (op, signed) {
+                    (BinaryOp::Eq, _) => "e",
+                    (BinaryOp::Ne, _) => "ne",
+                    (BinaryOp::Lt, true) => "l",
+                    (BinaryOp::Le, true) => "le",
+                    (BinaryOp::Gt, true) => "g",
+                    (BinaryOp::Ge, true) => "ge",
+                    (BinaryOp::Lt, false) => "b",
+                    (BinaryOp::Le, false) => "be",
+                    (BinaryOp::Gt, false) => "a",
+                    (BinaryOp::Ge, false) => "ae",
+                    _ => unreachable!("not a comparison"),
+                };
+                code += &format!("        cmp    {reg_left}, {reg_right}\n");
+                code += &format!("        set{condition}   al\n");
+                code += &format!("        movzx  {reg_left}, al\n");
+            }
+            BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul => unreachable!("`{op}` goes through arith"),
+        }
+        return code;
+    }
+
+    fn extend(&self, r: u8, size: u32, signed: bool) -> String {
+        match (size, signed) {
+            (4, true) => format!("        movsxd {}, {}\n", reg(r), sized(r, 4)),
+            (4, false) => format!("        mov    {0}, {0}\n", sized(r, 4)),
+            (1 | 2, true) => format!("        movsx  {}, {}\n", reg(r), sized(r, size)),
+            (1 | 2, false) => format!("        movzx  {}, {}\n", reg(r), sized(r, size)),
+            _ => "".to_string(),
+        }
+    }
+
+    fn scale(&self, r: u8, size: u32) -> String {
+        format!("        imul   {0}, {0}, {size}\n", reg(r))
+    }
+
+    fn unscale(&self, r: u8, size: u32) -> String {
+        let mut code = format!("        mov    rax, {}\n", reg(r));
+        code += "        cqo\n";
+        code += &format!("        mov    rcx, {size}\n");
+        code += "        idiv   rcx\n";
+        code += &format!("        mov    {}, rax\n", reg(r));
+        return code;
+    }
+
+    fn jump(&self, label: u32) -> String {
+        format!("        jmp    {}\n", label_ref(label))
+    }
+
+    fn branch_zero(&self, r: u8, label: u32) -> String {
+        format!("        test   {0}, {0}\n        jz     {1}\n", reg(r), label_ref(label))
+    }
+
+    fn branch_nonzero(&self, r: u8, label: u32) -> String {
+        format!("        test   {0}, {0}\n        jnz    {1}\n", reg(r), label_ref(label))
+    }
+
+    fn branch_not_equal(&self, r: u8, value: i64, label: u32) -> String {
+        // cmp only takes a 32 bits immediate
+        let mut code = if i32::try_from(value).is_ok() {
+            format!("        cmp    {}, {value}\n", reg(r))
+        } else {
+            format!("        mov    rax, 0x{:x}\n        cmp    {}, rax\n", value as u64, reg(r))
+        };
+        code += &format!("        jne    {}\n", label_ref(label));
+        return code;
+    }
+
+    fn push(&self, r: u8) -> String {
+        format!("        push   {}\n", reg(r))
+    }
+
+    fn pop(&self, r: u8) -> String {
+        format!("        pop    {}\n", reg(r))
+    }
+
+    fn argument(&self, i: usize, r: u8) -> String {
+        format!("        mov    {}, {}\n", ARG_REGS[i], reg(r))
+    }
+
+    fn argument_immediate(&self, i: usize, value: u64) -> String {
+        format!("        mov    {}, {value}\n", ARG_REGS[i])
+    }
+
+    fn argument_address(&self, i: usize, symbol: &str) -> String {
+        format!("        lea    {}, [rel {symbol}]\n", ARG_REGS[i])
+    }
+
+    fn call(&self, symbol: &str) -> String {
+        format!("        call   {symbol}\n")
+    }
+
+    /*
+     * rsp aligned to 16 bytes, and al = 0 (no vector register) when it is variadic
+     */
+    fn call_c(&self, name: &str, variadic: bool) -> String {
+        // the rsp of before is pushed on an aligned stack, and popped back after the call
+        let mut code = "        mov    rax, rsp\n".to_string();
+        code += "        and    rsp, -16\n";
+        code += "        sub    rsp, 8\n";
+        code += "        push   rax\n";
+        if variadic {
+            code += "        mov    eax, 0\n";
+        }
+        // through the PLT, the function can be in a shared library
+        code += &format!("        call   {name} wrt ..plt\n");
+        code += "        pop    rsp\n";
+        return code;
+    }
+
+    fn call_register(&self, r: u8) -> String {
+        format!("        call   {}\n", reg(r))
+    }
+
+    fn result(&self, r: u8, size: u32, signed: bool) -> String {
+        let reg = reg(r);
+        match (size, signed) {
+            (4, true) => format!("        movsxd {reg}, eax\n"),
+            (4, false) => format!("        mov    {}, eax\n", sized(r, 4)),
+            (2, true) => format!("        movsx  {reg}, ax\n"),
+            (2, false) => format!("        movzx  {reg}, ax\n"),
+            (1, true) => format!("        movsx  {reg}, al\n"),
+            (1, false) => format!("        movzx  {reg}, al\n"),
+            _ => format!("        mov    {reg}, rax\n"),
+        }
+    }
+
+    fn return_value(&self, r: u8) -> String {
+        format!("        mov    rax, {}\n", reg(r))
+    }
+
+    fn return_zero(&self) -> String {
+        "        mov    rax, 0\n".to_string()
+    }
+
+    fn prologue(&self, frame_size: u32) -> String {
+        let mut code = "        push   rbp\n".to_string();
+        code += "        mov    rbp, rsp\n";
+        code += &format!("        sub    rsp, {frame_size}\n");
+        return code;
+    }
+
+    fn store_argument(&self, i: usize, offset: u32, size: u32) -> String {
+        let reg = match size {
+            1 => ARG_REGS_8[i],
+            2 => ARG_REGS_16[i],
+            4 => ARG_REGS_32[i],
+            _ => ARG_REGS[i],
+        };
+        format!("        mov    {} [rbp-{offset}], {reg}\n", size_keyword(size))
+    }
+
+    fn epilogue(&self) -> String {
+        let mut code = "        mov    rsp, rbp\n".to_string();
+        code += "        pop    rbp\n";
+        code += "        ret\n";
+        return code;
+    }
+
+    /*
+     * rbx and r12 to r15 are scratch registers here but the callee saves them in C
+     */
+    fn export(&self, export: &str, symbol: &str) -> String {
+        let mut code = format!("\nglobal {export}\n{export}:\n");
+        for reg in CALLEE_SAVED {
+            code += &format!("        push   {reg}\n");
+        }
+        code += &format!("        call   {symbol}\n");
+        for reg in CALLEE_SAVED.iter().rev() {
+            code += &format!("        pop    {reg}\n");
+        }
+        code += "        ret\n";
+        return code;
+    }
+
+    /*
+     * _start finds argc at the top of the stack the kernel gave it and argv just above,
+     * the `main` of C gets them in rdi and rsi
+     */
+    fn entry(&self, name: &str, libc: bool, frame_size: u32, argc: u32, argv: u32) -> String {
+        let mut code = format!("global {name}\n{name}:\n");
+        code += &self.prologue(frame_size);
+        if libc {
+            code += &format!("        mov    QWORD [rbp-{argc}], rdi\n");
+            code += &format!("        mov    QWORD [rbp-{argv}], rsi\n");
+        } else {
+            code += "        mov    rax, QWORD [rbp+8]\n";
+            code += &format!("        mov    QWORD [rbp-{argc}], rax\n");
+            code += "        lea    rax, [rbp+16]\n";
+            code += &format!("        mov    QWORD [rbp-{argv}], rax\n");
+        }
+        return code;
+    }
+}
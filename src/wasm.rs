@@ -0,0 +1,460 @@
+/*
+ * The wasm32 backend: the program is lowered from its tree by lower.rs and its operations are written as a WebAssembly
+ * module for WASI, in the text format (.wat) then in the binary one (.wasm) by the assembler of binary.rs, with the
+ * runtime of src/runtime/wasm32
+ * values are i64 and a pointer holds an address of the linear memory, where the strings and the globals live
+ * the locals and the temporaries are i64 locals of the func, the frame is on a stack in the memory below the global
+ * $stack_pointer
+ */
+mod binary;
+
+pub use binary::assemble;
+
+use crate::ast::{BinaryOp, FnDecl, Program, Type};
+use crate::lower::{self, Backend, Body, Lowering};
+use crate::source::SourceMap;
+use crate::{check_tests, new_context, runtime, symbol, Context, Function, Linkage, Options};
+
+pub const TARGET: &str = "wasm32";
+
+// the memory: the runtime below STACK, the stack down from STACK + STACK_SIZE, then the strings and the globals,
+// then the heap of alloc
+const STACK: u32 = 8192;
+const STACK_SIZE: u32 = 1 << 20;
+const PAGE_SIZE: u32 = 65536;
+
+/*
+ * State of the translation: the data of the module and the temporaries and the loops of the function being written
+ * a value is a local, named by its `$`, or the instructions pushing it
+ */
+struct Wasm {
+    // the bytes put in the memory before the program starts, at their address, and where the next ones go
+    data: Vec<(u32, Vec<u8>)>,
+    data_end: u32,
+    // the temporaries in use, how many were when each statement being translated started, and the most ever used
+    temporaries: u32,
+    statements: Vec<u32>,
+    temporaries_used: u32,
+    // the number of loops, to name their blocks, and the ones the code being written is in
+    loops: u32,
+    loop_blocks: Vec<u32>,
+}
+
+impl Wasm {
+    /*
+     * bytes in the memory aligned on align, return their address
+     */
+    fn data(&mut self, bytes: Vec<u8>, align: u32) -> u32 {
+        let address = self.reserve(bytes.len() as u32, align);
+        self.data.push((address, bytes));
+        return address;
+    }
+
+    /*
+     * size bytes of the memory aligned on align, zeroed when the program starts
+     */
+    fn reserve(&mut self, size: u32, align: u32) -> u32 {
+        let address = self.data_end.div_ceil(align) * align;
+        self.data_end = address + size;
+        return address;
+    }
+
+    /*
+     * the func of a body: its locals, then the frame reserved below $stack_pointer if it has one,
+     * the body and the frame given back
+     */
+    fn func_codegen(&mut self, header: &str, locals: &[String], body: &Body, epilogue: &str) -> String {
+        let mut code = format!("  {header}\n");
+        let mut declared = "".to_string();
+        for local in locals {
+            declared += &format!("(local {local} i64) ");
+        }
+        for i in 0..self.temporaries_used {
+            declared += &format!("(local $tmp.{i} i64) ");
+        }
+        if body.frame_size.is_some() {
+            declared += "(local $frame.top i32) ";
+        }
+        if !declared.is_empty() {
+            code += &format!("    {}\n", declared.trim_end());
+        }
+        let mut instructions = "".to_string();
+        if let Some(frame_size) = body.frame_size {
+            let frame_size = frame_size.div_ceil(16) * 16;
+            instructions += &format!("global.get $stack_pointer\nlocal.tee $frame.top\ni32.const {frame_size}\ni32.sub\nglobal.set $stack_pointer\n");
+        }
+        instructions += &body.code;
+        if body.frame_size.is_some() {
+            instructions += "local.get $frame.top\nglobal.set $stack_pointer\n";
+        }
+        instructions += epilogue;
+        code += &indent(&instructions, 2);
+        code += "  )\n";
+        self.temporaries = 0;
+        self.temporaries_used = 0;
+        return code;
+    }
+
+    /*
+     * the instructions panicking, or calling a builtin, with text in the memory: its address and its length
+     */
+    fn text_arguments(&mut self, text: &str) -> String {
+        let len = text.len();
+        let address = self.data(text.as_bytes().to_vec(), 1);
+        return format!("i32.const {address}\ni32.const {len}\n");
+    }
+}
+
+/*
+ * the instructions pushing value
+ */
+fn push(value: &str) -> String {
+    if value.starts_with('$') {
+        return format!("local.get {value}\n");
+    }
+    return value.to_string();
+}
+
+impl Backend for Wasm {
+    fn constant(&self, value: u64) -> String {
+        return format!("i64.const {}\n", value as i64);
+    }
+
+    fn string(&mut self, text: &str) -> String {
+        let address = self.data(format!("{text}\0").into_bytes(), 1);
+        return format!("i64.const {address}\n");
+    }
+
+    fn frame_address(&self, offset: u32) -> String {
+        return format!("local.get $frame.top\ni32.const {offset}\ni32.sub\ni64.extend_i32_u\n");
+    }
+
+    fn global(&mut self, _name: &str, size: u32, align: u32, bytes: Option<Vec<u8>>, _location: &str) -> String {
+        let address = match bytes {
+            Some(bytes) => self.data(bytes, align),
+            None => self.reserve(size, align),
+        };
+        return format!("i64.const {address}\n");
+    }
+
+    fn temporary(&mut self) -> String {
+        let i = self.temporaries;
+        self.temporaries += 1;
+        self.temporaries_used = self.temporaries_used.max(self.temporaries);
+        return format!("$tmp.{i}");
+    }
+
+    /*
+     * `$name`, then `$name#2`, `$name#3`, ...
+     * a name of the program has no `.`, the locals of the translation have one
+     */
+    fn local(&self, name: &str, i: u32) -> String {
+        if i == 1 {
+            return format!("${name}");
+        }
+        return format!("${name}#{i}");
+    }
+
+    fn set(&mut self, destination: &str, value: &str) -> String {
+        return format!("{}local.set {destination}\n", push(value));
+    }
+
+    fn operation(&mut self, destination: &str, op: BinaryOp, a: &str, b: &str, signed: bool) -> String {
+        let s = if signed { "s" } else { "u" };
+        let instruction = match op {
+            BinaryOp::Add => "i64.add".to_string(),
+            BinaryOp::Sub => "i64.sub".to_string(),
+            BinaryOp::Mul => "i64.mul".to_string(),
+            BinaryOp::Div => format!("i64.div_{s}"),
+            BinaryOp::Rem => format!("i64.rem_{s}"),
+            BinaryOp::And => "i64.and".to_string(),
+            BinaryOp::Or => "i64.or".to_string(),
+            BinaryOp::Xor => "i64.xor".to_string(),
+            BinaryOp::Shl => "i64.shl".to_string(),
+            BinaryOp::Shr => format!("i64.shr_{s}"),
+            BinaryOp::Eq => "i64.eq".to_string(),
+            BinaryOp::Ne => "i64.ne".to_string(),
+            BinaryOp::Lt => format!("i64.lt_{s}"),
+            BinaryOp::Le => format!("i64.le_{s}"),
+            BinaryOp::Gt => format!("i64.gt_{s}"),
+            BinaryOp::Ge => format!("i64.ge_{s}"),
+        };
+        // a comparison gives an i32
+        let extend = if op.is_comparison() { "i64.extend_i32_u\n" } else { "" };
+        return format!("{}{}{instruction}\n{extend}local.set {destination}\n", push(a), push(b));
+    }
+
+    fn extend(&mut self, destination: &str, value: &str, size: u32, signed: bool) -> String {
+        let instructions = match (size, signed) {
+            (1, true) => "i64.extend8_s\n",
+            (2, true) => "i64.extend16_s\n",
+            (4, true) => "i64.extend32_s\n",
+            (1, false) => "i64.const 0xff\ni64.and\n",
+            (2, false) => "i64.const 0xffff\ni64.and\n",
+            _ => "i64.const 0xffffffff\ni64.and\n",
+        };
+        return format!("{}{instructions}local.set {destination}\n", push(value));
+    }
+
+    fn load(&mut self, destination: &str, address: &str, size: u32, signed: bool) -> String {
+        let instruction = match (size, signed) {
+            (1, true) => "i64.load8_s",
+            (1, false) => "i64.load8_u",
+            (2, true) => "i64.load16_s",
+            (2, false) => "i64.load16_u",
+            (4, true) => "i64.load32_s",
+            (4, false) => "i64.load32_u",
+            _ => "i64.load",
+        };
+        return format!("{}i32.wrap_i64\n{instruction}\nlocal.set {destination}\n", push(address));
+    }
+
+    fn store(&mut self, address: &str, value: &str, size: u32) -> String {
+        let instruction = match size {
+            1 => "i64.store8",
+            2 => "i64.store16",
+            4 => "i64.store32",
+            _ => "i64.store",
+        };
+        return format!("{}i32.wrap_i64\n{}{instruction}\n", push(address), push(value));
+    }
+
+    fn copy(&mut self, destination: &str, source: &str, size: u32) -> String {
+        return format!("{}i32.wrap_i64\n{}i32.wrap_i64\ni32.const {size}\nmemory.copy\n", push(destination), push(source));
+    }
+
+    /*
+     * a builtin gets the address and the length of location after its arguments
+     */
+    fn call(&mut self, destination: Option<&str>, name: &str, function: &Function, args: &[(String, Type)], location: &str) -> String {
+        let mut code: String = args.iter().map(|(value, _)| push(value)).collect();
+        match function.linkage {
+            Linkage::Builtin => {
+                code += &self.text_arguments(location);
+                code += &format!("call ${}\n", runtime::symbol(name));
+            }
+            Linkage::Stem => code += &format!("call ${}\n", symbol(name)),
+            Linkage::C { .. } => unreachable!("a program of wasm32 has no C functions"),
+        }
+        match destination {
+            Some(destination) => code += &format!("local.set {destination}\n"),
+            None if function.returns.is_some() => code += "drop\n",
+            None => {}
+        }
+        return code;
+    }
+
+    fn discard(&mut self, _value: &str) -> String {
+        return "".to_string();
+    }
+
+    fn put(&mut self, value: &str, signed: bool) -> String {
+        return format!("{}i32.const {}\ncall ${}\n", push(value), signed as u8, runtime::symbol("put"));
+    }
+
+    fn panic(&mut self, message: &str) -> String {
+        return format!("{}call ${}\n", self.text_arguments(message), runtime::symbol("panic"));
+    }
+
+    /*
+     * a `return` leaves the block $return around the body, with its value in $return.value
+     */
+    fn ret(&mut self, value: Option<&str>) -> String {
+        let mut code = "".to_string();
+        if let Some(value) = value {
+            code += &format!("{}local.set $return.value\n", push(value));
+        }
+        return code + "br $return\n";
+    }
+
+    fn if_nonzero(&mut self, value: &str) -> String {
+        return format!("{}i64.eqz\ni32.eqz\nif\n", push(value));
+    }
+
+    fn if_zero(&mut self, value: &str) -> String {
+        return format!("{}i64.eqz\nif\n", push(value));
+    }
+
+    fn if_else(&mut self) -> String {
+        return "else\n".to_string();
+    }
+
+    fn if_end(&mut self) -> String {
+        return "end\n".to_string();
+    }
+
+    fn loop_start(&mut self) -> String {
+        self.loops += 1;
+        let n = self.loops;
+        self.loop_blocks.push(n);
+        return format!("block $end.{n}\nloop $while.{n}\n");
+    }
+
+    fn loop_exit_if_zero(&mut self, value: &str) -> String {
+        let n = self.loop_blocks.last().expect("inside a loop");
+        return format!("{}i64.eqz\nbr_if $end.{n}\n", push(value));
+    }
+
+    fn loop_end(&mut self) -> String {
+        let n = self.loop_blocks.pop().expect("inside a loop");
+        return format!("br $while.{n}\nend\nend\n");
+    }
+
+    fn statement_start(&mut self) {
+        self.statements.push(self.temporaries);
+    }
+
+    /*
+     * the instructions of a statement after a comment with its location
+     */
+    fn statement_end(&mut self, location: &str, code: String) -> String {
+        self.temporaries = self.statements.pop().expect("the temporaries of the statement");
+        return format!(";; {location}\n{code}");
+    }
+
+    /*
+     * the body is in the block $return, falling off its end returns 0
+     */
+    fn function(&mut self, decl: &FnDecl, location: &str, body: Body) -> String {
+        let mut header = format!("(func ${}", symbol(&decl.name));
+        for param in &body.params {
+            header += &format!(" (param {param} i64)");
+        }
+        let mut locals = body.locals.clone();
+        let mut epilogue = "";
+        if decl.returns.is_some() {
+            header += " (result i64)";
+            locals.push("$return.value".to_string());
+            epilogue = "local.get $return.value\n";
+        }
+        header += &format!(" ;; {location}");
+        let body = Body { code: format!("block $return\n{}end\n", body.code), ..body };
+        return self.func_codegen(&header, &locals, &body, epilogue);
+    }
+}
+
+/*
+ * indent the instructions of a body by the blocks they are in, starting at depth
+ */
+fn indent(code: &str, depth: usize) -> String {
+    let mut indented = "".to_string();
+    let mut depth = depth;
+    for line in code.lines() {
+        let instruction = line.split_whitespace().next().unwrap_or("");
+        if instruction == "end" {
+            depth -= 1;
+        }
+        let offset = if instruction == "else" { depth - 1 } else { depth };
+        indented += &format!("{}{line}\n", "  ".repeat(offset));
+        if ["block", "loop", "if"].contains(&instruction) {
+            depth += 1;
+        }
+    }
+    return indented;
+}
+
+/*
+ * _start: the top-level statements then main, or the test given on the command line, then exit
+ */
+fn start_codegen(program: &Program, ctx: &mut Context, lower: &mut Lowering<Wasm>, options: &Options) -> String {
+    lower::function_start(&program.statements, ctx, lower);
+    let mut code = "".to_string();
+    if options.tests {
+        // the test is a function named after its index
+        let index = lower.backend.temporary();
+        code += &format!("call $runtime.test\nlocal.set {index}\n");
+        for i in 0..program.tests.len() {
+            code += &format!("local.get {index}\ni64.const {i}\ni64.eq\nif\ncall ${}\nend\n", symbol(&format!("test.{i}")));
+        }
+        code += "i64.const 0\n";
+    } else {
+        for stmt in &program.statements {
+            code += &lower::stmt_codegen(stmt, ctx, lower);
+        }
+
+        // then main, with argc and argv, and exit with what it returns
+        match program.functions.iter().find(|function| function.name == "main") {
+            Some(main) => {
+                if !main.params.is_empty() {
+                    let argv = lower.backend.temporary();
+                    code += &format!("call $runtime.argv\nlocal.set {argv}\nglobal.get $runtime.argc\nlocal.get {argv}\n");
+                }
+                code += &format!("call ${}\n", symbol("main"));
+                if main.returns.is_none() {
+                    code += "i64.const 0\n";
+                }
+            }
+            None => code += "i64.const 0\n",
+        }
+    }
+    code += &format!("i32.const 0\ni32.const 0\ncall ${}\n", runtime::symbol("exit"));
+    ctx.use_runtime("exit");
+    let body = lower::body(code, ctx, lower);
+    return lower.backend.func_codegen("(func $_start (export \"_start\")", &body.locals, &body, "");
+}
+
+/*
+ * a string of the text format, the bytes that aren't printable are escaped
+ */
+fn wat_string(bytes: &[u8]) -> String {
+    let mut text = "\"".to_string();
+    for byte in bytes {
+        match byte {
+            b'"' | b'\\' => text += &format!("\\{}", *byte as char),
+            0x20..=0x7e => text.push(*byte as char),
+            _ => text += &format!("\\{byte:02x}"),
+        }
+    }
+    text.push('"');
+    return text;
+}
+
+/*
+ * Take a Program and return the text of its module: the runtime, the functions, _start, and the data
+ * the memory holds the data and is grown by alloc for the heap
+ */
+pub fn generate_module(program: &Program, sources: &SourceMap, options: &Options) -> String {
+    let mut ctx = new_context(program, sources, options);
+    let stack_top = STACK + STACK_SIZE;
+    let mut lower = Lowering::new(Wasm {
+        data: vec![],
+        data_end: stack_top,
+        temporaries: 0,
+        statements: vec![],
+        temporaries_used: 0,
+        loops: 0,
+        loop_blocks: vec![],
+    });
+    lower::globals_codegen(&program.globals, &mut ctx, &mut lower);
+    let mut functions = "".to_string();
+    for function in &program.functions {
+        functions += "\n";
+        functions += &lower::fn_codegen(function, &mut ctx, &mut lower);
+    }
+    if options.tests {
+        check_tests(&program.tests, sources);
+        for (i, test) in program.tests.iter().enumerate() {
+            let decl = FnDecl { name: format!("test.{i}"), params: vec![], returns: None, body: test.body.clone(), export: None, span: test.span };
+            functions += "\n";
+            functions += &lower::fn_codegen(&decl, &mut ctx, &mut lower);
+        }
+    }
+    let start = start_codegen(program, &mut ctx, &mut lower, options);
+
+    let wasm = &lower.backend;
+    let mut code = "(module\n".to_string();
+    code += runtime::WASM32.prelude;
+    code += &format!("\n  (memory (export \"memory\") {})\n", wasm.data_end.div_ceil(PAGE_SIZE));
+    code += &format!("  ;; the stack grows down from {stack_top} to {STACK}\n");
+    code += &format!("  (global $stack_pointer (mut i32) (i32.const {stack_top}))\n");
+    code += &functions;
+    code += "\n";
+    code += &start;
+    code += &runtime::functions_codegen(&runtime::WASM32, &ctx.runtime);
+    code += "\n";
+    for (address, bytes) in &wasm.data {
+        code += &format!("  (data (i32.const {address}) {})\n", wat_string(bytes));
+    }
+    code += ")\n";
+    return code;
+}
@@ -0,0 +1,759 @@
+/*
+ * The binary format of a WebAssembly module, encoded from the text the code generation writes
+ * only the subset of the text format it uses is read: the fields of the module as s-expressions,
+ * the instructions of a function flat, one after the other, and blocks without results
+ * the text is made by the compiler, an error in it is a bug of the compiler and panics
+ */
+use std::collections::HashMap;
+
+enum Sexp {
+    List(Vec<Sexp>),
+    Atom(String),
+    Str(Vec<u8>),
+}
+
+impl Sexp {
+    fn atom(&self) -> Option<&str> {
+        match self {
+            Sexp::Atom(atom) => Some(atom),
+            _ => None,
+        }
+    }
+
+    fn string(&self) -> &[u8] {
+        match self {
+            Sexp::Str(bytes) => bytes,
+            _ => panic!("expected a string in the module text"),
+        }
+    }
+
+    /*
+     * the keyword starting a list, `func` for `(func ...)`
+     */
+    fn keyword(&self) -> Option<&str> {
+        match self {
+            Sexp::List(items) => items.first().and_then(Sexp::atom),
+            _ => None,
+        }
+    }
+
+    fn items(&self) -> &[Sexp] {
+        match self {
+            Sexp::List(items) => items,
+            _ => panic!("expected a list in the module text"),
+        }
+    }
+}
+
+/*
+ * the bytes of a string of the text format, with its `\n`, `\t`, `\\`, `\"` and `\hh` escapes
+ */
+fn unescape(text: &str) -> Vec<u8> {
+    let bytes = text.as_bytes();
+    let mut value = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] != b'\\' {
+            value.push(bytes[i]);
+            i += 1;
+            continue;
+        }
+        match bytes[i + 1] {
+            b'n' => value.push(b'\n'),
+            b't' => value.push(b'\t'),
+            b'\\' => value.push(b'\\'),
+            b'"' => value.push(b'"'),
+            b'\'' => value.push(b'\''),
+            _ => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).expect("an escape is ascii");
+                value.push(u8::from_str_radix(hex, 16).expect("an escape is two hex digits"));
+                i += 1;
+            }
+        }
+        i += 2;
+    }
+    return value;
+}
+
+/*
+ * read the s-expressions of text, `;;` starts a comment up to the end of the line
+ */
+fn parse(text: &str) -> Vec<Sexp> {
+    let mut stack: Vec<Vec<Sexp>> = vec![vec![]];
+    let mut chars = text.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        match c {
+            '(' => stack.push(vec![]),
+            ')' => {
+                let list = stack.pop().expect("a `)` closes a `(`");
+                stack.last_mut().expect("a `)` without its `(`").push(Sexp::List(list));
+            }
+            ';' if chars.peek().is_some_and(|(_, next)| *next == ';') => {
+                while chars.next_if(|(_, next)| *next != '\n').is_some() {}
+            }
+            '"' => {
+                let mut end = start + 1;
+                while let Some((i, c)) = chars.next() {
+                    end = i;
+                    match c {
+                        '\\' => {
+                            chars.next();
+                        }
+                        '"' => break,
+                        _ => {}
+                    }
+                }
+                stack.last_mut().unwrap().push(Sexp::Str(unescape(&text[start + 1..end])));
+            }
+            c if c.is_whitespace() => {}
+            _ => {
+                let mut end = start + c.len_utf8();
+                while let Some((i, c)) = chars.next_if(|(_, c)| !c.is_whitespace() && *c != '(' && *c != ')') {
+                    end = i + c.len_utf8();
+                }
+                stack.last_mut().unwrap().push(Sexp::Atom(text[start..end].to_string()));
+            }
+        }
+    }
+    assert!(stack.len() == 1, "a `(` is never closed in the module text");
+    return stack.pop().unwrap();
+}
+
+fn unsigned(bytes: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            bytes.push(byte);
+            return;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
+
+fn signed(bytes: &mut Vec<u8>, mut value: i64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0) {
+            bytes.push(byte);
+            return;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
+
+fn name(bytes: &mut Vec<u8>, name: &[u8]) {
+    unsigned(bytes, name.len() as u64);
+    bytes.extend(name);
+}
+
+/*
+ * an integer of the text format: decimal or hexadecimal, maybe negative, with `_` between the digits
+ * the values above the largest signed integer are their bits
+ */
+fn integer(text: &str) -> i64 {
+    let digits = text.replace('_', "");
+    let (negative, digits) = match digits.strip_prefix('-') {
+        Some(digits) => (true, digits.to_string()),
+        None => (false, digits),
+    };
+    let value = match digits.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => digits.parse::<u64>(),
+    };
+    let value = value.unwrap_or_else(|_| panic!("`{text}` is not an integer"));
+    return if negative { (value as i64).wrapping_neg() } else { value as i64 };
+}
+
+fn value_type(text: &str) -> u8 {
+    match text {
+        "i32" => 0x7f,
+        "i64" => 0x7e,
+        _ => panic!("`{text}` is not a value type"),
+    }
+}
+
+/*
+ * the opcode of an instruction without immediates
+ */
+fn plain_opcode(instruction: &str) -> Option<&'static [u8]> {
+    let opcode: &[u8] = match instruction {
+        "unreachable" => &[0x00],
+        "nop" => &[0x01],
+        "return" => &[0x0f],
+        "drop" => &[0x1a],
+        "select" => &[0x1b],
+        "memory.size" => &[0x3f, 0x00],
+        "memory.grow" => &[0x40, 0x00],
+        "i32.eqz" => &[0x45],
+        "i32.eq" => &[0x46],
+        "i32.ne" => &[0x47],
+        "i32.lt_s" => &[0x48],
+        "i32.lt_u" => &[0x49],
+        "i32.gt_s" => &[0x4a],
+        "i32.gt_u" => &[0x4b],
+        "i32.le_s" => &[0x4c],
+        "i32.le_u" => &[0x4d],
+        "i32.ge_s" => &[0x4e],
+        "i32.ge_u" => &[0x4f],
+        "i64.eqz" => &[0x50],
+        "i64.eq" => &[0x51],
+        "i64.ne" => &[0x52],
+        "i64.lt_s" => &[0x53],
+        "i64.lt_u" => &[0x54],
+        "i64.gt_s" => &[0x55],
+        "i64.gt_u" => &[0x56],
+        "i64.le_s" => &[0x57],
+        "i64.le_u" => &[0x58],
+        "i64.ge_s" => &[0x59],
+        "i64.ge_u" => &[0x5a],
+        "i32.add" => &[0x6a],
+        "i32.sub" => &[0x6b],
+        "i32.mul" => &[0x6c],
+        "i32.div_s" => &[0x6d],
+        "i32.div_u" => &[0x6e],
+        "i32.rem_s" => &[0x6f],
+        "i32.rem_u" => &[0x70],
+        "i32.and" => &[0x71],
+        "i32.or" => &[0x72],
+        "i32.xor" => &[0x73],
+        "i32.shl" => &[0x74],
+        "i32.shr_s" => &[0x75],
+        "i32.shr_u" => &[0x76],
+        "i64.add" => &[0x7c],
+        "i64.sub" => &[0x7d],
+        "i64.mul" => &[0x7e],
+        "i64.div_s" => &[0x7f],
+        "i64.div_u" => &[0x80],
+        "i64.rem_s" => &[0x81],
+        "i64.rem_u" => &[0x82],
+        "i64.and" => &[0x83],
+        "i64.or" => &[0x84],
+        "i64.xor" => &[0x85],
+        "i64.shl" => &[0x86],
+        "i64.shr_s" => &[0x87],
+        "i64.shr_u" => &[0x88],
+        "i32.wrap_i64" => &[0xa7],
+        "i64.extend_i32_s" => &[0xac],
+        "i64.extend_i32_u" => &[0xad],
+        "i64.extend8_s" => &[0xc2],
+        "i64.extend16_s" => &[0xc3],
+        "i64.extend32_s" => &[0xc4],
+        "memory.copy" => &[0xfc, 0x0a, 0x00, 0x00],
+        "memory.fill" => &[0xfc, 0x0b, 0x00],
+        _ => return None,
+    };
+    return Some(opcode);
+}
+
+/*
+ * the opcode of a load or a store and the log2 of its natural alignment
+ */
+fn memory_opcode(instruction: &str) -> Option<(u8, u64)> {
+    let opcode = match instruction {
+        "i32.load" => (0x28, 2),
+        "i64.load" => (0x29, 3),
+        "i32.load8_s" => (0x2c, 0),
+        "i32.load8_u" => (0x2d, 0),
+        "i32.load16_s" => (0x2e, 1),
+        "i32.load16_u" => (0x2f, 1),
+        "i64.load8_s" => (0x30, 0),
+        "i64.load8_u" => (0x31, 0),
+        "i64.load16_s" => (0x32, 1),
+        "i64.load16_u" => (0x33, 1),
+        "i64.load32_s" => (0x34, 2),
+        "i64.load32_u" => (0x35, 2),
+        "i32.store" => (0x36, 2),
+        "i64.store" => (0x37, 3),
+        "i32.store8" => (0x3a, 0),
+        "i32.store16" => (0x3b, 1),
+        "i64.store8" => (0x3c, 0),
+        "i64.store16" => (0x3d, 1),
+        "i64.store32" => (0x3e, 2),
+        _ => return None,
+    };
+    return Some(opcode);
+}
+
+/*
+ * the index of a `$name` in names, or the index written as a number
+ */
+fn index(atom: &str, names: &HashMap<String, u32>) -> u32 {
+    if atom.starts_with('$') {
+        return *names.get(atom).unwrap_or_else(|| panic!("unknown `{atom}` in the module text"));
+    }
+    return integer(atom) as u32;
+}
+
+/*
+ * the code of a function: its locals grouped by type, then its instructions
+ * locals holds the names of the parameters and of the locals in order
+ */
+fn function_code(body: &[Sexp], locals: &HashMap<String, u32>, local_types: &[u8], functions: &HashMap<String, u32>, globals: &HashMap<String, u32>) -> Vec<u8> {
+    let mut code = vec![];
+    let mut groups: Vec<(u32, u8)> = vec![];
+    for type_ in local_types {
+        match groups.last_mut() {
+            Some((count, last)) if last == type_ => *count += 1,
+            _ => groups.push((1, *type_)),
+        }
+    }
+    unsigned(&mut code, groups.len() as u64);
+    for (count, type_) in groups {
+        unsigned(&mut code, count as u64);
+        code.push(type_);
+    }
+
+    // the labels of the enclosing blocks, the innermost last
+    let mut labels: Vec<Option<String>> = vec![];
+    let mut atoms = body.iter().map(|item| item.atom().expect("the instructions of a function are flat")).peekable();
+    while let Some(instruction) = atoms.next() {
+        if let Some(opcode) = plain_opcode(instruction) {
+            code.extend(opcode);
+            continue;
+        }
+        if let Some((opcode, align)) = memory_opcode(instruction) {
+            let mut offset = 0;
+            while let Some(immediate) = atoms.next_if(|atom| atom.starts_with("offset=")) {
+                offset = integer(&immediate["offset=".len()..]) as u64;
+            }
+            code.push(opcode);
+            unsigned(&mut code, align);
+            unsigned(&mut code, offset);
+            continue;
+        }
+        match instruction {
+            "block" | "loop" | "if" => {
+                code.push(match instruction {
+                    "block" => 0x02,
+                    "loop" => 0x03,
+                    _ => 0x04,
+                });
+                // no results
+                code.push(0x40);
+                labels.push(atoms.next_if(|atom| atom.starts_with('$')).map(str::to_string));
+            }
+            "else" => code.push(0x05),
+            "end" => {
+                labels.pop().expect("an `end` closes a block");
+                code.push(0x0b);
+            }
+            "br" | "br_if" => {
+                code.push(if instruction == "br" { 0x0c } else { 0x0d });
+                let label = atoms.next().expect("a branch has a label");
+                let depth = if label.starts_with('$') {
+                    let position = labels.iter().rposition(|other| other.as_deref() == Some(label));
+                    labels.len() - 1 - position.unwrap_or_else(|| panic!("unknown label `{label}` in the module text"))
+                } else {
+                    integer(label) as usize
+                };
+                unsigned(&mut code, depth as u64);
+            }
+            "call" => {
+                code.push(0x10);
+                unsigned(&mut code, index(atoms.next().expect("a call has a function"), functions) as u64);
+            }
+            "local.get" | "local.set" | "local.tee" => {
+                code.push(match instruction {
+                    "local.get" => 0x20,
+                    "local.set" => 0x21,
+                    _ => 0x22,
+                });
+                unsigned(&mut code, index(atoms.next().expect("a local instruction has a local"), locals) as u64);
+            }
+            "global.get" | "global.set" => {
+                code.push(if instruction == "global.get" { 0x23 } else { 0x24 });
+                unsigned(&mut code, index(atoms.next().expect("a global instruction has a global"), globals) as u64);
+            }
+            "i32.const" => {
+                code.push(0x41);
+                signed(&mut code, integer(atoms.next().expect("a constant has a value")) as i32 as i64);
+            }
+            "i64.const" => {
+                code.push(0x42);
+                signed(&mut code, integer(atoms.next().expect("a constant has a value")));
+            }
+            _ => panic!("unknown instruction `{instruction}` in the module text"),
+        }
+    }
+    assert!(labels.is_empty(), "a block is never closed in the module text");
+    code.push(0x0b);
+    return code;
+}
+
+/*
+ * a section: its id and its content with its size
+ */
+fn section(bytes: &mut Vec<u8>, id: u8, content: &[u8]) {
+    bytes.push(id);
+    unsigned(bytes, content.len() as u64);
+    bytes.extend(content);
+}
+
+/*
+ * a vector of the section content: its number of items then the items
+ */
+fn vector(count: usize, items: Vec<u8>) -> Vec<u8> {
+    let mut bytes = vec![];
+    unsigned(&mut bytes, count as u64);
+    bytes.extend(items);
+    return bytes;
+}
+
+/*
+ * the parameters and the results of `(param ...)` and `(result ...)` items, with the names of the parameters
+ */
+fn signature(items: &[Sexp], names: &mut HashMap<String, u32>) -> (Vec<u8>, Vec<u8>) {
+    let (mut params, mut results) = (vec![], vec![]);
+    for item in items {
+        match item.keyword() {
+            Some("param") => {
+                let rest = &item.items()[1..];
+                match rest[0].atom() {
+                    Some(id) if id.starts_with('$') => {
+                        names.insert(id.to_string(), params.len() as u32);
+                        params.push(value_type(rest[1].atom().unwrap()));
+                    }
+                    _ => params.extend(rest.iter().map(|type_| value_type(type_.atom().unwrap()))),
+                }
+            }
+            Some("result") => results.extend(item.items()[1..].iter().map(|type_| value_type(type_.atom().unwrap()))),
+            _ => {}
+        }
+    }
+    return (params, results);
+}
+
+/*
+ * the binary module of the text of one
+ */
+pub fn assemble(text: &str) -> Vec<u8> {
+    let module = parse(text);
+    let [module] = module.as_slice() else {
+        panic!("the module text holds one module");
+    };
+    assert!(module.keyword() == Some("module"), "the module text holds one module");
+    let fields = &module.items()[1..];
+
+    // the imported functions come first in the index space of the functions
+    let mut types: Vec<(Vec<u8>, Vec<u8>)> = vec![];
+    let mut type_index = |signature: (Vec<u8>, Vec<u8>)| -> u32 {
+        match types.iter().position(|other| *other == signature) {
+            Some(i) => i as u32,
+            None => {
+                types.push(signature);
+                (types.len() - 1) as u32
+            }
+        }
+    };
+    let mut functions: HashMap<String, u32> = HashMap::new();
+    let mut globals: HashMap<String, u32> = HashMap::new();
+    let mut count = 0;
+    for field in fields.iter().filter(|field| field.keyword() == Some("import")) {
+        if let Some(id) = field.items()[3].items().get(1).and_then(Sexp::atom).filter(|id| id.starts_with('$')) {
+            functions.insert(id.to_string(), count);
+        }
+        count += 1;
+    }
+    for field in fields {
+        let id = field.items().get(1).and_then(Sexp::atom).filter(|id| id.starts_with('$'));
+        match (field.keyword(), id) {
+            (Some("func"), id) => {
+                if let Some(id) = id {
+                    functions.insert(id.to_string(), count);
+                }
+                count += 1;
+            }
+            (Some("global"), Some(id)) => {
+                globals.insert(id.to_string(), globals.len() as u32);
+            }
+            _ => {}
+        }
+    }
+
+    let (mut imports, mut import_count) = (vec![], 0);
+    let (mut declarations, mut codes, mut function_count) = (vec![], vec![], 0);
+    let (mut memories, mut global_section, mut global_count) = (vec![], vec![], 0);
+    let (mut exports, mut export_count) = (vec![], 0);
+    let (mut data, mut data_count) = (vec![], 0);
+    for field in fields {
+        let items = field.items();
+        match field.keyword() {
+            Some("import") => {
+                name(&mut imports, items[1].string());
+                name(&mut imports, items[2].string());
+                let function = items[3].items();
+                imports.push(0x00);
+                let index = type_index(signature(function, &mut HashMap::new()));
+                unsigned(&mut imports, index as u64);
+                import_count += 1;
+            }
+            Some("memory") => {
+                let mut rest = &items[1..];
+                if let Some(Some("export")) = rest.first().map(Sexp::keyword) {
+                    name(&mut exports, rest[0].items()[1].string());
+                    exports.push(0x02);
+                    unsigned(&mut exports, 0);
+                    export_count += 1;
+                    rest = &rest[1..];
+                }
+                memories.push(0x00);
+                unsigned(&mut memories, integer(rest[0].atom().expect("a memory has a size")) as u64);
+            }
+            Some("global") => {
+                let (type_, init) = (&items[2], items[3].items());
+                match type_.keyword() {
+                    Some("mut") => {
+                        global_section.push(value_type(type_.items()[1].atom().unwrap()));
+                        global_section.push(0x01);
+                    }
+                    _ => {
+                        global_section.push(value_type(type_.atom().unwrap()));
+                        global_section.push(0x00);
+                    }
+                }
+                let instructions: Vec<Sexp> = init.iter().map(|item| Sexp::Atom(item.atom().unwrap().to_string())).collect();
+                let code = function_code(&instructions, &HashMap::new(), &[], &functions, &globals);
+                // no locals, the expression and its end
+                global_section.extend(&code[1..]);
+                global_count += 1;
+            }
+            Some("func") => {
+                let mut locals: HashMap<String, u32> = HashMap::new();
+                let (params, results) = signature(items, &mut locals);
+                let mut local_types = vec![];
+                let mut body_start = 1;
+                for (i, item) in items.iter().enumerate().skip(1) {
+                    match item.keyword() {
+                        Some("export") => {
+                            name(&mut exports, item.items()[1].string());
+                            exports.push(0x00);
+                            unsigned(&mut exports, functions[items[1].atom().unwrap()] as u64);
+                            export_count += 1;
+                        }
+                        Some("local") => {
+                            let rest = &item.items()[1..];
+                            match rest[0].atom() {
+                                Some(id) if id.starts_with('$') => {
+                                    locals.insert(id.to_string(), (params.len() + local_types.len()) as u32);
+                                    local_types.push(value_type(rest[1].atom().unwrap()));
+                                }
+                                _ => local_types.extend(rest.iter().map(|type_| value_type(type_.atom().unwrap()))),
+                            }
+                        }
+                        Some(_) => {}
+                        None if i == 1 && item.atom().is_some_and(|id| id.starts_with('$')) => {}
+                        None => break,
+                    }
+                    body_start = i + 1;
+                }
+                unsigned(&mut declarations, type_index((params, results)) as u64);
+                let code = function_code(&items[body_start..], &locals, &local_types, &functions, &globals);
+                unsigned(&mut codes, code.len() as u64);
+                codes.extend(code);
+                function_count += 1;
+            }
+            Some("export") => {
+                name(&mut exports, items[1].string());
+                let kind = &items[2].items();
+                exports.push(match kind[0].atom() {
+                    Some("func") => 0x00,
+                    Some("memory") => 0x02,
+                    Some("global") => 0x03,
+                    _ => panic!("unknown export in the module text"),
+                });
+                let index = match kind[0].atom() {
+                    Some("func") => functions[kind[1].atom().unwrap()],
+                    Some("global") => globals[kind[1].atom().unwrap()],
+                    _ => 0,
+                };
+                unsigned(&mut exports, index as u64);
+                export_count += 1;
+            }
+            Some("data") => {
+                // in the memory 0, at an address given by its constant
+                unsigned(&mut data, 0);
+                let offset: Vec<Sexp> = items[1].items().iter().map(|item| Sexp::Atom(item.atom().unwrap().to_string())).collect();
+                data.extend(&function_code(&offset, &HashMap::new(), &[], &functions, &globals)[1..]);
+                let bytes: Vec<u8> = items[2..].iter().flat_map(|item| item.string().to_vec()).collect();
+                unsigned(&mut data, bytes.len() as u64);
+                data.extend(bytes);
+                data_count += 1;
+            }
+            _ => panic!("unknown field in the module text"),
+        }
+    }
+
+    let mut type_section = vec![];
+    for (params, results) in &types {
+        type_section.push(0x60);
+        unsigned(&mut type_section, params.len() as u64);
+        type_section.extend(params);
+        unsigned(&mut type_section, results.len() as u64);
+        type_section.extend(results);
+    }
+    let mut bytes = b"\0asm".to_vec();
+    bytes.extend([1, 0, 0, 0]);
+    section(&mut bytes, 1, &vector(types.len(), type_section));
+    section(&mut bytes, 2, &vector(import_count, imports));
+    section(&mut bytes, 3, &vector(function_count, declarations));
+    if !memories.is_empty() {
+        section(&mut bytes, 5, &vector(1, memories));
+    }
+    section(&mut bytes, 6, &vector(global_count, global_section));
+    section(&mut bytes, 7, &vector(export_count, exports));
+    section(&mut bytes, 10, &vector(function_count, codes));
+    section(&mut bytes, 11, &vector(data_count, data));
+    return bytes;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leb(encode: fn(&mut Vec<u8>, u64), value: u64) -> Vec<u8> {
+        let mut bytes = vec![];
+        encode(&mut bytes, value);
+        return bytes;
+    }
+
+    fn sleb(value: i64) -> Vec<u8> {
+        let mut bytes = vec![];
+        signed(&mut bytes, value);
+        return bytes;
+    }
+
+    #[test]
+    fn unsigned_leb128() {
+        assert_eq!(leb(unsigned, 0), [0x00]);
+        assert_eq!(leb(unsigned, 1), [0x01]);
+        assert_eq!(leb(unsigned, 127), [0x7f]);
+        assert_eq!(leb(unsigned, 128), [0x80, 0x01]);
+        assert_eq!(leb(unsigned, 16383), [0xff, 0x7f]);
+        assert_eq!(leb(unsigned, 16384), [0x80, 0x80, 0x01]);
+        assert_eq!(leb(unsigned, 624485), [0xe5, 0x8e, 0x26]);
+        assert_eq!(leb(unsigned, u32::MAX as u64), [0xff, 0xff, 0xff, 0xff, 0x0f]);
+        assert_eq!(leb(unsigned, u64::MAX), [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01]);
+    }
+
+    #[test]
+    fn signed_leb128() {
+        assert_eq!(sleb(0), [0x00]);
+        assert_eq!(sleb(-1), [0x7f]);
+        assert_eq!(sleb(63), [0x3f]);
+        // bit 6 is the sign of the last byte, 64 needs a second one
+        assert_eq!(sleb(64), [0xc0, 0x00]);
+        assert_eq!(sleb(-64), [0x40]);
+        assert_eq!(sleb(-65), [0xbf, 0x7f]);
+        assert_eq!(sleb(1024), [0x80, 0x08]);
+        assert_eq!(sleb(-123456), [0xc0, 0xbb, 0x78]);
+        assert_eq!(sleb(i32::MIN as i64), [0x80, 0x80, 0x80, 0x80, 0x78]);
+        assert_eq!(sleb(i64::MAX), [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00]);
+        assert_eq!(sleb(i64::MIN), [0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x7f]);
+    }
+
+    #[test]
+    fn text_integers() {
+        assert_eq!(integer("42"), 42);
+        assert_eq!(integer("-1"), -1);
+        assert_eq!(integer("0xff"), 255);
+        assert_eq!(integer("1_000"), 1000);
+        assert_eq!(integer("18446744073709551615"), -1);
+        assert_eq!(integer("-9223372036854775808"), i64::MIN);
+    }
+
+    #[test]
+    fn string_escapes() {
+        assert_eq!(unescape(r#"a\n\t\\\"\'\00\ff"#), b"a\n\t\\\"'\x00\xff");
+    }
+
+    #[test]
+    fn minimal_module() {
+        let bytes = assemble(r#"(module (func $f (export "f") (result i32) i32.const 42))"#);
+        #[rustfmt::skip]
+        let expected = [
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00,
+            // types: () -> i32
+            0x01, 0x05, 0x01, 0x60, 0x00, 0x01, 0x7f,
+            // no imports
+            0x02, 0x01, 0x00,
+            // one function of type 0
+            0x03, 0x02, 0x01, 0x00,
+            // no globals
+            0x06, 0x01, 0x00,
+            // export "f" as the function 0
+            0x07, 0x05, 0x01, 0x01, b'f', 0x00, 0x00,
+            // no locals, i32.const 42, end
+            0x0a, 0x06, 0x01, 0x04, 0x00, 0x41, 0x2a, 0x0b,
+            // no data
+            0x0b, 0x01, 0x00,
+        ];
+        assert_eq!(bytes, expected);
+    }
+
+    #[test]
+    fn module_with_imports_memory_globals_blocks_and_data() {
+        let bytes = assemble(
+            r#"
+            ;; the shape of the modules of the code generation
+            (module
+              (import "wasi_snapshot_preview1" "proc_exit" (func $exit (param i32)))
+              (memory (export "memory") 1)
+              (global $sp (mut i32) (i32.const 1024))
+              (data (i32.const 8) "hi\0a")
+              (func $main (export "_start") (local $x i64) (local $y i64)
+                i64.const -1
+                local.set $y
+                block $done
+                  loop $again
+                    global.get $sp
+                    br_if $again
+                    br $done
+                  end
+                end
+                i32.const 0
+                i64.load8_u offset=8
+                drop
+                i32.const 0
+                call $exit))
+            "#,
+        );
+        let mut expected = vec![0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00];
+        // types: (i32) -> () of the import, () -> () of $main
+        expected.extend([0x01, 0x08, 0x02, 0x60, 0x01, 0x7f, 0x00, 0x60, 0x00, 0x00]);
+        expected.extend([0x02, 0x24, 0x01, 0x16]);
+        expected.extend(b"wasi_snapshot_preview1");
+        expected.push(0x09);
+        expected.extend(b"proc_exit");
+        expected.extend([0x00, 0x00]);
+        expected.extend([0x03, 0x02, 0x01, 0x01]);
+        // one memory of at least 1 page
+        expected.extend([0x05, 0x03, 0x01, 0x00, 0x01]);
+        // a mutable i32 set to 1024
+        expected.extend([0x06, 0x07, 0x01, 0x7f, 0x01, 0x41, 0x80, 0x08, 0x0b]);
+        expected.extend([0x07, 0x13, 0x02, 0x06]);
+        expected.extend(b"memory");
+        expected.extend([0x02, 0x00, 0x06]);
+        expected.extend(b"_start");
+        // $main is after the import
+        expected.extend([0x00, 0x01]);
+        #[rustfmt::skip]
+        expected.extend([
+            0x0a, 0x20, 0x01, 0x1e,
+            // two i64 locals, $y is the local 1
+            0x01, 0x02, 0x7e,
+            0x42, 0x7f, 0x21, 0x01,
+            0x02, 0x40, 0x03, 0x40,
+            0x23, 0x00,
+            // br_if $again is the innermost label, br $done the one around it
+            0x0d, 0x00, 0x0c, 0x01,
+            0x0b, 0x0b,
+            0x41, 0x00, 0x31, 0x00, 0x08, 0x1a,
+            0x41, 0x00, 0x10, 0x00,
+            0x0b,
+        ]);
+        expected.extend([0x0b, 0x09, 0x01, 0x00, 0x41, 0x08, 0x0b, 0x03, b'h', b'i', b'\n']);
+        assert_eq!(bytes, expected);
+    }
+}
@@ -0,0 +1,269 @@
+/*
+ * Behavior tests: each program is written with `stem-rs --emit=c`, compiled with cc and run,
+ * then its output and its exit status are checked
+ *
+ * cargo test --test programs
+ */
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/*
+ * what a program did: its output, the messages of its panics and its exit status
+ */
+struct Run {
+    stdout: String,
+    stderr: String,
+    status: i32,
+}
+
+/*
+ * a directory of its own for the test name, with the files of the program in it
+ */
+fn program_dir(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("stem-test-{}-{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&dir);
+    for (file, text) in files {
+        let path = dir.join(file);
+        fs::create_dir_all(path.parent().unwrap()).expect("Can't create the test directory");
+        fs::write(path, text).expect("Can't write the test program");
+    }
+    dir
+}
+
+/*
+ * compile main.stm of dir with flags, then with cc along with the C files of c_files, and run it
+ * the compilers have to succeed
+ */
+fn compile_and_run(dir: &Path, flags: &[&str], c_files: &[&str]) -> Run {
+    let stem = Command::new(env!("CARGO_BIN_EXE_stem-rs"))
+        .args(flags)
+        .arg("--emit=c")
+        .arg("main.stm")
+        .current_dir(dir)
+        .output()
+        .expect("Can't run stem-rs");
+    assert!(stem.status.success(), "stem-rs failed: {}", String::from_utf8_lossy(&stem.stderr));
+    let cc = Command::new("cc")
+        .args(["-std=c99", "-pedantic", "-Werror", "-o", "program", "output.c"])
+        .args(c_files)
+        .current_dir(dir)
+        .output()
+        .expect("Can't run cc");
+    assert!(cc.status.success(), "cc failed: {}", String::from_utf8_lossy(&cc.stderr));
+    let program = Command::new(dir.join("program")).current_dir(dir).output().expect("Can't run the program");
+    fs::remove_dir_all(dir).expect("Can't remove the test directory");
+    Run {
+        stdout: String::from_utf8_lossy(&program.stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&program.stderr).into_owned(),
+        status: program.status.code().expect("the program exited"),
+    }
+}
+
+/*
+ * the run of a program of a single file, with the overflow checks unless flags has --release
+ */
+fn run(name: &str, program: &str, flags: &[&str]) -> Run {
+    let dir = program_dir(name, &[("main.stm", program)]);
+    compile_and_run(&dir, flags, &[])
+}
+
+/*
+ * the output of a program that exits with 0, one value per line
+ */
+fn output(name: &str, program: &str) -> Vec<String> {
+    let run = run(name, program, &[]);
+    assert_eq!(run.status, 0, "the program failed: {}", run.stderr);
+    run.stdout.lines().map(str::to_string).collect()
+}
+
+/*
+ * the message of the panic that stops a program compiled with flags, without its location
+ */
+fn panic_message(name: &str, program: &str, flags: &[&str]) -> String {
+    let run = run(name, program, flags);
+    assert_eq!(run.status, 101, "the program didn't panic, it printed {:?}", run.stdout);
+    let (_, message) = run.stderr.trim_end().split_once(": ").expect("a panic starts with its location");
+    message.to_string()
+}
+
+/*
+ * the error stem-rs gives for a program it rejects, without its location
+ */
+fn compile_error(name: &str, program: &str) -> String {
+    let dir = program_dir(name, &[("main.stm", program)]);
+    let stem = Command::new(env!("CARGO_BIN_EXE_stem-rs")).args(["--emit=c", "main.stm"]).current_dir(&dir).output().expect("Can't run stem-rs");
+    fs::remove_dir_all(&dir).expect("Can't remove the test directory");
+    assert!(!stem.status.success(), "stem-rs accepted the program");
+    let stderr = String::from_utf8_lossy(&stem.stderr);
+    let (_, message) = stderr.trim_end().rsplit_once(": ").expect("an error has a location");
+    message.to_string()
+}
+
+#[test]
+fn struct_fields_are_aligned() {
+    let program = "
+struct S { a: u8, b: i64, c: u16, d: u8 }
+let s = S { a: 1, b: 2, c: 3, d: 4 };
+let base: *u8 = &s;
+let b: *u8 = &s.b;
+let c: *u8 = &s.c;
+let d: *u8 = &s.d;
+put b - base;
+put c - base;
+put d - base;
+let next: *u8 = &s + 1;
+put next - base;
+";
+    assert_eq!(output("struct_fields_are_aligned", program), ["8", "16", "18", "24"]);
+}
+
+#[test]
+fn struct_fields_are_read_and_written() {
+    let program = "
+struct P { x: i32, y: u8 }
+struct R { min: P, max: P }
+let r = R { min: P { x: -1, y: 2 }, max: P { x: 30, y: 250 } };
+r.min.x = r.max.x + 12;
+r.max.y += 5;
+r.min = P { x: r.min.y, y: r.min.x };
+put r.min.x;
+put r.min.y;
+put r.max.y;
+let p: *R = &r;
+p.max.x = 7;
+put r.max.x;
+";
+    assert_eq!(output("struct_fields_are_read_and_written", program), ["2", "42", "255", "7"]);
+}
+
+#[test]
+fn structs_are_passed_and_returned_by_value() {
+    let program = "
+struct P { x: i64, y: i32, c: u8 }
+fn make(x: i64, y: i32) -> P { return P { x: x, y: y, c: 7 }; }
+fn sum(p: P) -> i64 { let s = p.x + p.y + p.c; p.x = 100; return s; }
+fn swap(p: P) -> P { return P { x: p.y, y: p.x, c: p.c + 1 }; }
+fn fib(n: i64) -> P { if n < 2 { return P { x: n, y: 0, c: 0 }; } let a = fib(n - 1); let b = fib(n - 2); return P { x: a.x + b.x, y: 0, c: 0 }; }
+fn set(p: P, q: *P) -> i64 { q.x = 5; return p.x; }
+let p = make(3, 4);
+put sum(p);
+put p.x;
+put sum(P { x: 1, y: 2, c: 3 });
+let s = swap(swap(p));
+put s.c;
+put make(5, 6).y;
+put fib(20).x;
+put set(p, &p);
+put p.x;
+";
+    assert_eq!(output("structs_are_passed_and_returned_by_value", program), ["14", "3", "6", "9", "6", "6765", "3", "5"]);
+}
+
+#[test]
+fn structs_are_not_passed_to_c() {
+    let program = "struct P { x: i64 }\npub extern fn f(p: P) -> i64 { return p.x; }\n";
+    assert_eq!(compile_error("structs_are_not_passed_to_c", program), "parameter `p` has type `P`, only integers can be passed to a function exported to C");
+}
+
+#[test]
+fn strings_are_printed() {
+    let run = run("strings_are_printed", "let s = \"hello\\n\";\nprint(s, 6);\nput 42;\nprint(s + 1, 2);\nprint(\"\\n\", 1);\n", &[]);
+    assert_eq!(run.stdout, "hello\n42\nel\n");
+}
+
+#[test]
+fn pointers_move_by_elements() {
+    let program = "
+let a: *i32 = alloc(40);
+let i = 0;
+while i < 10 { *(a + i) = i * i; i++; }
+let p = a + 3;
+put *p;
+put *(p + 2);
+put (a + 9) - p;
+let q: **i32 = &p;
+**q = -1;
+put *(a + 3);
+free(a);
+";
+    assert_eq!(output("pointers_move_by_elements", program), ["9", "25", "6", "-1"]);
+}
+
+#[test]
+fn globals_keep_their_values() {
+    let program = "
+struct P { x: i64, y: i64 }
+const K: i16 = -300;
+static count: u8 = 250;
+static origin: P = P { x: 1, y: 2 };
+static zero: i64;
+fn tick() { count += 1; zero = zero + K; }
+tick();
+tick();
+put count;
+put zero;
+origin.y = K * 2;
+put origin.x + origin.y;
+";
+    assert_eq!(output("globals_keep_their_values", program), ["252", "-600", "-599"]);
+}
+
+#[test]
+fn modules_have_their_own_names() {
+    let files = [
+        ("main.stm", "mod math;\nimport \"lib/geo.stm\";\nlet p = geo::Point { x: 3, y: 4 };\nput geo::norm2(&p);\nput math::square(5);\nput math::LIMIT;\nlet square = 2;\nput square;\n"),
+        ("math.stm", "const LIMIT: i64 = 99;\nfn square(x: i64) -> i64 { return x * x; }\n"),
+        ("lib/geo.stm", "struct Point { x: i64, y: i64 }\nfn norm2(p: *Point) -> i64 { return p.x * p.x + p.y * p.y; }\n"),
+    ];
+    let run = compile_and_run(&program_dir("modules_have_their_own_names", &files), &[], &[]);
+    assert_eq!(run.stdout, "25\n25\n99\n2\n");
+}
+
+#[test]
+fn names_of_modules_stay_apart() {
+    let files = [
+        ("main.stm", "import \"lib/math.stm\";\nfn math__sq(x: i64) -> i64 { return x + 1; }\nfn div(x: i64) -> i64 { return x; }\nput math::sq(5);\nput math__sq(5);\nput div(-7) / 2;\n"),
+        ("lib/math.stm", "fn sq(x: i64) -> i64 { return x * x; }\n"),
+    ];
+    let run = compile_and_run(&program_dir("names_of_modules_stay_apart", &files), &[], &[]);
+    assert_eq!(run.stdout, "25\n6\n-3\n");
+}
+
+#[test]
+fn c_functions_are_called() {
+    let files = [
+        ("main.stm", "extern fn strlen(s: *u8) -> u64;\nextern fn add3(a: i8, b: i64, c: u16) -> i64;\nput strlen(\"four\");\nput add3(-1i8, 2, 65535u16);\n"),
+        ("side.c", "#include <stdint.h>\nint64_t add3(int8_t a, int64_t b, uint16_t c) { return a + b + c; }\n"),
+    ];
+    let run = compile_and_run(&program_dir("c_functions_are_called", &files), &[], &["side.c"]);
+    assert_eq!(run.stdout, "4\n65536\n");
+}
+
+#[test]
+fn arithmetic_panics_on_overflow() {
+    assert_eq!(panic_message("add_overflow", "let x: u8 = 200;\nput x + 100;\n", &[]), "attempt to add with overflow");
+    assert_eq!(panic_message("sub_overflow", "let x: u32 = 1;\nput x - 2;\n", &[]), "attempt to subtract with overflow");
+    assert_eq!(panic_message("mul_overflow", "let x = 9223372036854775807;\nput x * 2;\n", &[]), "attempt to multiply with overflow");
+    assert_eq!(panic_message("neg_overflow", "let x: i8 = -128;\nput -x;\n", &[]), "attempt to negate with overflow");
+    assert_eq!(panic_message("null_deref", "let p: *i64 = 0;\nput *p;\n", &[]), "attempt to dereference a null pointer");
+}
+
+#[test]
+fn negated_literals_reach_the_minimum() {
+    let program = "put -9223372036854775808;\nput -128i8;\nlet x: i16 = -32768;\nput x;\n";
+    assert_eq!(output("negated_literals_reach_the_minimum", program), ["-9223372036854775808", "-128", "-32768"]);
+}
+
+#[test]
+fn division_panics() {
+    assert_eq!(panic_message("div_zero", "let x = 0;\nput 1 / x;\n", &["--release"]), "attempt to divide by zero");
+    assert_eq!(panic_message("rem_zero", "let x = 0;\nput 1 % x;\n", &["--release"]), "attempt to calculate the remainder with a divisor of zero");
+    assert_eq!(panic_message("div_min", "let x: i16 = -32768;\nlet y: i16 = -1;\nput x / y;\n", &["--release"]), "attempt to divide with overflow");
+}
+
+#[test]
+fn release_wraps() {
+    let run = run("release_wraps", "let x: u8 = 200;\nput x + 100;\nlet y: i8 = -128;\nput -y;\nput 1u8 << 9;\nput ~0u8;\n", &["--release"]);
+    assert_eq!((run.stdout.as_str(), run.status), ("44\n-128\n0\n255\n", 0));
+}